@@ -0,0 +1,344 @@
+//! Record/replay of HTTP interactions made through `api::requests`.
+//!
+//! A cassette in [`CassetteMode::Record`] mode performs every request against the network
+//! and appends the request/response pair to a JSON file. The same file can later be
+//! loaded in [`CassetteMode::Replay`] mode, where responses are served from the file and
+//! no network traffic happens. Cookies, CSRF and auth tokens, passwords and flow tokens are
+//! redacted with [`redact`](crate::api::redact) before anything is written to disk.
+
+use crate::api::redact::{redact_form, redact_headers, redact_json};
+use crate::error::{Result, TwitterError};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum CassetteBody {
+    Json(Value),
+    Form(Vec<(String, String)>),
+    /// Multipart bodies are opaque once built, so only their presence is recorded.
+    Multipart,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<CassetteBody>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: CassetteRequest,
+    pub response: CassetteResponse,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug)]
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    used: Vec<bool>,
+}
+
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    state: Mutex<CassetteState>,
+}
+
+impl Cassette {
+    /// Starts a new recording at `path`, overwriting any existing cassette there.
+    pub fn record(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            mode: CassetteMode::Record,
+            state: Mutex::new(CassetteState {
+                interactions: Vec::new(),
+                used: Vec::new(),
+            }),
+        }
+    }
+
+    /// Loads a previously recorded cassette for replay.
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self> {
+        let content = fs::read_to_string(path.as_ref())?;
+        let file: CassetteFile = serde_json::from_str(&content)?;
+        let used = vec![false; file.interactions.len()];
+
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            mode: CassetteMode::Replay,
+            state: Mutex::new(CassetteState {
+                interactions: file.interactions,
+                used,
+            }),
+        })
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state.lock().unwrap().interactions.clone()
+    }
+
+    /// Appends an interaction and rewrites the cassette file, so a recording survives
+    /// a session that ends early.
+    pub(crate) fn record_interaction(
+        &self,
+        request: CassetteRequest,
+        response: CassetteResponse,
+    ) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.interactions.push(Interaction { request, response });
+        state.used.push(true);
+
+        let file = CassetteFile {
+            interactions: state.interactions.clone(),
+        };
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+
+    /// Finds the response recorded for `request`. Matching interactions are served in
+    /// recording order; once all are used, the last one keeps being served.
+    pub(crate) fn find(&self, request: &CassetteRequest) -> Result<CassetteResponse> {
+        let key = match_key(request);
+        let mut state = self.state.lock().unwrap();
+
+        let matches: Vec<usize> = state
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| match_key(&interaction.request) == key)
+            .map(|(index, _)| index)
+            .collect();
+
+        let index = matches
+            .iter()
+            .copied()
+            .find(|&index| !state.used[index])
+            .or_else(|| matches.last().copied())
+            .ok_or_else(|| {
+                TwitterError::Api(format!(
+                    "No cassette interaction recorded for {} {}",
+                    request.method, request.url
+                ))
+            })?;
+
+        state.used[index] = true;
+        Ok(state.interactions[index].response.clone())
+    }
+}
+
+impl CassetteRequest {
+    pub(crate) fn new(request: &Request, body: Option<CassetteBody>) -> Self {
+        Self {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers: redact_headers(request.headers()),
+            body: body.map(redact_body),
+        }
+    }
+}
+
+impl CassetteResponse {
+    pub(crate) fn new(status: StatusCode, headers: &HeaderMap, body: &str) -> Self {
+        let body = match serde_json::from_str::<Value>(body) {
+            Ok(mut value) => {
+                redact_json(&mut value);
+                value.to_string()
+            }
+            Err(_) => body.to_string(),
+        };
+        Self {
+            status: status.as_u16(),
+            headers: redact_headers(headers),
+            body,
+        }
+    }
+
+    pub(crate) fn into_parts(self) -> Result<(StatusCode, HeaderMap, String)> {
+        let status = StatusCode::from_u16(self.status)
+            .map_err(|e| TwitterError::InvalidResponse(e.to_string()))?;
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+
+        Ok((status, headers, self.body))
+    }
+}
+
+pub(crate) fn redact_body(body: CassetteBody) -> CassetteBody {
+    match body {
+        CassetteBody::Json(mut value) => {
+            redact_json(&mut value);
+            CassetteBody::Json(value)
+        }
+        CassetteBody::Form(mut fields) => {
+            redact_form(&mut fields);
+            CassetteBody::Form(fields)
+        }
+        other => other,
+    }
+}
+
+/// Key used to match a live request against recorded ones: the method, the URL path and
+/// its query sorted with JSON-valued parameters canonicalised, and the body. The host is
+/// ignored so a cassette recorded against x.com can be replayed against any `Hosts`.
+fn match_key(request: &CassetteRequest) -> (String, String, Option<String>) {
+    let url = match Url::parse(&request.url) {
+        Ok(url) => {
+            let mut query: Vec<(String, String)> = url
+                .query_pairs()
+                .map(|(key, value)| (key.into_owned(), canonical_json(&value)))
+                .collect();
+            query.sort();
+            let query = query
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<_>>()
+                .join("&");
+            format!("{}?{}", url.path(), query)
+        }
+        Err(_) => request.url.clone(),
+    };
+
+    let body = match &request.body {
+        Some(CassetteBody::Json(value)) => Some(value.to_string()),
+        Some(CassetteBody::Form(pairs)) => {
+            let mut pairs = pairs.clone();
+            pairs.sort();
+            Some(format!("{:?}", pairs))
+        }
+        Some(CassetteBody::Multipart) | None => None,
+    };
+
+    (request.method.to_uppercase(), url, body)
+}
+
+fn canonical_json(value: &str) -> String {
+    serde_json::from_str::<Value>(value)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|_| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::oauth2::AuthorizationRequest;
+    use crate::mock::{MockServer, MOCK_ACCESS_TOKEN, MOCK_AUTH_TOKEN, MOCK_CT0};
+
+    #[test]
+    fn test_match_key_ignores_host_and_query_order() {
+        let request = |url: &str| CassetteRequest {
+            method: "GET".into(),
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+        };
+
+        let recorded = request(
+            "https://x.com/i/api/graphql/q/UserTweets?variables=%7B%22a%22%3A1%2C%22b%22%3A2%7D&features=%7B%7D",
+        );
+        let live = request(
+            "http://127.0.0.1:9000/i/api/graphql/q/UserTweets?features=%7B%7D&variables=%7B%22b%22%3A2%2C%22a%22%3A1%7D",
+        );
+
+        assert_eq!(match_key(&recorded), match_key(&live));
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let path = std::env::temp_dir().join(format!("cassette-{}.json", std::process::id()));
+
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper.set_cassette(Cassette::record(&path));
+        let recorded = scraper.get_profile("mockuser").await.unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains(MOCK_AUTH_TOKEN));
+        assert!(!content.contains(MOCK_CT0));
+
+        // Replaying never reaches the server, so requests made now are not recorded by it.
+        let requests_before = server.requests().len();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper.set_cassette(Cassette::replay(&path).unwrap());
+        let replayed = scraper.get_profile("mockuser").await.unwrap();
+
+        assert_eq!(replayed.id, recorded.id);
+        assert_eq!(server.requests().len(), requests_before);
+        assert!(scraper.get_profile("someone_else").await.is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recorded_responses_are_redacted() {
+        let path = std::env::temp_dir().join(format!("cassette-login-{}.json", std::process::id()));
+
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.guest_scraper().await.unwrap();
+        scraper.set_cassette(Cassette::record(&path));
+        scraper
+            .login("mockuser".into(), "password".into(), None, None)
+            .await
+            .unwrap();
+        AuthorizationRequest::new("client", "http://localhost/cb", &["tweet.read"])
+            .exchange_code(&scraper.twitter_client, "the-code", None)
+            .await
+            .unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(content.contains("LoginEnterPassword"));
+        assert!(!content.contains("g;mock:login"));
+        assert!(!content.contains(MOCK_ACCESS_TOKEN));
+        assert!(!content.contains("mock_refresh_token"));
+        assert!(!content.contains("the-code"));
+    }
+}
@@ -1,23 +1,284 @@
+use crate::api::cassette::Cassette;
+use crate::api::endpoints::{ApiEndpoint, OperationRegistry};
+use crate::api::headers::HeaderProfile;
+use crate::api::hosts::Hosts;
+use crate::api::interceptor::Interceptor;
+use crate::api::proxy::{ProxyConfig, ProxyRotation};
+use crate::api::rate_limit::{RateLimitInfo, RateLimits};
+use crate::api::requests;
+use crate::api::retry::RetryPolicy;
+use crate::auth::config::AuthConfig;
+use crate::auth::relogin::AutoRelogin;
 use crate::auth::user_auth::TwitterAuth;
 use crate::error::{Result, TwitterError};
 use crate::models::Tweet;
-use reqwest::{Client, Method};
+use reqwest::header::HeaderMap;
+use reqwest::{Client, ClientBuilder, Method};
 use serde::de::DeserializeOwned;
+use std::sync::Arc;
 use std::time::Duration;
+use url::Url;
 
 pub struct TwitterClient {
     pub client: Client,
     pub auth: Box<dyn TwitterAuth + Send + Sync>,
+    pub hosts: Hosts,
+    pub cassette: Option<Arc<Cassette>>,
+    pub rate_limits: RateLimits,
+    /// Longest time a request may sleep waiting for its rate limit to reset; `None` fails
+    /// with `TwitterError::RateLimit` instead of waiting.
+    pub rate_limit_wait: Option<Duration>,
+    pub retry_policy: RetryPolicy,
+    pub operations: OperationRegistry,
+    /// Refreshes `operations` from the web client bundle when a GraphQL request fails
+    /// with 404, then retries it once with the new query ID.
+    pub operation_discovery: bool,
+    /// Logs in again and replays a request once when X rejects the session.
+    pub auto_relogin: Option<Arc<AutoRelogin>>,
+    /// Proxies requests are sent through instead of `client`'s direct connection.
+    pub proxies: Option<Arc<ProxyRotation>>,
+    /// Run, in order, for every request sent and response received.
+    pub interceptors: Vec<Arc<dyn Interceptor>>,
+    /// The settings this client was built with, reused for proxied connections and when
+    /// a scraper rebuilds its client after logging in.
+    pub(crate) builder: TwitterClientBuilder,
+}
+
+/// How the HTTP version is chosen for each connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HttpVersion {
+    /// HTTP/2 when the server offers it during the TLS handshake, HTTP/1.1 otherwise.
+    #[default]
+    Auto,
+    Http1Only,
+    /// HTTP/2 without negotiation, for servers known to support it.
+    Http2PriorKnowledge,
+}
+
+/// Connection and header settings for a [`TwitterClient`].
+#[derive(Debug, Clone)]
+pub struct TwitterClientBuilder {
+    hosts: Hosts,
+    read_timeout: Duration,
+    connect_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout: Option<Duration>,
+    http_version: HttpVersion,
+    header_profile: HeaderProfile,
+}
+
+impl Default for TwitterClientBuilder {
+    fn default() -> Self {
+        Self {
+            hosts: Hosts::default(),
+            read_timeout: Duration::from_secs(30),
+            connect_timeout: None,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+            http_version: HttpVersion::default(),
+            header_profile: HeaderProfile::default(),
+        }
+    }
+}
+
+impl TwitterClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_hosts(mut self, hosts: Hosts) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
+    /// Longest a request may take from being sent until its response is read in full.
+    /// Defaults to 30 seconds.
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Most idle connections kept open to each host.
+    pub fn with_pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// How long an idle connection is kept open.
+    pub fn with_pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_http_version(mut self, http_version: HttpVersion) -> Self {
+        self.http_version = http_version;
+        self
+    }
+
+    /// The browser, platform and languages requests present themselves with. Defaults to
+    /// desktop Chrome in US English.
+    pub fn with_header_profile(mut self, header_profile: HeaderProfile) -> Self {
+        self.header_profile = header_profile;
+        self
+    }
+
+    pub fn header_profile(&self) -> &HeaderProfile {
+        &self.header_profile
+    }
+
+    pub fn build(self, auth: Box<dyn TwitterAuth + Send + Sync>) -> Result<TwitterClient> {
+        Ok(TwitterClient {
+            client: self.http_client_builder().build()?,
+            auth,
+            hosts: self.hosts.clone(),
+            cassette: None,
+            rate_limits: RateLimits::default(),
+            rate_limit_wait: None,
+            retry_policy: RetryPolicy::default(),
+            operations: OperationRegistry::default(),
+            operation_discovery: false,
+            auto_relogin: None,
+            proxies: None,
+            interceptors: Vec::new(),
+            builder: self,
+        })
+    }
+
+    /// A reqwest builder with these settings, for the direct client and every proxy's.
+    pub(crate) fn http_client_builder(&self) -> ClientBuilder {
+        let mut builder = Client::builder()
+            .timeout(self.read_timeout)
+            .cookie_store(true);
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        match self.http_version {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1Only => builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+        }
+    }
 }
 
 impl TwitterClient {
     pub fn new(auth: Box<dyn TwitterAuth + Send + Sync>) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .cookie_store(true)
-            .build()?;
+        TwitterClientBuilder::default().build(auth)
+    }
+
+    pub fn builder() -> TwitterClientBuilder {
+        TwitterClientBuilder::default()
+    }
+
+    pub fn with_hosts(mut self, hosts: Hosts) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
+    /// Records or replays every request made through `api::requests` using `cassette`.
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(Arc::new(cassette));
+        self
+    }
 
-        Ok(Self { client, auth })
+    /// Waits up to `max_wait` for a rate limit to reset instead of failing.
+    pub fn with_rate_limit_wait(mut self, max_wait: Duration) -> Self {
+        self.rate_limit_wait = Some(max_wait);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Shares `operations` with this client, e.g. to use one set of query ID overrides
+    /// for several accounts.
+    pub fn with_operations(mut self, operations: OperationRegistry) -> Self {
+        self.operations = operations;
+        self
+    }
+
+    pub fn with_operation_discovery(mut self, enabled: bool) -> Self {
+        self.operation_discovery = enabled;
+        self
+    }
+
+    /// Logs in again with `config`'s credentials when a request fails because the session
+    /// was rejected (HTTP 401, error codes 32, 89 or 239), then replays the request once.
+    pub fn with_auto_relogin(mut self, config: AuthConfig) -> Self {
+        self.auto_relogin = Some(Arc::new(AutoRelogin::new(config)));
+        self
+    }
+
+    /// Sends every request, including the login, through `proxy`.
+    pub fn with_proxy(self, proxy: ProxyConfig) -> Result<Self> {
+        self.with_proxies(vec![proxy], "")
+    }
+
+    /// Sends every request through one of `proxies`, starting with the one assigned to
+    /// `account` and moving to the next when it cannot be reached.
+    pub fn with_proxies(mut self, proxies: Vec<ProxyConfig>, account: &str) -> Result<Self> {
+        self.proxies = Some(Arc::new(ProxyRotation::new(
+            proxies,
+            account,
+            self.builder.clone(),
+        )?));
+        Ok(self)
+    }
+
+    /// Adds `interceptor` after the ones already added.
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn Interceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// The proxy requests are currently sent through.
+    pub fn current_proxy(&self) -> Option<ProxyConfig> {
+        self.proxies.as_ref().map(|proxies| proxies.current().clone())
+    }
+
+    pub fn header_profile(&self) -> &HeaderProfile {
+        self.builder.header_profile()
+    }
+
+    /// Sets the browser headers of a request about to be sent. Official API requests
+    /// are left as they are.
+    pub(crate) fn apply_header_profile(&self, headers: &mut HeaderMap) {
+        if !self.auth.is_official_api() {
+            self.builder.header_profile.apply(headers);
+        }
+    }
+
+    /// The client to send a request with and, when proxied, the index of its proxy.
+    pub(crate) fn http_client(&self) -> (Option<usize>, Client) {
+        match &self.proxies {
+            Some(proxies) => {
+                let (index, client) = proxies.client();
+                (Some(index), client)
+            }
+            None => (None, self.client.clone()),
+        }
+    }
+
+    /// Starts a request for the GraphQL operation `name` as currently registered.
+    pub fn graphql_endpoint(&self, name: &str) -> ApiEndpoint {
+        self.operations.endpoint(&self.hosts, name)
+    }
+
+    /// Latest budget reported for `endpoint`, a GraphQL operation name or REST path.
+    pub fn rate_limit(&self, endpoint: &str) -> Option<RateLimitInfo> {
+        self.rate_limits.get(endpoint)
     }
 
     pub async fn send_tweet(&self, text: &str, media_ids: Option<Vec<String>>) -> Result<Tweet> {
@@ -29,17 +290,17 @@ impl TwitterClient {
             params["media"] = serde_json::json!({ "media_ids": ids });
         }
 
-        let endpoint = "https://api.x.com/2/tweets";
-        self.post(endpoint, Some(params)).await
+        let endpoint = self.hosts.api_url("2/tweets");
+        self.post(&endpoint, Some(params)).await
     }
 
     pub async fn get_tweet(&self, tweet_id: &str) -> Result<Tweet> {
-        let endpoint = format!("https://api.x.com/2/tweets/{}", tweet_id);
+        let endpoint = self.hosts.api_url(&format!("2/tweets/{}", tweet_id));
         self.get(&endpoint).await
     }
 
     pub async fn get_user_tweets(&self, user_id: &str, limit: usize) -> Result<Vec<Tweet>> {
-        let endpoint = format!("https://api.x.com/2/users/{}/tweets", user_id);
+        let endpoint = self.hosts.api_url(&format!("2/users/{}/tweets", user_id));
         let params = serde_json::json!({
             "max_results": limit,
             "tweet.fields": "created_at,author_id,conversation_id,public_metrics"
@@ -67,31 +328,120 @@ impl TwitterClient {
         self.request(Method::POST, endpoint, params).await
     }
 
+    /// Sends a request through the same pipeline as every other request: session headers,
+    /// cookie capture, retries, rate-limit tracking and error mapping. `params` go in the
+    /// query string of a GET and in the JSON body otherwise.
     pub async fn request<T: DeserializeOwned>(
         &self,
         method: Method,
         endpoint: &str,
         params: Option<serde_json::Value>,
     ) -> Result<T> {
-        let mut headers = reqwest::header::HeaderMap::new();
+        let mut headers = HeaderMap::new();
         self.auth.install_headers(&mut headers).await?;
 
-        let mut request = self.client.request(method, endpoint);
-        request = request.headers(headers);
+        let (url, body) = if method == Method::GET {
+            (with_query(endpoint, params)?, None)
+        } else {
+            (endpoint.to_string(), params)
+        };
+        let (response, _) = requests::request_api(self, &url, headers, method, body).await?;
+        Ok(response)
+    }
+}
+
+/// `endpoint` with the fields of `params` appended as query parameters; strings are sent
+/// as is and other values as JSON.
+fn with_query(endpoint: &str, params: Option<serde_json::Value>) -> Result<String> {
+    let Some(serde_json::Value::Object(params)) = params else {
+        return Ok(endpoint.to_string());
+    };
+    let mut url = Url::parse(endpoint)
+        .map_err(|e| TwitterError::Config(format!("Invalid URL {}: {}", endpoint, e)))?;
+    for (name, value) in params {
+        match value {
+            serde_json::Value::String(value) => url.query_pairs_mut().append_pair(&name, &value),
+            value => url.query_pairs_mut().append_pair(&name, &value.to_string()),
+        };
+    }
+    Ok(url.to_string())
+}
 
-        if let Some(params) = params {
-            request = request.json(&params);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::headers::{Browser, Platform};
+    use crate::mock::{MockResponse, MockServer};
+    use crate::scraper::Scraper;
+    use serde_json::json;
 
-        let response = request.send().await?;
+    #[tokio::test]
+    async fn test_header_profile_is_kept_after_login() {
+        let server = MockServer::start().await.unwrap();
+        let builder = TwitterClient::builder()
+            .with_hosts(server.hosts())
+            .with_connect_timeout(Duration::from_secs(5))
+            .with_header_profile(
+                HeaderProfile::new(Browser::Firefox, Platform::Mobile)
+                    .with_accept_language("de-DE,de;q=0.9"),
+            );
+        let mut scraper = Scraper::with_client_builder(builder).await.unwrap();
+        scraper
+            .login("mockuser".into(), "password".into(), None, None)
+            .await
+            .unwrap();
+        scraper.get_profile("mockuser").await.unwrap();
 
-        if response.status().is_success() {
-            Ok(response.json().await?)
-        } else {
-            Err(TwitterError::Api(format!(
-                "Request failed with status: {}",
-                response.status()
-            )))
+        let requests = server.requests();
+        assert!(requests.len() > 1);
+        for request in requests {
+            assert!(request.header("user-agent").unwrap().contains("Android 14; Mobile"));
+            assert_eq!(request.header("accept-language"), Some("de-DE,de;q=0.9"));
+            assert_eq!(request.header("x-twitter-client-language"), Some("de"));
+            assert_eq!(request.header("sec-ch-ua"), None);
         }
     }
+
+    #[tokio::test]
+    async fn test_request_shares_the_request_pipeline() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper.set_retry_policy(
+            RetryPolicy::default().with_backoff(Duration::ZERO, Duration::ZERO),
+        );
+        let client = &scraper.twitter_client;
+        let url = client.hosts.api_url("2/users/1000/tweets");
+        server.mock_once("/2/users/1000/tweets", MockResponse::json(503, json!({})));
+        server.mock_once(
+            "/2/users/1000/tweets",
+            MockResponse::json(200, json!({"data": []}))
+                .with_header("x-rate-limit-limit", "900")
+                .with_header("x-rate-limit-remaining", "899")
+                .with_header("x-rate-limit-reset", "1735689600")
+                .with_header("set-cookie", "ct0=rotated; Path=/; Secure"),
+        );
+
+        let _: serde_json::Value = client
+            .get_with_params(&url, Some(json!({"max_results": 5, "expansions": "author_id"})))
+            .await
+            .unwrap();
+
+        let requests = server.requests_to("/2/users/1000/tweets");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].query_param("max_results"), Some("5"));
+        assert_eq!(requests[1].query_param("expansions"), Some("author_id"));
+        assert_eq!(client.rate_limit("/2/users/1000/tweets").unwrap().remaining, 899);
+
+        server.mock_once(
+            "/2/users/1000/tweets",
+            MockResponse::json(
+                404,
+                json!({"errors": [{"code": 50, "message": "User not found."}]}),
+            ),
+        );
+        let error = client.get::<serde_json::Value>(&url).await.unwrap_err();
+        assert_eq!(error.api_error().unwrap().errors[0].code, Some(50));
+        let requests = server.requests_to("/2/users/1000/tweets");
+        assert_eq!(requests.last().unwrap().header("x-csrf-token"), Some("rotated"));
+    }
 }
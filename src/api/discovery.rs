@@ -0,0 +1,224 @@
+//! Discovery of current GraphQL query IDs from the x.com web client bundle.
+//!
+//! The web client ships every GraphQL operation it uses inside its main JS bundle as
+//! `{queryId:"…",operationName:"…",operationType:"…",metadata:{featureSwitches:[…]}}`.
+//! Parsing those entries lets an [`OperationRegistry`] catch up with rotated query IDs
+//! without a new release of this crate.
+
+use crate::api::client::TwitterClient;
+use crate::api::endpoints::OperationRegistry;
+use crate::api::requests::request_text;
+use crate::error::{Result, TwitterError};
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use std::fs;
+use std::path::Path;
+use url::Url;
+
+lazy_static! {
+    static ref RE_OPERATION: Regex = Regex::new(
+        r#"queryId:"([^"]+)",operationName:"([^"]+)",operationType:"([^"]+)",metadata:\{featureSwitches:\[([^\]]*)\](?:,fieldToggles:\[([^\]]*)\])?"#
+    )
+    .unwrap();
+    static ref RE_MAIN_BUNDLE: Regex =
+        Regex::new(r#"src="([^"]*/client-web[^"]*/main\.[0-9A-Za-z]+\.js)""#).unwrap();
+}
+
+/// One operation entry found in the bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredOperation {
+    pub query_id: String,
+    pub operation_name: String,
+    /// `query` or `mutation`.
+    pub operation_type: String,
+    pub feature_switches: Vec<String>,
+    pub field_toggles: Vec<String>,
+}
+
+/// Extracts every operation entry from the contents of a bundle.
+pub fn parse_bundle(js: &str) -> Vec<DiscoveredOperation> {
+    RE_OPERATION
+        .captures_iter(js)
+        .map(|captures| DiscoveredOperation {
+            query_id: captures[1].to_string(),
+            operation_name: captures[2].to_string(),
+            operation_type: captures[3].to_string(),
+            feature_switches: parse_string_list(&captures[4]),
+            field_toggles: captures
+                .get(5)
+                .map(|list| parse_string_list(list.as_str()))
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+fn parse_string_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|item| item.trim().trim_matches('"'))
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Finds the URL of the main bundle in the x.com page, resolved against `page_url`.
+pub fn find_main_bundle_url(html: &str, page_url: &str) -> Option<String> {
+    let src = RE_MAIN_BUNDLE.captures(html)?.get(1)?.as_str();
+    let url = Url::parse(page_url).ok()?.join(src).ok()?;
+    Some(url.to_string())
+}
+
+/// Downloads the x.com page from `client.hosts.web` and the main bundle it links.
+pub async fn fetch_bundle(client: &TwitterClient) -> Result<String> {
+    let page_url = client.hosts.web_url("");
+    let html = request_text(client, &page_url, HeaderMap::new()).await?;
+    let bundle_url = find_main_bundle_url(&html, &page_url).ok_or_else(|| {
+        TwitterError::InvalidResponse("No main bundle script found in the x.com page".into())
+    })?;
+
+    request_text(client, &bundle_url, HeaderMap::new()).await
+}
+
+/// Refreshes `client.operations` from the live bundle. Returns the names of the
+/// operations whose query ID changed.
+pub async fn refresh_operations(client: &TwitterClient) -> Result<Vec<String>> {
+    let bundle = fetch_bundle(client).await?;
+    refresh_from_bundle(&client.operations, &bundle)
+}
+
+/// Refreshes `registry` from a locally saved copy of the bundle.
+pub fn refresh_from_file(
+    registry: &OperationRegistry,
+    path: impl AsRef<Path>,
+) -> Result<Vec<String>> {
+    let bundle = fs::read_to_string(path)?;
+    refresh_from_bundle(registry, &bundle)
+}
+
+fn refresh_from_bundle(registry: &OperationRegistry, bundle: &str) -> Result<Vec<String>> {
+    let operations = parse_bundle(bundle);
+    if operations.is_empty() {
+        return Err(TwitterError::InvalidResponse(
+            "No GraphQL operations found in the bundle".into(),
+        ));
+    }
+    Ok(registry.update_from_discovered(&operations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::hosts::Hosts;
+    use crate::mock::{MockResponse, MockServer};
+
+    const BUNDLE: &str = include_str!("../mock/fixtures/main_bundle.js");
+
+    #[test]
+    fn test_parse_bundle() {
+        let operations = parse_bundle(BUNDLE);
+        let user_tweets = operations
+            .iter()
+            .find(|operation| operation.operation_name == "UserTweets")
+            .unwrap();
+
+        assert_eq!(user_tweets.query_id, "mockUserTweetsQueryId");
+        assert_eq!(user_tweets.operation_type, "query");
+        assert_eq!(
+            user_tweets.feature_switches[..2],
+            [
+                "rweb_tipjar_consumption_enabled",
+                "responsive_web_graphql_exclude_directive_enabled"
+            ]
+        );
+        assert_eq!(user_tweets.field_toggles, ["withArticlePlainText"]);
+
+        let favorite = operations
+            .iter()
+            .find(|operation| operation.operation_name == "FavoriteTweet")
+            .unwrap();
+        assert_eq!(favorite.operation_type, "mutation");
+        assert!(favorite.feature_switches.is_empty());
+    }
+
+    #[test]
+    fn test_find_main_bundle_url() {
+        let html = r#"<link rel="preload" as="script" crossorigin="anonymous" href="https://abs.twimg.com/responsive-web/client-web/vendor.f5fc1a4a.js" /><script type="text/javascript" charset="utf-8" nonce="abc" crossorigin="anonymous" src="https://abs.twimg.com/responsive-web/client-web/main.0c8fa5ba.js"></script>"#;
+        assert_eq!(
+            find_main_bundle_url(html, "https://x.com/").as_deref(),
+            Some("https://abs.twimg.com/responsive-web/client-web/main.0c8fa5ba.js")
+        );
+
+        let html = r#"<script src="/responsive-web/client-web/main.mock.js"></script>"#;
+        assert_eq!(
+            find_main_bundle_url(html, "http://127.0.0.1:8080/").as_deref(),
+            Some("http://127.0.0.1:8080/responsive-web/client-web/main.mock.js")
+        );
+    }
+
+    #[test]
+    fn test_refresh_from_saved_bundle() {
+        let path = std::env::temp_dir().join(format!("main-{}.js", std::process::id()));
+        fs::write(&path, BUNDLE).unwrap();
+
+        let registry = OperationRegistry::default();
+        let mut updated = refresh_from_file(&registry, &path).unwrap();
+        updated.sort();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(updated, ["UserByScreenName", "UserTweets"]);
+        assert_eq!(
+            registry.query_id("UserTweets").as_deref(),
+            Some("mockUserTweetsQueryId")
+        );
+
+        let endpoint = registry.endpoint(&Hosts::default(), "UserTweets");
+        let features = endpoint.features.unwrap();
+        // Known switches keep their default, new ones are sent disabled and switches the
+        // bundle no longer lists are dropped.
+        assert_eq!(features.get("rweb_tipjar_consumption_enabled"), Some(true));
+        assert_eq!(
+            features.get("responsive_web_grok_share_attachment_enabled"),
+            Some(false)
+        );
+        assert!(!features.contains("tweetypie_unmention_optimization_enabled"));
+    }
+
+    #[test]
+    fn test_operation_without_switches_keeps_features() {
+        let registry = OperationRegistry::default();
+        let before = registry.endpoint(&Hosts::default(), "UserTweets").features;
+
+        let updated = registry.update_from_discovered(&[DiscoveredOperation {
+            query_id: "newUserTweetsQueryId".into(),
+            operation_name: "UserTweets".into(),
+            operation_type: "query".into(),
+            feature_switches: Vec::new(),
+            field_toggles: Vec::new(),
+        }]);
+
+        assert_eq!(updated, ["UserTweets"]);
+        let endpoint = registry.endpoint(&Hosts::default(), "UserTweets");
+        assert!(endpoint.url.contains("newUserTweetsQueryId"));
+        assert!(before.is_some());
+        assert_eq!(endpoint.features, before);
+    }
+
+    #[tokio::test]
+    async fn test_stale_query_id_is_refreshed_on_404() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper.set_operation_discovery(true);
+
+        server.mock_once("UserByScreenName", MockResponse::text(404, ""));
+        let profile = scraper.get_profile("mockuser").await.unwrap();
+        assert_eq!(profile.username, "mockuser");
+
+        let requests = server.requests_to("UserByScreenName");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[1].path,
+            "/i/api/graphql/mockUserByScreenNameQueryId/UserByScreenName"
+        );
+        assert_eq!(server.requests_to("/").len(), 1);
+    }
+}
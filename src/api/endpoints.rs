@@ -1,4 +1,15 @@
+use crate::api::client::TwitterClient;
+use crate::api::discovery::DiscoveredOperation;
+use crate::api::features::{FeaturePreset, FeatureSet};
+use crate::api::hosts::Hosts;
+use crate::error::{Result, TwitterError};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
 use urlencoding;
 
 // Constants for default options matching TypeScript
@@ -36,332 +47,635 @@ pub const DEFAULT_TWEET_FIELDS: &[&str] = &[
     "note_tweet",
 ];
 
+/// A GraphQL request: the operation URL plus the `variables`, `features` and
+/// `fieldToggles` objects, which go in the query string for GET and in the JSON body
+/// for POST.
 #[derive(Debug, Clone)]
 pub struct ApiEndpoint {
     pub url: String,
-    pub variables: Option<HashMap<String, serde_json::Value>>,
-    pub features: Option<HashMap<String, bool>>,
-    pub field_toggles: Option<HashMap<String, bool>>,
+    pub method: Method,
+    /// Name of the registered operation the endpoint was built from.
+    pub operation: Option<String>,
+    pub variables: Option<Value>,
+    pub features: Option<FeatureSet>,
+    pub field_toggles: Option<Value>,
 }
 
 impl ApiEndpoint {
-    pub fn to_request_url(&self) -> String {
-        let mut params = Vec::new();
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new(Method::GET, url)
+    }
 
-        if let Some(variables) = &self.variables {
-            params.push(format!(
-                "variables={}",
-                urlencoding::encode(&serde_json::to_string(&variables).unwrap())
-            ));
-        }
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new(Method::POST, url)
+    }
 
-        if let Some(features) = &self.features {
-            params.push(format!(
-                "features={}",
-                urlencoding::encode(&serde_json::to_string(&features).unwrap())
-            ));
+    fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            method,
+            operation: None,
+            variables: None,
+            features: None,
+            field_toggles: None,
         }
+    }
+
+    pub fn with_variables(mut self, variables: Value) -> Self {
+        self.variables = Some(variables);
+        self
+    }
 
-        if let Some(toggles) = &self.field_toggles {
-            params.push(format!(
-                "fieldToggles={}",
-                urlencoding::encode(&serde_json::to_string(&toggles).unwrap())
-            ));
+    pub fn with_features(mut self, features: FeatureSet) -> Self {
+        self.features = Some(features);
+        self
+    }
+
+    /// Sets a single feature switch for this request, on top of the operation's defaults.
+    pub fn with_feature(mut self, name: &str, enabled: bool) -> Self {
+        self.features
+            .get_or_insert_with(FeatureSet::new)
+            .set(name, enabled);
+        self
+    }
+
+    /// Sets `features` on top of the operation's defaults, keeping the other switches.
+    pub fn with_merged_features(mut self, features: &FeatureSet) -> Self {
+        self.features
+            .get_or_insert_with(FeatureSet::new)
+            .merge(features);
+        self
+    }
+
+    pub fn with_field_toggles(mut self, field_toggles: Value) -> Self {
+        self.field_toggles = Some(field_toggles);
+        self
+    }
+
+    fn params(&self) -> Vec<(&'static str, Value)> {
+        [
+            ("variables", self.variables.clone()),
+            ("features", self.features.as_ref().map(FeatureSet::to_value)),
+            ("fieldToggles", self.field_toggles.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .collect()
+    }
+
+    /// The URL to request; for GET it carries the parameters in the query string.
+    pub fn to_request_url(&self) -> String {
+        if self.method != Method::GET {
+            return self.url.clone();
         }
 
+        let params = self
+            .params()
+            .into_iter()
+            .map(|(name, value)| format!("{}={}", name, urlencoding::encode(&value.to_string())))
+            .collect::<Vec<_>>();
+
         if params.is_empty() {
             self.url.clone()
         } else {
             format!("{}?{}", self.url, params.join("&"))
         }
     }
+
+    /// The JSON body to send; only requests other than GET have one.
+    pub fn to_request_body(&self) -> Option<Value> {
+        if self.method == Method::GET {
+            return None;
+        }
+
+        let body = self
+            .params()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect::<serde_json::Map<_, _>>();
+        Some(Value::Object(body))
+    }
 }
 
-pub struct Endpoints;
+/// A GraphQL operation as the web client calls it: `<query id>/<name>` plus the features
+/// it sends when a call site does not choose its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphqlOperation {
+    pub name: String,
+    pub query_id: String,
+    pub method: Method,
+    pub features: Option<FeatureSet>,
+}
 
-impl Endpoints {
-    pub fn tweet_detail(tweet_id: &str) -> ApiEndpoint {
-        ApiEndpoint {
-            url: "https://twitter.com/i/api/graphql/xOhkmRac04YFZmOzU9PJHg/TweetDetail".to_string(),
-            variables: Some(HashMap::from([
-                ("focalTweetId".to_string(), tweet_id.into()),
-                ("with_rux_injections".to_string(), false.into()),
-                ("includePromotedContent".to_string(), true.into()),
-                ("withCommunity".to_string(), true.into()),
-                (
-                    "withQuickPromoteEligibilityTweetFields".to_string(),
-                    true.into(),
-                ),
-                ("withBirdwatchNotes".to_string(), true.into()),
-                ("withVoice".to_string(), true.into()),
-                ("withV2Timeline".to_string(), true.into()),
-            ])),
-            features: Some(HashMap::from([
-                (
-                    "responsive_web_graphql_exclude_directive_enabled".to_string(),
-                    true,
-                ),
-                ("verified_phone_label_enabled".to_string(), false),
-                (
-                    "creator_subscriptions_tweet_preview_api_enabled".to_string(),
-                    true,
-                ),
-                (
-                    "responsive_web_graphql_timeline_navigation_enabled".to_string(),
-                    true,
-                ),
-                (
-                    "responsive_web_graphql_skip_user_profile_image_extensions_enabled".to_string(),
-                    false,
-                ),
-                ("tweetypie_unmention_optimization_enabled".to_string(), true),
-                ("responsive_web_edit_tweet_api_enabled".to_string(), true),
-                (
-                    "graphql_is_translatable_rweb_tweet_is_translatable_enabled".to_string(),
-                    true,
-                ),
-                ("view_counts_everywhere_api_enabled".to_string(), true),
-                ("longform_notetweets_consumption_enabled".to_string(), true),
-                ("tweet_awards_web_tipping_enabled".to_string(), false),
-                (
-                    "freedom_of_speech_not_reach_fetch_enabled".to_string(),
-                    true,
-                ),
-                ("standardized_nudges_misinfo".to_string(), true),
-                (
-                    "responsive_web_twitter_article_tweet_consumption_enabled".to_string(),
-                    false,
-                ),
-                (
-                    "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled"
-                        .to_string(),
-                    true,
-                ),
-                (
-                    "longform_notetweets_rich_text_read_enabled".to_string(),
-                    true,
-                ),
-                ("longform_notetweets_inline_media_enabled".to_string(), true),
-                (
-                    "responsive_web_media_download_video_enabled".to_string(),
-                    false,
-                ),
-                ("responsive_web_enhance_cards_enabled".to_string(), false),
-            ])),
-            field_toggles: Some(HashMap::from([(
-                "withArticleRichContentState".to_string(),
-                false,
-            )])),
+impl GraphqlOperation {
+    fn new(name: &str, query_id: &str, method: Method) -> Self {
+        Self {
+            name: name.to_string(),
+            query_id: query_id.to_string(),
+            method,
+            features: FeaturePreset::for_operation(name).map(FeaturePreset::features),
         }
     }
 
-    pub fn tweet_by_rest_id(tweet_id: &str) -> ApiEndpoint {
-        ApiEndpoint {
-            url: "https://twitter.com/i/api/graphql/DJS3BdhUhcaEpZ7B7irJDg/TweetResultByRestId"
-                .to_string(),
-            variables: Some(HashMap::from([
-                ("tweetId".to_string(), tweet_id.into()),
-                ("withCommunity".to_string(), false.into()),
-                ("includePromotedContent".to_string(), false.into()),
-                ("withVoice".to_string(), false.into()),
-            ])),
-            features: Some(HashMap::from([
-                (
-                    "creator_subscriptions_tweet_preview_api_enabled".to_string(),
-                    true,
-                ),
-                ("tweetypie_unmention_optimization_enabled".to_string(), true),
-                ("responsive_web_edit_tweet_api_enabled".to_string(), true),
-                (
-                    "graphql_is_translatable_rweb_tweet_is_translatable_enabled".to_string(),
-                    true,
-                ),
-                ("view_counts_everywhere_api_enabled".to_string(), true),
-                ("longform_notetweets_consumption_enabled".to_string(), true),
-                (
-                    "responsive_web_twitter_article_tweet_consumption_enabled".to_string(),
-                    false,
-                ),
-                ("tweet_awards_web_tipping_enabled".to_string(), false),
-                (
-                    "freedom_of_speech_not_reach_fetch_enabled".to_string(),
-                    true,
-                ),
-                ("standardized_nudges_misinfo".to_string(), true),
-            ])),
-            field_toggles: None,
+    /// Path of the operation below the GraphQL root, e.g. `abc/CreateTweet`.
+    pub fn path(&self) -> String {
+        format!("{}/{}", self.query_id, self.name)
+    }
+}
+
+/// Runtime patch for one operation. `features` entries are merged into the default
+/// features, so a single flag can be flipped without repeating the whole set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperationOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<FeatureSet>,
+}
+
+/// GraphQL operations known to the client, keyed by operation name. Clones share the same
+/// operations, so overrides survive the clients a `Scraper` rebuilds on login and one
+/// registry can be passed to several clients with [`TwitterClient::with_operations`].
+///
+/// X rotates query IDs regularly; [`OperationRegistry::load_file`] patches them at runtime
+/// from a JSON or TOML file mapping operation names to [`OperationOverride`]s:
+///
+/// ```toml
+/// [CreateTweet]
+/// query_id = "oB-5XsHNAbjvARJEc8CZFw"
+///
+/// [SearchTimeline.features]
+/// rweb_video_timestamps_enabled = false
+/// ```
+#[derive(Debug, Clone)]
+pub struct OperationRegistry {
+    inner: Arc<RwLock<HashMap<String, GraphqlOperation>>>,
+}
+
+impl Default for OperationRegistry {
+    fn default() -> Self {
+        let operations = builtin_operations()
+            .into_iter()
+            .map(|operation| (operation.name.clone(), operation))
+            .collect();
+
+        Self {
+            inner: Arc::new(RwLock::new(operations)),
         }
     }
+}
 
-    pub fn user_tweets(user_id: &str, count: i32, cursor: Option<&str>) -> ApiEndpoint {
-        let mut variables = HashMap::from([
-            ("userId".to_string(), user_id.into()),
-            ("count".to_string(), count.into()),
-            ("includePromotedContent".to_string(), true.into()),
-            (
-                "withQuickPromoteEligibilityTweetFields".to_string(),
-                true.into(),
-            ),
-            ("withVoice".to_string(), true.into()),
-            ("withV2Timeline".to_string(), true.into()),
-        ]);
+impl OperationRegistry {
+    pub fn get(&self, name: &str) -> Option<GraphqlOperation> {
+        self.inner.read().unwrap().get(name).cloned()
+    }
 
-        if let Some(cursor_value) = cursor {
-            variables.insert("cursor".to_string(), cursor_value.into());
+    pub fn query_id(&self, name: &str) -> Option<String> {
+        self.get(name).map(|operation| operation.query_id)
+    }
+
+    pub fn operations(&self) -> Vec<GraphqlOperation> {
+        let mut operations: Vec<_> = self.inner.read().unwrap().values().cloned().collect();
+        operations.sort_by(|a, b| a.name.cmp(&b.name));
+        operations
+    }
+
+    pub fn set_query_id(&self, name: &str, query_id: &str) -> Result<()> {
+        self.apply_overrides(HashMap::from([(
+            name.to_string(),
+            OperationOverride {
+                query_id: Some(query_id.to_string()),
+                features: None,
+            },
+        )]))
+    }
+
+    /// Applies `overrides`; nothing is changed if any of them names an unknown operation.
+    pub fn apply_overrides(&self, overrides: HashMap<String, OperationOverride>) -> Result<()> {
+        let mut operations = self.inner.write().unwrap();
+
+        if let Some(name) = overrides
+            .keys()
+            .find(|name| !operations.contains_key(*name))
+        {
+            return Err(TwitterError::Config(format!(
+                "Unknown GraphQL operation: {}",
+                name
+            )));
+        }
+
+        for (name, patch) in overrides {
+            let operation = operations.get_mut(&name).unwrap();
+            if let Some(query_id) = patch.query_id {
+                operation.query_id = query_id;
+            }
+            if let Some(features) = patch.features {
+                operation
+                    .features
+                    .get_or_insert_with(FeatureSet::new)
+                    .merge(&features);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load_json(&self, json: &str) -> Result<()> {
+        self.apply_overrides(serde_json::from_str(json)?)
+    }
+
+    pub fn load_toml(&self, toml: &str) -> Result<()> {
+        let overrides = toml::from_str(toml).map_err(|e| TwitterError::Config(e.to_string()))?;
+        self.apply_overrides(overrides)
+    }
+
+    /// Loads overrides from `path`, parsed as TOML for a `.toml` extension and as JSON
+    /// otherwise.
+    pub fn load_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            self.load_toml(&content)
+        } else {
+            self.load_json(&content)
         }
+    }
+
+    /// Takes query IDs and feature switches from operations found in the web client
+    /// bundle. Switches already known keep their value and new ones are sent as `false`;
+    /// an operation found without any switches keeps its features, since that usually means
+    /// its metadata was not matched. Operations this crate does not use are ignored.
+    /// Returns the names of the operations whose query ID changed.
+    pub fn update_from_discovered(&self, discovered: &[DiscoveredOperation]) -> Vec<String> {
+        let mut operations = self.inner.write().unwrap();
+        let mut updated = Vec::new();
+
+        for found in discovered {
+            let Some(operation) = operations.get_mut(&found.operation_name) else {
+                continue;
+            };
+
+            if operation.query_id != found.query_id {
+                operation.query_id = found.query_id.clone();
+                if !updated.contains(&operation.name) {
+                    updated.push(operation.name.clone());
+                }
+            }
 
-        ApiEndpoint {
-            url: "https://twitter.com/i/api/graphql/V7H0Ap3_Hh2FyS75OCDO3Q/UserTweets".to_string(),
-            variables: Some(variables),
-            features: Some(HashMap::from([
-                ("rweb_tipjar_consumption_enabled".to_string(), true),
-                (
-                    "responsive_web_graphql_exclude_directive_enabled".to_string(),
-                    true,
-                ),
-                ("verified_phone_label_enabled".to_string(), false),
-                (
-                    "creator_subscriptions_tweet_preview_api_enabled".to_string(),
-                    true,
-                ),
-                (
-                    "responsive_web_graphql_timeline_navigation_enabled".to_string(),
-                    true,
-                ),
-                (
-                    "responsive_web_graphql_skip_user_profile_image_extensions_enabled".to_string(),
-                    false,
-                ),
-                (
-                    "communities_web_enable_tweet_community_results_fetch".to_string(),
-                    true,
-                ),
-                (
-                    "c9s_tweet_anatomy_moderator_badge_enabled".to_string(),
-                    true,
-                ),
-                ("articles_preview_enabled".to_string(), true),
-                ("tweetypie_unmention_optimization_enabled".to_string(), true),
-                ("responsive_web_edit_tweet_api_enabled".to_string(), true),
-                (
-                    "graphql_is_translatable_rweb_tweet_is_translatable_enabled".to_string(),
-                    true,
-                ),
-                ("view_counts_everywhere_api_enabled".to_string(), true),
-                ("longform_notetweets_consumption_enabled".to_string(), true),
-                (
-                    "responsive_web_twitter_article_tweet_consumption_enabled".to_string(),
-                    true,
-                ),
-                ("tweet_awards_web_tipping_enabled".to_string(), false),
-                (
-                    "creator_subscriptions_quote_tweet_preview_enabled".to_string(),
-                    false,
-                ),
-                (
-                    "freedom_of_speech_not_reach_fetch_enabled".to_string(),
-                    true,
-                ),
-                ("standardized_nudges_misinfo".to_string(), true),
-                (
-                    "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled"
-                        .to_string(),
-                    true,
-                ),
-                ("rweb_video_timestamps_enabled".to_string(), true),
-                (
-                    "longform_notetweets_rich_text_read_enabled".to_string(),
-                    true,
-                ),
-                ("longform_notetweets_inline_media_enabled".to_string(), true),
-                ("responsive_web_enhance_cards_enabled".to_string(), false),
-            ])),
-            field_toggles: Some(HashMap::from([("withArticlePlainText".to_string(), false)])),
+            if found.feature_switches.is_empty() {
+                continue;
+            }
+            let features = found
+                .feature_switches
+                .iter()
+                .map(|name| {
+                    let enabled = operation
+                        .features
+                        .as_ref()
+                        .and_then(|features| features.get(name))
+                        .unwrap_or(false);
+                    (name.as_str(), enabled)
+                })
+                .collect();
+            operation.features = Some(features);
         }
+        updated
     }
 
-    pub fn user_tweets_and_replies(user_id: &str, count: i32, cursor: Option<&str>) -> ApiEndpoint {
-        let mut variables = HashMap::from([
-            ("userId".to_string(), user_id.into()),
-            ("count".to_string(), count.into()),
-            ("includePromotedContent".to_string(), true.into()),
-            ("withCommunity".to_string(), true.into()),
-            ("withVoice".to_string(), true.into()),
-            ("withV2Timeline".to_string(), true.into()),
-        ]);
+    /// Starts a request for operation `name` with its default features.
+    ///
+    /// Panics if `name` is not registered; the crate only asks for built-in operations.
+    pub fn endpoint(&self, hosts: &Hosts, name: &str) -> ApiEndpoint {
+        let operation = self
+            .get(name)
+            .unwrap_or_else(|| panic!("Unknown GraphQL operation: {}", name));
+
+        let mut endpoint = ApiEndpoint::new(
+            operation.method.clone(),
+            hosts.graphql_url(&operation.path()),
+        );
+        endpoint.operation = Some(operation.name);
+        match operation.features {
+            Some(features) => endpoint.with_features(features),
+            None => endpoint,
+        }
+    }
+}
+
+fn builtin_operations() -> Vec<GraphqlOperation> {
+    use Method as M;
+
+    vec![
+        GraphqlOperation::new("UserTweets", "V7H0Ap3_Hh2FyS75OCDO3Q", M::GET),
+        GraphqlOperation::new("UserTweetsAndReplies", "E4wA5vo2sjVyvpliUffSCw", M::GET),
+        GraphqlOperation::new("TweetDetail", "xOhkmRac04YFZmOzU9PJHg", M::GET),
+        GraphqlOperation::new("TweetResultByRestId", "DJS3BdhUhcaEpZ7B7irJDg", M::GET),
+        GraphqlOperation::new("Likes", "YlkSUg4Czo2Zx7yRqpwDow", M::GET),
+        GraphqlOperation::new("ListLatestTweetsTimeline", "LFKj1wqHNTsEJ4Oq7TzaNA", M::GET),
+        GraphqlOperation::new("CreateTweet", "a1p9RWpkYKBjWv_I3WzS-A", M::POST),
+        GraphqlOperation::new("CreateNoteTweet", "YNXM2DGuE2Sff6a2JD3Ztw", M::POST),
+        GraphqlOperation::new("FavoriteTweet", "lI07N6Otwv1PhnEgXILM7A", M::POST),
+        GraphqlOperation::new("CreateRetweet", "ojPdsZsimiJrUGLR1sjUtA", M::POST),
+        GraphqlOperation::new("UserByScreenName", "G3KGOASz96M-Qu0nwmGXNg", M::GET),
+        GraphqlOperation::new("UserByRestId", "xf3jd90KKBCUxdlI_tNHZw", M::GET),
+        GraphqlOperation::new("Following", "iSicc7LrzWGBgDPL0tM_TQ", M::GET),
+        GraphqlOperation::new("SearchTimeline", "gkjsKepM6gl_HmFWoWKfgg", M::GET),
+        GraphqlOperation::new("HomeTimeline", "HJFjzBgCs16TqxewQOeLNg", M::GET),
+        GraphqlOperation::new("ExplorePage", "_XV-G8GPq40yR0j1h86YZg", M::GET),
+        GraphqlOperation::new("GenericTimelineById", "-R9ACaB96xqEnX2BJ_RbFA", M::GET),
+    ]
+}
+
+pub struct Endpoints;
+
+impl Endpoints {
+    pub fn tweet_detail(client: &TwitterClient, tweet_id: &str) -> ApiEndpoint {
+        client
+            .graphql_endpoint("TweetDetail")
+            .with_variables(json!({
+                "focalTweetId": tweet_id,
+                "with_rux_injections": false,
+                "includePromotedContent": true,
+                "withCommunity": true,
+                "withQuickPromoteEligibilityTweetFields": true,
+                "withBirdwatchNotes": true,
+                "withVoice": true,
+                "withV2Timeline": true
+            }))
+            .with_field_toggles(json!({
+                "withArticleRichContentState": false
+            }))
+    }
+
+    pub fn tweet_by_rest_id(client: &TwitterClient, tweet_id: &str) -> ApiEndpoint {
+        client
+            .graphql_endpoint("TweetResultByRestId")
+            .with_variables(json!({
+                "tweetId": tweet_id,
+                "withCommunity": false,
+                "includePromotedContent": false,
+                "withVoice": false
+            }))
+    }
+
+    pub fn user_tweets(
+        client: &TwitterClient,
+        user_id: &str,
+        count: i32,
+        cursor: Option<&str>,
+    ) -> ApiEndpoint {
+        let mut variables = json!({
+            "userId": user_id,
+            "count": count,
+            "includePromotedContent": true,
+            "withQuickPromoteEligibilityTweetFields": true,
+            "withVoice": true,
+            "withV2Timeline": true
+        });
+
+        if let Some(cursor_value) = cursor {
+            variables["cursor"] = cursor_value.into();
+        }
+
+        client
+            .graphql_endpoint("UserTweets")
+            .with_variables(variables)
+            .with_field_toggles(json!({
+                "withArticlePlainText": false
+            }))
+    }
+
+    pub fn user_tweets_and_replies(
+        client: &TwitterClient,
+        user_id: &str,
+        count: i32,
+        cursor: Option<&str>,
+    ) -> ApiEndpoint {
+        let mut variables = json!({
+            "userId": user_id,
+            "count": count,
+            "includePromotedContent": true,
+            "withCommunity": true,
+            "withVoice": true,
+            "withV2Timeline": true
+        });
 
         if let Some(cursor_value) = cursor {
-            variables.insert("cursor".to_string(), cursor_value.into());
+            variables["cursor"] = cursor_value.into();
         }
 
-        ApiEndpoint {
-            url: "https://twitter.com/i/api/graphql/E4wA5vo2sjVyvpliUffSCw/UserTweetsAndReplies"
-                .to_string(),
-            variables: Some(variables),
-            features: Some(HashMap::from([
-                ("rweb_tipjar_consumption_enabled".to_string(), true),
-                (
-                    "responsive_web_graphql_exclude_directive_enabled".to_string(),
-                    true,
-                ),
-                ("verified_phone_label_enabled".to_string(), false),
-                (
-                    "creator_subscriptions_tweet_preview_api_enabled".to_string(),
-                    true,
-                ),
-                (
-                    "responsive_web_graphql_timeline_navigation_enabled".to_string(),
-                    true,
-                ),
-                (
-                    "responsive_web_graphql_skip_user_profile_image_extensions_enabled".to_string(),
-                    false,
-                ),
-                (
-                    "communities_web_enable_tweet_community_results_fetch".to_string(),
-                    true,
-                ),
-                (
-                    "c9s_tweet_anatomy_moderator_badge_enabled".to_string(),
-                    true,
-                ),
-                ("articles_preview_enabled".to_string(), true),
-                ("tweetypie_unmention_optimization_enabled".to_string(), true),
-                ("responsive_web_edit_tweet_api_enabled".to_string(), true),
-                (
-                    "graphql_is_translatable_rweb_tweet_is_translatable_enabled".to_string(),
-                    true,
-                ),
-                ("view_counts_everywhere_api_enabled".to_string(), true),
-                ("longform_notetweets_consumption_enabled".to_string(), true),
-                (
-                    "responsive_web_twitter_article_tweet_consumption_enabled".to_string(),
-                    true,
-                ),
-                ("tweet_awards_web_tipping_enabled".to_string(), false),
-                (
-                    "creator_subscriptions_quote_tweet_preview_enabled".to_string(),
-                    false,
-                ),
-                (
-                    "freedom_of_speech_not_reach_fetch_enabled".to_string(),
-                    true,
-                ),
-                ("standardized_nudges_misinfo".to_string(), true),
-                (
-                    "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled"
-                        .to_string(),
-                    true,
-                ),
-                ("rweb_video_timestamps_enabled".to_string(), true),
-                (
-                    "longform_notetweets_rich_text_read_enabled".to_string(),
-                    true,
-                ),
-                ("longform_notetweets_inline_media_enabled".to_string(), true),
-                ("responsive_web_enhance_cards_enabled".to_string(), false),
-            ])),
-            field_toggles: Some(HashMap::from([("withArticlePlainText".to_string(), false)])),
+        client
+            .graphql_endpoint("UserTweetsAndReplies")
+            .with_variables(variables)
+            .with_field_toggles(json!({
+                "withArticlePlainText": false
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::user_auth::TwitterUserAuth;
+    use crate::constants::BEARER_TOKEN;
+    use crate::mock::MockServer;
+
+    #[test]
+    fn test_get_encodes_params_in_query() {
+        let endpoint = ApiEndpoint::get("https://x.com/i/api/graphql/abc/UserByScreenName")
+            .with_variables(json!({ "screen_name": "mockuser", "withSafetyModeUserFields": true }))
+            .with_features(FeatureSet::new().with("verified_phone_label_enabled", false))
+            .with_field_toggles(json!({ "withAuxiliaryUserLabels": false }));
+
+        assert_eq!(
+            endpoint.to_request_url(),
+            "https://x.com/i/api/graphql/abc/UserByScreenName\
+             ?variables=%7B%22screen_name%22%3A%22mockuser%22%2C%22withSafetyModeUserFields%22%3Atrue%7D\
+             &features=%7B%22verified_phone_label_enabled%22%3Afalse%7D\
+             &fieldToggles=%7B%22withAuxiliaryUserLabels%22%3Afalse%7D"
+        );
+        assert_eq!(endpoint.to_request_body(), None);
+    }
+
+    #[test]
+    fn test_post_sends_params_in_body() {
+        let endpoint = ApiEndpoint::post("https://x.com/i/api/graphql/abc/CreateTweet")
+            .with_variables(json!({ "tweet_text": "hello" }))
+            .with_field_toggles(json!({}));
+
+        assert_eq!(
+            endpoint.to_request_url(),
+            "https://x.com/i/api/graphql/abc/CreateTweet"
+        );
+        assert_eq!(
+            endpoint.to_request_body(),
+            Some(json!({ "variables": { "tweet_text": "hello" }, "fieldToggles": {} }))
+        );
+    }
+
+    #[test]
+    fn test_endpoint_without_params() {
+        let endpoint = ApiEndpoint::get("https://x.com/i/api/graphql/abc/Op");
+        assert_eq!(
+            endpoint.to_request_url(),
+            "https://x.com/i/api/graphql/abc/Op"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_user_tweets_cursor() {
+        let auth = TwitterUserAuth::new(BEARER_TOKEN.to_string())
+            .await
+            .unwrap();
+        let client = TwitterClient::new(Box::new(auth)).unwrap();
+        let endpoint = Endpoints::user_tweets(&client, "1000", 20, Some("DAAB"));
+        let url = url::Url::parse(&endpoint.to_request_url()).unwrap();
+        let variables: Value = url
+            .query_pairs()
+            .find(|(name, _)| name == "variables")
+            .map(|(_, value)| serde_json::from_str(&value).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            url.path(),
+            "/i/api/graphql/V7H0Ap3_Hh2FyS75OCDO3Q/UserTweets"
+        );
+        assert_eq!(variables["userId"], "1000");
+        assert_eq!(variables["count"], 20);
+        assert_eq!(variables["cursor"], "DAAB");
+    }
+
+    #[test]
+    fn test_registry_overrides() {
+        let registry = OperationRegistry::default();
+        registry
+            .load_json(r#"{ "CreateTweet": { "query_id": "new-id" } }"#)
+            .unwrap();
+        registry
+            .load_toml(
+                r#"
+                [SearchTimeline.features]
+                rweb_video_timestamps_enabled = false
+                "#,
+            )
+            .unwrap();
+
+        let endpoint = registry.endpoint(&Hosts::default(), "CreateTweet");
+        assert_eq!(
+            endpoint.url,
+            "https://x.com/i/api/graphql/new-id/CreateTweet"
+        );
+        assert_eq!(endpoint.method, Method::POST);
+
+        let features = registry.get("SearchTimeline").unwrap().features.unwrap();
+        assert_eq!(features.get("rweb_video_timestamps_enabled"), Some(false));
+        assert_eq!(features.get("verified_phone_label_enabled"), Some(false));
+        assert_eq!(
+            features.get("rweb_lists_timeline_redesign_enabled"),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_per_request_feature_toggle() {
+        let registry = OperationRegistry::default();
+        let endpoint = registry
+            .endpoint(&Hosts::default(), "SearchTimeline")
+            .with_feature("responsive_web_enhance_cards_enabled", true)
+            .with_feature("some_new_flag", false);
+
+        let features = endpoint.features.unwrap();
+        assert_eq!(
+            features.get("responsive_web_enhance_cards_enabled"),
+            Some(true)
+        );
+        assert_eq!(features.get("some_new_flag"), Some(false));
+        assert_eq!(features.len(), FeaturePreset::Search.features().len() + 1);
+
+        // The registered defaults are untouched.
+        let defaults = registry.get("SearchTimeline").unwrap().features.unwrap();
+        assert_eq!(
+            defaults.get("responsive_web_enhance_cards_enabled"),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_registry_rejects_unknown_operations() {
+        let registry = OperationRegistry::default();
+        let result = registry.load_json(
+            r#"{ "CreateTweet": { "query_id": "new-id" }, "CreateTweat": { "query_id": "x" } }"#,
+        );
+
+        assert!(matches!(result, Err(TwitterError::Config(_))));
+        assert_eq!(
+            registry.query_id("CreateTweet").as_deref(),
+            Some("a1p9RWpkYKBjWv_I3WzS-A")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_overridden_query_id_is_used() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("operations-{}.toml", std::process::id()));
+        fs::write(&path, "[UserByScreenName]\nquery_id = \"rotated\"\n").unwrap();
+        scraper.load_operation_overrides(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        scraper.get_profile("mockuser").await.unwrap();
+
+        let requests = server.requests_to("UserByScreenName");
+        assert_eq!(
+            requests.last().unwrap().path,
+            "/i/api/graphql/rotated/UserByScreenName"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_operations_send_variables_in_query() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+        let client = &scraper.twitter_client;
+
+        crate::profile::get_profile(client, "mockuser")
+            .await
+            .unwrap();
+        crate::profile::get_screen_name_by_user_id(client, "1000")
+            .await
+            .unwrap();
+        crate::tweets::fetch_tweets(client, "1000", 20, None)
+            .await
+            .unwrap();
+        crate::tweets::fetch_list_tweets(client, "3000", 20, None)
+            .await
+            .unwrap();
+        crate::tweets::fetch_liked_tweets(client, "1000", 20, None)
+            .await
+            .unwrap();
+        crate::explore::get_explore_timelines(client).await.unwrap();
+        crate::trends::get_trends(client, "trending", 20)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        for request in requests.iter().filter(|r| r.path.contains("/graphql/")) {
+            assert_eq!(request.method, "GET");
+            assert!(
+                request.query_json("variables").is_some(),
+                "{} has no variables in its query",
+                request.route()
+            );
+            assert!(request.query_json("features").is_some());
+            assert!(request.body.is_empty(), "{} sent a body", request.route());
         }
+        assert_eq!(
+            requests
+                .iter()
+                .filter(|r| r.path.contains("/graphql/"))
+                .count(),
+            7
+        );
     }
 }
@@ -0,0 +1,564 @@
+//! GraphQL feature switches, the `features` parameter sent with most GraphQL requests.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A set of named feature switches.
+///
+/// Sets merge with [`FeatureSet::merge`], where the other set's values win, and single
+/// switches can be toggled per request through [`ApiEndpoint::with_feature`].
+///
+/// [`ApiEndpoint::with_feature`]: crate::api::endpoints::ApiEndpoint::with_feature
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FeatureSet(BTreeMap<String, bool>);
+
+impl FeatureSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_pairs(pairs: &[(&str, bool)]) -> Self {
+        Self(
+            pairs
+                .iter()
+                .map(|(name, enabled)| (name.to_string(), *enabled))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, name: &str) -> Option<bool> {
+        self.0.get(name).copied()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn set(&mut self, name: &str, enabled: bool) {
+        self.0.insert(name.to_string(), enabled);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<bool> {
+        self.0.remove(name)
+    }
+
+    pub fn with(mut self, name: &str, enabled: bool) -> Self {
+        self.set(name, enabled);
+        self
+    }
+
+    pub fn without(mut self, name: &str) -> Self {
+        self.remove(name);
+        self
+    }
+
+    /// Copies every switch of `other` into this set, overriding values already present.
+    pub fn merge(&mut self, other: &FeatureSet) {
+        self.0.extend(
+            other
+                .0
+                .iter()
+                .map(|(name, enabled)| (name.clone(), *enabled)),
+        );
+    }
+
+    pub fn merged(mut self, other: &FeatureSet) -> Self {
+        self.merge(other);
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.0
+            .iter()
+            .map(|(name, enabled)| (name.as_str(), *enabled))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+impl<'a> FromIterator<(&'a str, bool)> for FeatureSet {
+    fn from_iter<I: IntoIterator<Item = (&'a str, bool)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(name, enabled)| (name.to_string(), enabled))
+                .collect(),
+        )
+    }
+}
+
+/// The feature sets the web client sends, named after the operations using them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeaturePreset {
+    /// `UserTweets` and `UserTweetsAndReplies`.
+    Timeline,
+    HomeTimeline,
+    /// `ExplorePage` and `GenericTimelineById`.
+    Explore,
+    /// `Likes` and `ListLatestTweetsTimeline`.
+    TweetList,
+    TweetDetail,
+    TweetResultByRestId,
+    CreateTweet,
+    /// `CreateTweet` when quoting another tweet.
+    QuoteTweet,
+    NoteTweet,
+    Search,
+    UserByScreenName,
+    UserByRestId,
+    Following,
+}
+
+impl FeaturePreset {
+    /// The preset the web client sends with the GraphQL operation `name`, if any.
+    pub fn for_operation(name: &str) -> Option<Self> {
+        Some(match name {
+            "UserTweets" | "UserTweetsAndReplies" => Self::Timeline,
+            "HomeTimeline" => Self::HomeTimeline,
+            "ExplorePage" | "GenericTimelineById" => Self::Explore,
+            "Likes" | "ListLatestTweetsTimeline" => Self::TweetList,
+            "TweetDetail" => Self::TweetDetail,
+            "TweetResultByRestId" => Self::TweetResultByRestId,
+            "CreateTweet" => Self::CreateTweet,
+            "CreateNoteTweet" => Self::NoteTweet,
+            "SearchTimeline" => Self::Search,
+            "UserByScreenName" => Self::UserByScreenName,
+            "UserByRestId" => Self::UserByRestId,
+            "Following" => Self::Following,
+            _ => return None,
+        })
+    }
+
+    pub fn features(self) -> FeatureSet {
+        match self {
+            Self::Timeline => FeatureSet::from_pairs(TIMELINE),
+            Self::HomeTimeline => Self::Timeline
+                .features()
+                .without("tweetypie_unmention_optimization_enabled"),
+            Self::Explore => Self::HomeTimeline
+                .features()
+                .merged(&FeatureSet::from_pairs(&[
+                    ("profile_label_improvements_pcf_label_in_post_enabled", true),
+                    ("premium_content_api_read_enabled", false),
+                    (
+                        "responsive_web_grok_analyze_button_fetch_trends_enabled",
+                        false,
+                    ),
+                    ("responsive_web_grok_analyze_post_followups_enabled", true),
+                    ("responsive_web_jetfuel_frame", false),
+                    ("responsive_web_grok_share_attachment_enabled", true),
+                    ("responsive_web_grok_image_annotation_enabled", true),
+                ])),
+            Self::TweetList => Self::QuoteTweet
+                .features()
+                .merged(&FeatureSet::from_pairs(&[
+                    ("creator_subscriptions_quote_tweet_preview_enabled", false),
+                    (
+                        "profile_label_improvements_pcf_label_in_post_enabled",
+                        false,
+                    ),
+                    ("rweb_tipjar_consumption_enabled", true),
+                    ("articles_preview_enabled", true),
+                ])),
+            Self::TweetDetail => FeatureSet::from_pairs(TWEET_DETAIL),
+            Self::TweetResultByRestId => FeatureSet::from_pairs(TWEET_RESULT_BY_REST_ID),
+            Self::CreateTweet => FeatureSet::from_pairs(CREATE_TWEET),
+            Self::QuoteTweet => Self::CreateTweet
+                .features()
+                .merged(&FeatureSet::from_pairs(QUOTE_TWEET)),
+            Self::NoteTweet => FeatureSet::from_pairs(NOTE_TWEET),
+            Self::Search => Self::CreateTweet
+                .features()
+                .with("interactive_text_enabled", false)
+                .with("longform_notetweets_inline_media_enabled", true)
+                .with(
+                    "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled",
+                    true,
+                )
+                .with("responsive_web_media_download_video_enabled", false)
+                .without("rweb_video_timestamps_enabled")
+                .without("c9s_tweet_anatomy_moderator_badge_enabled"),
+            Self::UserByScreenName => FeatureSet::from_pairs(USER_BY_SCREEN_NAME),
+            Self::UserByRestId => FeatureSet::from_pairs(USER_BY_REST_ID),
+            Self::Following => FeatureSet::from_pairs(FOLLOWING),
+        }
+    }
+}
+
+/// Switches a quote tweet sets on top of the `CreateTweet` features.
+pub(crate) const QUOTE_TWEET: &[(&str, bool)] = &[
+    ("rweb_video_timestamps_enabled", true),
+    ("c9s_tweet_anatomy_moderator_badge_enabled", true),
+];
+
+const TIMELINE: &[(&str, bool)] = &[
+    ("rweb_tipjar_consumption_enabled", true),
+    ("responsive_web_graphql_exclude_directive_enabled", true),
+    ("verified_phone_label_enabled", false),
+    ("creator_subscriptions_tweet_preview_api_enabled", true),
+    ("responsive_web_graphql_timeline_navigation_enabled", true),
+    (
+        "responsive_web_graphql_skip_user_profile_image_extensions_enabled",
+        false,
+    ),
+    ("communities_web_enable_tweet_community_results_fetch", true),
+    ("c9s_tweet_anatomy_moderator_badge_enabled", true),
+    ("articles_preview_enabled", true),
+    ("tweetypie_unmention_optimization_enabled", true),
+    ("responsive_web_edit_tweet_api_enabled", true),
+    (
+        "graphql_is_translatable_rweb_tweet_is_translatable_enabled",
+        true,
+    ),
+    ("view_counts_everywhere_api_enabled", true),
+    ("longform_notetweets_consumption_enabled", true),
+    (
+        "responsive_web_twitter_article_tweet_consumption_enabled",
+        true,
+    ),
+    ("tweet_awards_web_tipping_enabled", false),
+    ("creator_subscriptions_quote_tweet_preview_enabled", false),
+    ("freedom_of_speech_not_reach_fetch_enabled", true),
+    ("standardized_nudges_misinfo", true),
+    (
+        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled",
+        true,
+    ),
+    ("rweb_video_timestamps_enabled", true),
+    ("longform_notetweets_rich_text_read_enabled", true),
+    ("longform_notetweets_inline_media_enabled", true),
+    ("responsive_web_enhance_cards_enabled", false),
+];
+
+const TWEET_DETAIL: &[(&str, bool)] = &[
+    ("responsive_web_graphql_exclude_directive_enabled", true),
+    ("verified_phone_label_enabled", false),
+    ("creator_subscriptions_tweet_preview_api_enabled", true),
+    ("responsive_web_graphql_timeline_navigation_enabled", true),
+    (
+        "responsive_web_graphql_skip_user_profile_image_extensions_enabled",
+        false,
+    ),
+    ("tweetypie_unmention_optimization_enabled", true),
+    ("responsive_web_edit_tweet_api_enabled", true),
+    (
+        "graphql_is_translatable_rweb_tweet_is_translatable_enabled",
+        true,
+    ),
+    ("view_counts_everywhere_api_enabled", true),
+    ("longform_notetweets_consumption_enabled", true),
+    ("tweet_awards_web_tipping_enabled", false),
+    ("freedom_of_speech_not_reach_fetch_enabled", true),
+    ("standardized_nudges_misinfo", true),
+    (
+        "responsive_web_twitter_article_tweet_consumption_enabled",
+        false,
+    ),
+    (
+        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled",
+        true,
+    ),
+    ("longform_notetweets_rich_text_read_enabled", true),
+    ("longform_notetweets_inline_media_enabled", true),
+    ("responsive_web_media_download_video_enabled", false),
+    ("responsive_web_enhance_cards_enabled", false),
+];
+
+const TWEET_RESULT_BY_REST_ID: &[(&str, bool)] = &[
+    ("creator_subscriptions_tweet_preview_api_enabled", true),
+    ("tweetypie_unmention_optimization_enabled", true),
+    ("responsive_web_edit_tweet_api_enabled", true),
+    (
+        "graphql_is_translatable_rweb_tweet_is_translatable_enabled",
+        true,
+    ),
+    ("view_counts_everywhere_api_enabled", true),
+    ("longform_notetweets_consumption_enabled", true),
+    (
+        "responsive_web_twitter_article_tweet_consumption_enabled",
+        false,
+    ),
+    ("tweet_awards_web_tipping_enabled", false),
+    ("freedom_of_speech_not_reach_fetch_enabled", true),
+    ("standardized_nudges_misinfo", true),
+];
+
+const CREATE_TWEET: &[(&str, bool)] = &[
+    ("interactive_text_enabled", true),
+    ("longform_notetweets_inline_media_enabled", false),
+    ("responsive_web_text_conversations_enabled", false),
+    (
+        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled",
+        false,
+    ),
+    ("vibe_api_enabled", false),
+    ("rweb_lists_timeline_redesign_enabled", true),
+    ("responsive_web_graphql_exclude_directive_enabled", true),
+    ("verified_phone_label_enabled", false),
+    ("creator_subscriptions_tweet_preview_api_enabled", true),
+    ("responsive_web_graphql_timeline_navigation_enabled", true),
+    (
+        "responsive_web_graphql_skip_user_profile_image_extensions_enabled",
+        false,
+    ),
+    ("tweetypie_unmention_optimization_enabled", true),
+    ("responsive_web_edit_tweet_api_enabled", true),
+    (
+        "graphql_is_translatable_rweb_tweet_is_translatable_enabled",
+        true,
+    ),
+    ("view_counts_everywhere_api_enabled", true),
+    ("longform_notetweets_consumption_enabled", true),
+    ("tweet_awards_web_tipping_enabled", false),
+    ("freedom_of_speech_not_reach_fetch_enabled", true),
+    ("standardized_nudges_misinfo", true),
+    ("longform_notetweets_rich_text_read_enabled", true),
+    ("responsive_web_enhance_cards_enabled", false),
+    ("subscriptions_verification_info_enabled", true),
+    ("subscriptions_verification_info_reason_enabled", true),
+    (
+        "subscriptions_verification_info_verified_since_enabled",
+        true,
+    ),
+    ("super_follow_badge_privacy_enabled", false),
+    ("super_follow_exclusive_tweet_notifications_enabled", false),
+    ("super_follow_tweet_api_enabled", false),
+    ("super_follow_user_api_enabled", false),
+    ("android_graphql_skip_api_media_color_palette", false),
+    ("creator_subscriptions_subscription_count_enabled", false),
+    ("blue_business_profile_image_shape_enabled", false),
+    (
+        "unified_cards_ad_metadata_container_dynamic_card_content_query_enabled",
+        false,
+    ),
+    ("rweb_video_timestamps_enabled", false),
+    ("c9s_tweet_anatomy_moderator_badge_enabled", false),
+    (
+        "responsive_web_twitter_article_tweet_consumption_enabled",
+        false,
+    ),
+];
+
+const NOTE_TWEET: &[(&str, bool)] = &[
+    ("premium_content_api_read_enabled", false),
+    ("communities_web_enable_tweet_community_results_fetch", true),
+    ("c9s_tweet_anatomy_moderator_badge_enabled", true),
+    (
+        "responsive_web_grok_analyze_button_fetch_trends_enabled",
+        true,
+    ),
+    ("responsive_web_edit_tweet_api_enabled", true),
+    (
+        "graphql_is_translatable_rweb_tweet_is_translatable_enabled",
+        true,
+    ),
+    ("view_counts_everywhere_api_enabled", true),
+    ("longform_notetweets_consumption_enabled", true),
+    (
+        "responsive_web_twitter_article_tweet_consumption_enabled",
+        true,
+    ),
+    ("tweet_awards_web_tipping_enabled", false),
+    ("longform_notetweets_rich_text_read_enabled", true),
+    ("longform_notetweets_inline_media_enabled", true),
+    ("responsive_web_graphql_exclude_directive_enabled", true),
+    ("verified_phone_label_enabled", false),
+    ("freedom_of_speech_not_reach_fetch_enabled", true),
+    ("standardized_nudges_misinfo", true),
+    (
+        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled",
+        true,
+    ),
+    ("responsive_web_graphql_timeline_navigation_enabled", true),
+    ("responsive_web_enhance_cards_enabled", false),
+];
+
+const USER_BY_SCREEN_NAME: &[(&str, bool)] = &[
+    ("hidden_profile_likes_enabled", false),
+    ("hidden_profile_subscriptions_enabled", false),
+    ("responsive_web_graphql_exclude_directive_enabled", true),
+    ("verified_phone_label_enabled", false),
+    (
+        "subscriptions_verification_info_is_identity_verified_enabled",
+        false,
+    ),
+    (
+        "subscriptions_verification_info_verified_since_enabled",
+        true,
+    ),
+    ("highlights_tweets_tab_ui_enabled", true),
+    ("creator_subscriptions_tweet_preview_api_enabled", true),
+    (
+        "responsive_web_graphql_skip_user_profile_image_extensions_enabled",
+        false,
+    ),
+    ("responsive_web_graphql_timeline_navigation_enabled", true),
+];
+
+const USER_BY_REST_ID: &[(&str, bool)] = &[
+    ("hidden_profile_subscriptions_enabled", true),
+    ("rweb_tipjar_consumption_enabled", true),
+    ("responsive_web_graphql_exclude_directive_enabled", true),
+    ("verified_phone_label_enabled", false),
+    ("highlights_tweets_tab_ui_enabled", true),
+    ("responsive_web_twitter_article_notes_tab_enabled", true),
+    ("subscriptions_feature_can_gift_premium", false),
+    ("creator_subscriptions_tweet_preview_api_enabled", true),
+    (
+        "responsive_web_graphql_skip_user_profile_image_extensions_enabled",
+        false,
+    ),
+    ("responsive_web_graphql_timeline_navigation_enabled", true),
+];
+
+const FOLLOWING: &[(&str, bool)] = &[
+    (
+        "responsive_web_twitter_article_tweet_consumption_enabled",
+        false,
+    ),
+    (
+        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled",
+        true,
+    ),
+    ("longform_notetweets_inline_media_enabled", true),
+    ("responsive_web_media_download_video_enabled", false),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_overrides_existing_values() {
+        let base = FeatureSet::from_pairs(&[("a", true), ("b", false)]);
+        let merged = base.merged(&FeatureSet::from_pairs(&[("b", true), ("c", false)]));
+
+        assert_eq!(merged.get("a"), Some(true));
+        assert_eq!(merged.get("b"), Some(true));
+        assert_eq!(merged.get("c"), Some(false));
+        assert_eq!(merged.len(), 3);
+    }
+
+    /// What each derived preset sets and drops on top of the preset it starts from, as
+    /// found in the feature blobs the crate sent before presets existed.
+    #[test]
+    fn test_derived_presets_match_baseline() {
+        type Diff = (
+            FeaturePreset,
+            FeaturePreset,
+            &'static [(&'static str, bool)],
+            &'static [&'static str],
+        );
+        let cases: &[Diff] = &[
+            (
+                FeaturePreset::HomeTimeline,
+                FeaturePreset::Timeline,
+                &[],
+                &["tweetypie_unmention_optimization_enabled"],
+            ),
+            (
+                FeaturePreset::Explore,
+                FeaturePreset::HomeTimeline,
+                &[
+                    ("premium_content_api_read_enabled", false),
+                    ("profile_label_improvements_pcf_label_in_post_enabled", true),
+                    (
+                        "responsive_web_grok_analyze_button_fetch_trends_enabled",
+                        false,
+                    ),
+                    ("responsive_web_grok_analyze_post_followups_enabled", true),
+                    ("responsive_web_grok_image_annotation_enabled", true),
+                    ("responsive_web_grok_share_attachment_enabled", true),
+                    ("responsive_web_jetfuel_frame", false),
+                ],
+                &[],
+            ),
+            (
+                FeaturePreset::TweetList,
+                FeaturePreset::QuoteTweet,
+                &[
+                    ("articles_preview_enabled", true),
+                    ("creator_subscriptions_quote_tweet_preview_enabled", false),
+                    (
+                        "profile_label_improvements_pcf_label_in_post_enabled",
+                        false,
+                    ),
+                    ("rweb_tipjar_consumption_enabled", true),
+                ],
+                &[],
+            ),
+            (
+                FeaturePreset::QuoteTweet,
+                FeaturePreset::CreateTweet,
+                &[
+                    ("c9s_tweet_anatomy_moderator_badge_enabled", true),
+                    ("rweb_video_timestamps_enabled", true),
+                ],
+                &[],
+            ),
+            (
+                FeaturePreset::Search,
+                FeaturePreset::CreateTweet,
+                &[
+                    ("interactive_text_enabled", false),
+                    ("longform_notetweets_inline_media_enabled", true),
+                    ("responsive_web_media_download_video_enabled", false),
+                    (
+                        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled",
+                        true,
+                    ),
+                ],
+                &[
+                    "c9s_tweet_anatomy_moderator_badge_enabled",
+                    "rweb_video_timestamps_enabled",
+                ],
+            ),
+        ];
+
+        for (derived, base, set, dropped) in cases {
+            let features = derived.features();
+            let base = base.features();
+            let changed: Vec<_> = features
+                .iter()
+                .filter(|(name, enabled)| base.get(name) != Some(*enabled))
+                .collect();
+            let removed: Vec<_> = base
+                .iter()
+                .map(|(name, _)| name)
+                .filter(|name| !features.contains(name))
+                .collect();
+            assert_eq!(changed, *set, "{:?}", derived);
+            assert_eq!(removed, *dropped, "{:?}", derived);
+        }
+    }
+
+    #[test]
+    fn test_operations_use_their_presets() {
+        for (name, preset) in [
+            ("UserTweets", FeaturePreset::Timeline),
+            ("Likes", FeaturePreset::TweetList),
+            ("ListLatestTweetsTimeline", FeaturePreset::TweetList),
+            ("CreateNoteTweet", FeaturePreset::NoteTweet),
+        ] {
+            assert_eq!(FeaturePreset::for_operation(name), Some(preset), "{}", name);
+        }
+        assert_eq!(FeaturePreset::for_operation("FavoriteTweet"), None);
+    }
+
+    #[test]
+    fn test_to_value() {
+        let features = FeatureSet::new().with("b", false).with("a", true);
+        assert_eq!(features.to_value().to_string(), r#"{"a":true,"b":false}"#);
+    }
+}
@@ -0,0 +1,175 @@
+//! The browser a client presents itself as.
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+const CHROME_VERSION: &str = "135";
+const FIREFOX_VERSION: &str = "137.0";
+const SAFARI_VERSION: &str = "18.3";
+
+/// Headers only Chromium-based browsers send, removed for the other profiles.
+const CHROMIUM_ONLY: [&str; 4] = [
+    "sec-ch-ua",
+    "sec-ch-ua-mobile",
+    "sec-ch-ua-platform",
+    "priority",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Browser {
+    #[default]
+    Chrome,
+    Firefox,
+    Safari,
+}
+
+/// Desktop is Windows for Chrome and Firefox and macOS for Safari; mobile is Android for
+/// Chrome and Firefox and iOS for Safari.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Platform {
+    #[default]
+    Desktop,
+    Mobile,
+}
+
+/// The user agent, client hints and language headers sent with every request. They are
+/// derived together so the set matches what the chosen browser would send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderProfile {
+    pub browser: Browser,
+    pub platform: Platform,
+    pub accept_language: String,
+    /// Interface language sent as `x-twitter-client-language`, e.g. `en`.
+    pub client_language: String,
+}
+
+impl Default for HeaderProfile {
+    fn default() -> Self {
+        Self::new(Browser::default(), Platform::default())
+    }
+}
+
+impl HeaderProfile {
+    pub fn new(browser: Browser, platform: Platform) -> Self {
+        Self {
+            browser,
+            platform,
+            accept_language: "en-US,en;q=0.9".to_string(),
+            client_language: "en".to_string(),
+        }
+    }
+
+    /// Sets `accept-language` and the client language to its first language, so
+    /// `ja-JP,ja;q=0.9` also sends `ja`.
+    pub fn with_accept_language(mut self, accept_language: impl Into<String>) -> Self {
+        self.accept_language = accept_language.into();
+        if let Some(language) = self
+            .accept_language
+            .split([',', ';', '-'])
+            .next()
+            .map(str::trim)
+            .filter(|language| !language.is_empty() && *language != "*")
+        {
+            self.client_language = language.to_lowercase();
+        }
+        self
+    }
+
+    pub fn with_client_language(mut self, client_language: impl Into<String>) -> Self {
+        self.client_language = client_language.into();
+        self
+    }
+
+    pub fn user_agent(&self) -> String {
+        match (self.browser, self.platform) {
+            (Browser::Chrome, Platform::Desktop) => format!(
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{}.0.0.0 Safari/537.36",
+                CHROME_VERSION
+            ),
+            (Browser::Chrome, Platform::Mobile) => format!(
+                "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{}.0.0.0 Mobile Safari/537.36",
+                CHROME_VERSION
+            ),
+            (Browser::Firefox, Platform::Desktop) => format!(
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:{0}) Gecko/20100101 Firefox/{0}",
+                FIREFOX_VERSION
+            ),
+            (Browser::Firefox, Platform::Mobile) => format!(
+                "Mozilla/5.0 (Android 14; Mobile; rv:{0}) Gecko/{0} Firefox/{0}",
+                FIREFOX_VERSION
+            ),
+            (Browser::Safari, Platform::Desktop) => format!(
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/{} Safari/605.1.15",
+                SAFARI_VERSION
+            ),
+            (Browser::Safari, Platform::Mobile) => format!(
+                "Mozilla/5.0 (iPhone; CPU iPhone OS {} like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/{} Mobile/15E148 Safari/604.1",
+                SAFARI_VERSION.replace('.', "_"),
+                SAFARI_VERSION
+            ),
+        }
+    }
+
+    /// Replaces the browser-specific headers in `headers` with this profile's.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) {
+        for name in CHROMIUM_ONLY {
+            headers.remove(name);
+        }
+        let mut insert = |name: &'static str, value: &str| {
+            if let Ok(value) = HeaderValue::from_str(value) {
+                headers.insert(HeaderName::from_static(name), value);
+            }
+        };
+
+        insert("user-agent", &self.user_agent());
+        insert("accept-language", &self.accept_language);
+        insert("x-twitter-client-language", &self.client_language);
+        if self.browser == Browser::Chrome {
+            insert(
+                "sec-ch-ua",
+                &format!(
+                    "\"Google Chrome\";v=\"{0}\", \"Not-A.Brand\";v=\"8\", \"Chromium\";v=\"{0}\"",
+                    CHROME_VERSION
+                ),
+            );
+            let (mobile, platform) = match self.platform {
+                Platform::Desktop => ("?0", "\"Windows\""),
+                Platform::Mobile => ("?1", "\"Android\""),
+            };
+            insert("sec-ch-ua-mobile", mobile);
+            insert("sec-ch-ua-platform", platform);
+            insert("priority", "u=1, i");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_send_matching_headers() {
+        let mut headers = HeaderMap::new();
+        HeaderProfile::default().apply(&mut headers);
+        assert!(headers["user-agent"]
+            .to_str()
+            .unwrap()
+            .contains("Chrome/135"));
+        assert_eq!(headers["sec-ch-ua-platform"], "\"Windows\"");
+
+        HeaderProfile::new(Browser::Safari, Platform::Mobile).apply(&mut headers);
+        assert!(headers["user-agent"]
+            .to_str()
+            .unwrap()
+            .contains("iPhone OS 18_3"));
+        assert!(headers.get("sec-ch-ua").is_none());
+        assert!(headers.get("priority").is_none());
+    }
+
+    #[test]
+    fn test_client_language_follows_accept_language() {
+        let profile = HeaderProfile::default().with_accept_language("ja-JP,ja;q=0.9,en;q=0.8");
+        assert_eq!(profile.client_language, "ja");
+        let profile = profile.with_client_language("en");
+        assert_eq!(profile.client_language, "en");
+    }
+}
@@ -0,0 +1,90 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hosts {
+    /// Web client host serving the x.com page that links the JS bundle.
+    pub web: String,
+    /// REST v1.1/v2 host, e.g. `https://api.x.com`.
+    pub api: String,
+    /// Web client API prefix used for DM endpoints, e.g. `https://x.com/i/api`.
+    pub web_api: String,
+    /// GraphQL prefix that query IDs and operation names are appended to.
+    pub graphql: String,
+    /// Media upload host.
+    pub upload: String,
+    /// Host serving `guest/activate.json` and the onboarding task flow.
+    pub login: String,
+}
+
+impl Default for Hosts {
+    fn default() -> Self {
+        Self {
+            web: "https://x.com".to_string(),
+            api: "https://api.x.com".to_string(),
+            web_api: "https://x.com/i/api".to_string(),
+            graphql: "https://x.com/i/api/graphql".to_string(),
+            upload: "https://upload.twitter.com".to_string(),
+            login: "https://api.x.com".to_string(),
+        }
+    }
+}
+
+impl Hosts {
+    /// Points every host at a single base URL, keeping the production path layout.
+    /// Useful for running against a local mock server.
+    pub fn with_base_url(base_url: &str) -> Self {
+        let base = base_url.trim_end_matches('/');
+        Self {
+            web: base.to_string(),
+            api: base.to_string(),
+            web_api: format!("{}/i/api", base),
+            graphql: format!("{}/i/api/graphql", base),
+            upload: base.to_string(),
+            login: base.to_string(),
+        }
+    }
+
+    pub fn graphql_url(&self, operation_path: &str) -> String {
+        format!("{}/{}", self.graphql, operation_path)
+    }
+
+    pub fn web_url(&self, path: &str) -> String {
+        format!("{}/{}", self.web, path)
+    }
+
+    pub fn api_url(&self, path: &str) -> String {
+        format!("{}/{}", self.api, path)
+    }
+
+    pub fn web_api_url(&self, path: &str) -> String {
+        format!("{}/{}", self.web_api, path)
+    }
+
+    pub fn upload_url(&self, path: &str) -> String {
+        format!("{}/{}", self.upload, path)
+    }
+
+    pub fn login_url(&self, path: &str) -> String {
+        format!("{}/{}", self.login, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_base_url() {
+        let hosts = Hosts::with_base_url("http://127.0.0.1:8080/");
+        assert_eq!(
+            hosts.graphql_url("abc/UserTweets"),
+            "http://127.0.0.1:8080/i/api/graphql/abc/UserTweets"
+        );
+        assert_eq!(
+            hosts.web_api_url("1.1/dm/new2.json"),
+            "http://127.0.0.1:8080/i/api/1.1/dm/new2.json"
+        );
+        assert_eq!(
+            hosts.upload_url("1.1/media/upload.json"),
+            "http://127.0.0.1:8080/1.1/media/upload.json"
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Hooks that see every request a client sends and every response it receives.
+
+use crate::error::Result;
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, Request, StatusCode};
+use url::Url;
+
+/// A response as passed to [`Interceptor::on_response`], or produced by
+/// [`Interceptor::on_request`] instead of sending the request.
+#[derive(Debug, Clone)]
+pub struct InterceptedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl InterceptedResponse {
+    pub fn new(status: StatusCode, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: body.into(),
+        }
+    }
+}
+
+/// Inspects and changes requests and responses, for example to add headers, collect
+/// metrics, answer from a cache or inject faults in tests.
+///
+/// Interceptors run in the order they were added, for every attempt of a request including
+/// retries. Responses then go through the client's usual handling, so an injected 503 is
+/// retried and an injected 429 counts against the rate limit.
+#[async_trait]
+pub trait Interceptor: Send + Sync {
+    /// Called with the signed request just before it is sent. Returning a response skips
+    /// sending it and the remaining interceptors' `on_request`.
+    async fn on_request(&self, _request: &mut Request) -> Result<Option<InterceptedResponse>> {
+        Ok(None)
+    }
+
+    async fn on_response(
+        &self,
+        _method: &Method,
+        _url: &Url,
+        _response: &mut InterceptedResponse,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::retry::RetryPolicy;
+    use crate::mock::MockServer;
+    use reqwest::header::HeaderValue;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct Tagger {
+        responses: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Interceptor for Tagger {
+        async fn on_request(&self, request: &mut Request) -> Result<Option<InterceptedResponse>> {
+            request
+                .headers_mut()
+                .insert("x-trace-id", HeaderValue::from_static("abc"));
+            Ok(None)
+        }
+
+        async fn on_response(
+            &self,
+            _method: &Method,
+            _url: &Url,
+            _response: &mut InterceptedResponse,
+        ) -> Result<()> {
+            self.responses.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Answers repeated GETs from memory.
+    #[derive(Default)]
+    struct Cache {
+        responses: Mutex<HashMap<String, InterceptedResponse>>,
+    }
+
+    #[async_trait]
+    impl Interceptor for Cache {
+        async fn on_request(&self, request: &mut Request) -> Result<Option<InterceptedResponse>> {
+            Ok(self
+                .responses
+                .lock()
+                .unwrap()
+                .get(request.url().as_str())
+                .cloned())
+        }
+
+        async fn on_response(
+            &self,
+            method: &Method,
+            url: &Url,
+            response: &mut InterceptedResponse,
+        ) -> Result<()> {
+            if method == Method::GET && response.status.is_success() {
+                self.responses
+                    .lock()
+                    .unwrap()
+                    .insert(url.to_string(), response.clone());
+            }
+            Ok(())
+        }
+    }
+
+    /// Fails the first request with a 503 without sending it.
+    #[derive(Default)]
+    struct Fault {
+        injected: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Interceptor for Fault {
+        async fn on_request(&self, _request: &mut Request) -> Result<Option<InterceptedResponse>> {
+            if self.injected.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Ok(Some(InterceptedResponse::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "{}",
+                )));
+            }
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_see_requests_and_responses() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        let tagger = Arc::new(Tagger::default());
+        scraper.add_interceptor(tagger.clone());
+
+        scraper.get_profile("mockuser").await.unwrap();
+
+        let requests = server.requests_to("UserByScreenName");
+        assert_eq!(requests[0].header("x-trace-id"), Some("abc"));
+        assert_eq!(tagger.responses.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_interceptor_can_answer_from_cache() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper.add_interceptor(Arc::new(Cache::default()));
+
+        let first = scraper.get_profile("mockuser").await.unwrap();
+        let second = scraper.get_profile("mockuser").await.unwrap();
+
+        assert_eq!(first.username, second.username);
+        assert_eq!(server.requests_to("UserByScreenName").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_injected_fault_is_retried() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper
+            .set_retry_policy(RetryPolicy::default().with_backoff(Duration::ZERO, Duration::ZERO));
+        let fault = Arc::new(Fault::default());
+        scraper.add_interceptor(fault.clone());
+
+        scraper.get_profile("mockuser").await.unwrap();
+
+        assert_eq!(fault.injected.load(Ordering::SeqCst), 2);
+        assert_eq!(server.requests_to("UserByScreenName").len(), 1);
+    }
+}
@@ -1,6 +1,24 @@
+pub mod cassette;
 pub mod client;
+pub mod discovery;
 pub mod endpoints;
+pub mod features;
+pub mod headers;
+pub mod hosts;
+pub mod interceptor;
+pub mod proxy;
+pub mod rate_limit;
+pub mod redact;
 pub mod requests;
-pub use client::TwitterClient;
-pub use endpoints::Endpoints;
+pub mod retry;
+pub use cassette::{Cassette, CassetteMode};
+pub use client::{HttpVersion, TwitterClient, TwitterClientBuilder};
+pub use endpoints::{Endpoints, OperationRegistry};
+pub use features::{FeaturePreset, FeatureSet};
+pub use headers::{Browser, HeaderProfile, Platform};
+pub use hosts::Hosts;
+pub use interceptor::{InterceptedResponse, Interceptor};
+pub use proxy::ProxyConfig;
+pub use rate_limit::RateLimitInfo;
+pub use retry::RetryPolicy;
 pub use reqwest::Method;
@@ -0,0 +1,117 @@
+use chrono::{DateTime, TimeZone, Utc};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Request budget reported by the `x-rate-limit-*` response headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitInfo {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_at: DateTime<Utc>,
+}
+
+impl RateLimitInfo {
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<i64>().ok())
+        };
+
+        Some(Self {
+            limit: header("x-rate-limit-limit")?.try_into().ok()?,
+            remaining: header("x-rate-limit-remaining")?.try_into().ok()?,
+            reset_at: Utc
+                .timestamp_opt(header("x-rate-limit-reset")?, 0)
+                .single()?,
+        })
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0 && self.reset_at > Utc::now()
+    }
+
+    /// Time left until the budget resets, zero if the reset is already in the past.
+    pub fn time_until_reset(&self) -> Duration {
+        time_until(self.reset_at)
+    }
+}
+
+pub(crate) fn time_until(instant: DateTime<Utc>) -> Duration {
+    (instant - Utc::now()).to_std().unwrap_or_default()
+}
+
+/// Latest budget seen per endpoint. Clones share the same map, so the clients a
+/// `Scraper` rebuilds on login keep the budgets seen so far.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimits {
+    inner: Arc<Mutex<HashMap<String, RateLimitInfo>>>,
+}
+
+impl RateLimits {
+    pub fn get(&self, endpoint: &str) -> Option<RateLimitInfo> {
+        self.inner.lock().unwrap().get(endpoint).copied()
+    }
+
+    pub fn all(&self) -> HashMap<String, RateLimitInfo> {
+        self.inner.lock().unwrap().clone()
+    }
+
+    pub(crate) fn update(&self, endpoint: &str, info: RateLimitInfo) {
+        self.inner
+            .lock()
+            .unwrap()
+            .insert(endpoint.to_string(), info);
+    }
+}
+
+/// Key under which a request's budget is tracked: the GraphQL operation name for GraphQL
+/// requests, otherwise the URL path.
+pub fn endpoint_key(url: &str) -> String {
+    let path = url::Url::parse(url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_else(|_| url.split('?').next().unwrap_or_default().to_string());
+
+    if path.contains("/graphql/") {
+        path.rsplit('/').next().unwrap_or_default().to_string()
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-rate-limit-limit", "150".parse().unwrap());
+        headers.insert("x-rate-limit-remaining", "149".parse().unwrap());
+        headers.insert("x-rate-limit-reset", "1735689600".parse().unwrap());
+
+        let info = RateLimitInfo::from_headers(&headers).unwrap();
+        assert_eq!(info.limit, 150);
+        assert_eq!(info.remaining, 149);
+        assert_eq!(info.reset_at.timestamp(), 1735689600);
+
+        headers.remove("x-rate-limit-reset");
+        assert!(RateLimitInfo::from_headers(&headers).is_none());
+    }
+
+    #[test]
+    fn test_endpoint_key() {
+        assert_eq!(
+            endpoint_key("https://x.com/i/api/graphql/abc/UserTweets?variables=%7B%7D"),
+            "UserTweets"
+        );
+        assert_eq!(
+            endpoint_key("https://api.x.com/1.1/friendships/create.json"),
+            "/1.1/friendships/create.json"
+        );
+    }
+}
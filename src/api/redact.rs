@@ -0,0 +1,188 @@
+//! Redaction of secrets from anything that is logged or written to disk.
+//!
+//! Cookies, CSRF and auth tokens, passwords, flow tokens and codes typed into login steps
+//! are replaced with [`REDACTED`]; names and structure are kept so the output stays useful.
+
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Headers whose values are secrets rather than cookie lists.
+const REDACTED_HEADERS: &[&str] = &["authorization", "x-csrf-token", "x-guest-token"];
+
+/// Headers holding cookies; only the cookie values are redacted so names stay readable.
+const COOKIE_HEADERS: &[&str] = &["cookie", "set-cookie"];
+
+/// JSON fields that carry credentials or session tokens, wherever they appear.
+const REDACTED_FIELDS: &[&str] = &[
+    "password",
+    "flow_token",
+    "auth_token",
+    "ct0",
+    "guest_token",
+    "completion_deeplink",
+    "access_token",
+    "refresh_token",
+    "code_verifier",
+    "client_secret",
+];
+
+/// Form-only secrets: the OAuth 2.0 authorization code. In JSON, `code` is an error code.
+const REDACTED_FORM_FIELDS: &[&str] = &["code"];
+
+/// Login step inputs whose `text` is a secret: TOTP, OTP and verification codes or the
+/// account's email and phone number.
+const REDACTED_TEXT_INPUTS: &[&str] = &["enter_text"];
+
+pub fn redact_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let name = name.as_str().to_lowercase();
+            let value = value.to_str().unwrap_or_default();
+            let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_string()
+            } else if COOKIE_HEADERS.contains(&name.as_str()) {
+                redact_cookies(value)
+            } else {
+                value.to_string()
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+/// Replaces cookie values in a `Cookie` or `Set-Cookie` header, keeping names and
+/// attributes intact.
+pub fn redact_cookies(value: &str) -> String {
+    let is_set_cookie_attr = |part: &str| {
+        let name = part.split('=').next().unwrap_or_default().trim();
+        matches!(
+            name.to_lowercase().as_str(),
+            "expires" | "max-age" | "domain" | "path" | "secure" | "httponly" | "samesite"
+        )
+    };
+
+    value
+        .split(';')
+        .map(|part| match part.split_once('=') {
+            Some((name, _)) if !is_set_cookie_attr(part) => format!("{}={}", name, REDACTED),
+            _ => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+pub fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) {
+                    *value = Value::String(REDACTED.to_string());
+                } else if REDACTED_TEXT_INPUTS.contains(&key.as_str()) {
+                    if let Some(text) = value.get_mut("text") {
+                        *text = Value::String(REDACTED.to_string());
+                    }
+                    redact_json(value);
+                } else {
+                    redact_json(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+/// Redacts the values of form fields named in [`REDACTED_FIELDS`] or
+/// [`REDACTED_FORM_FIELDS`].
+pub fn redact_form(fields: &mut [(String, String)]) {
+    for (name, value) in fields.iter_mut() {
+        if REDACTED_FIELDS.contains(&name.as_str()) || REDACTED_FORM_FIELDS.contains(&name.as_str())
+        {
+            *value = REDACTED.to_string();
+        }
+    }
+}
+
+/// A redacted copy of `value`.
+pub fn redacted_json(value: &Value) -> Value {
+    let mut value = value.clone();
+    redact_json(&mut value);
+    value
+}
+
+/// A response body for logging: redacted if it is JSON, otherwise only its length.
+pub fn redacted_body(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(value) => redacted_json(&value).to_string(),
+        Err(_) => format!("<{} bytes>", body.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_cookies() {
+        assert_eq!(
+            redact_cookies("auth_token=abc; ct0=def"),
+            "auth_token=[REDACTED]; ct0=[REDACTED]"
+        );
+        assert_eq!(
+            redact_cookies("ct0=def; Path=/; Domain=.x.com; Secure"),
+            "ct0=[REDACTED]; Path=/; Domain=.x.com; Secure"
+        );
+    }
+
+    #[test]
+    fn test_redact_login_step() {
+        let step = json!({
+            "flow_token": "g;123:456",
+            "subtask_inputs": [
+                {
+                    "subtask_id": "LoginEnterPassword",
+                    "enter_password": { "password": "hunter2", "link": "next_link" }
+                },
+                {
+                    "subtask_id": "LoginTwoFactorAuthChallenge",
+                    "enter_text": { "text": "123456", "link": "next_link" }
+                }
+            ]
+        });
+
+        assert_eq!(
+            redacted_json(&step),
+            json!({
+                "flow_token": REDACTED,
+                "subtask_inputs": [
+                    {
+                        "subtask_id": "LoginEnterPassword",
+                        "enter_password": { "password": REDACTED, "link": "next_link" }
+                    },
+                    {
+                        "subtask_id": "LoginTwoFactorAuthChallenge",
+                        "enter_text": { "text": REDACTED, "link": "next_link" }
+                    }
+                ]
+            })
+        );
+        assert_eq!(redacted_body("<html>"), "<6 bytes>");
+    }
+
+    #[test]
+    fn test_redact_form() {
+        let mut form = vec![
+            ("grant_type".to_string(), "refresh_token".to_string()),
+            ("refresh_token".to_string(), "secret".to_string()),
+            ("code".to_string(), "authorization-code".to_string()),
+        ];
+        redact_form(&mut form);
+        assert_eq!(form[0].1, "refresh_token");
+        assert_eq!(form[1].1, REDACTED);
+        assert_eq!(form[2].1, REDACTED);
+    }
+}
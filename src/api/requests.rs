@@ -1,10 +1,394 @@
-use crate::error::Result;
-use reqwest::multipart::Form;
-use reqwest::{header::HeaderMap, Client, Method};
+use crate::api::cassette::{
+    redact_body, CassetteBody, CassetteMode, CassetteRequest, CassetteResponse,
+};
+use crate::api::client::TwitterClient;
+use crate::api::discovery;
+use crate::api::endpoints::ApiEndpoint;
+use crate::api::interceptor::InterceptedResponse;
+use crate::api::rate_limit::{endpoint_key, time_until, RateLimitInfo};
+use crate::api::redact;
+use crate::auth::oauth2::OAuth2Auth;
+use crate::auth::relogin;
+use crate::auth::user_auth::TwitterUserAuth;
+use crate::error::{ApiError, ApiErrorKind, Result, TwitterError};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, Method, Request, Response, StatusCode};
 use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::time::Instant;
+
+/// Sends the request produced by `build`, rebuilding and re-signing it for every retry.
+/// Every request of a [`TwitterClient`] goes through here.
+///
+/// Cookies set by any response are stored in the client's session, and the endpoint's
+/// budget is updated from it. Transient failures are retried according to the client's
+/// [`RetryPolicy`](crate::api::retry::RetryPolicy). When the client has a rate-limit wait
+/// configured, an exhausted budget or a 429 whose reset falls within that wait is slept out
+/// and the request sent once more. A proxy that cannot be reached is swapped for the
+/// client's next one.
+async fn send<F>(
+    client: &TwitterClient,
+    build: F,
+    body: Option<CassetteBody>,
+) -> Result<(StatusCode, HeaderMap, String)>
+where
+    F: Fn() -> reqwest::Result<Request>,
+{
+    let build = || -> Result<Request> {
+        let mut request = build()?;
+        client.apply_header_profile(request.headers_mut());
+        client.auth.sign_request(&mut request)?;
+        Ok(request)
+    };
+    let mut request = build()?;
+    let method = request.method().clone();
+    let endpoint = endpoint_key(request.url().as_str());
+    let policy = &client.retry_policy;
+
+    if let (Some(max_wait), Some(info)) =
+        (client.rate_limit_wait, client.rate_limits.get(&endpoint))
+    {
+        if info.is_exhausted() && info.time_until_reset() <= max_wait {
+            tokio::time::sleep(info.time_until_reset()).await;
+        }
+    }
+
+    let mut attempt = 1;
+    let mut waited_for_reset = false;
+    let mut proxies_tried = 1;
+    loop {
+        tracing::trace!(
+            %method,
+            url = %request.url(),
+            attempt,
+            headers = ?redact::redact_headers(request.headers()),
+            body = ?body.clone().map(redact_body),
+            "Sending request"
+        );
+        let started = Instant::now();
+        let (proxy, http) = client.http_client();
+        let (status, headers, text) = match execute(client, &http, request, body.clone()).await {
+            // Nothing was sent, so any request can move on to the next proxy.
+            Err(TwitterError::Network(error))
+                if error.is_connect()
+                    && proxy.is_some()
+                    && proxies_tried < client.proxies.as_ref().map_or(0, |p| p.len()) =>
+            {
+                if let (Some(proxies), Some(proxy)) = (&client.proxies, proxy) {
+                    proxies.rotate_from(proxy)?;
+                }
+                proxies_tried += 1;
+                request = build()?;
+                continue;
+            }
+            Err(TwitterError::Network(error))
+                if policy.should_retry_error(&method, &error, attempt) =>
+            {
+                tokio::time::sleep(policy.backoff(attempt, None)).await;
+                attempt += 1;
+                request = build()?;
+                continue;
+            }
+            result => result?,
+        };
+        tracing::debug!(
+            %method,
+            %endpoint,
+            status = status.as_u16(),
+            attempt,
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "Request completed"
+        );
+        tracing::trace!(
+            headers = ?redact::redact_headers(&headers),
+            body = %redact::redacted_body(&text),
+            "Response"
+        );
+
+        if let Some(auth) = client.auth.as_any().downcast_ref::<TwitterUserAuth>() {
+            auth.store_cookies_from_headers(&headers).await;
+        }
+
+        let info = RateLimitInfo::from_headers(&headers);
+        if let Some(info) = info {
+            client.rate_limits.update(&endpoint, info);
+        }
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let reset_at = info.map(|info| info.reset_at);
+            match (client.rate_limit_wait, reset_at) {
+                (Some(max_wait), Some(reset_at))
+                    if !waited_for_reset && time_until(reset_at) <= max_wait =>
+                {
+                    tokio::time::sleep(time_until(reset_at)).await;
+                    waited_for_reset = true;
+                    request = build()?;
+                    continue;
+                }
+                _ => return Err(TwitterError::RateLimit { reset_at, endpoint }),
+            }
+        }
+
+        if policy.should_retry_status(&method, status, attempt) {
+            tokio::time::sleep(policy.backoff(attempt, Some(&headers))).await;
+            attempt += 1;
+            request = build()?;
+            continue;
+        }
+
+        return Ok((status, headers, text));
+    }
+}
+
+/// Sends `request` with `http` through the client's interceptors, which may answer it
+/// themselves.
+async fn execute(
+    client: &TwitterClient,
+    http: &Client,
+    mut request: Request,
+    body: Option<CassetteBody>,
+) -> Result<(StatusCode, HeaderMap, String)> {
+    let method = request.method().clone();
+    let url = request.url().clone();
+    let mut intercepted = None;
+    for interceptor in &client.interceptors {
+        intercepted = interceptor.on_request(&mut request).await?;
+        if intercepted.is_some() {
+            break;
+        }
+    }
+    let mut response = match intercepted {
+        Some(response) => response,
+        None => {
+            let (status, headers, body) = execute_http(client, http, request, body).await?;
+            InterceptedResponse {
+                status,
+                headers,
+                body,
+            }
+        }
+    };
+    for interceptor in &client.interceptors {
+        interceptor
+            .on_response(&method, &url, &mut response)
+            .await?;
+    }
+    Ok((response.status, response.headers, response.body))
+}
+
+/// Sends `request` with `http`, going through the client's cassette when one is configured.
+async fn execute_http(
+    client: &TwitterClient,
+    http: &Client,
+    request: Request,
+    body: Option<CassetteBody>,
+) -> Result<(StatusCode, HeaderMap, String)> {
+    let Some(cassette) = &client.cassette else {
+        return read_response(http.execute(request).await?).await;
+    };
+
+    let recorded = CassetteRequest::new(&request, body);
+    if cassette.mode() == CassetteMode::Replay {
+        return cassette.find(&recorded)?.into_parts();
+    }
+
+    let (status, headers, text) = read_response(http.execute(request).await?).await?;
+    cassette.record_interaction(recorded, CassetteResponse::new(status, &headers, &text))?;
+    Ok((status, headers, text))
+}
+
+/// Turns a raw response into the parsed body, or an [`ApiError`] when the status is not a
+/// success or a GraphQL response carries `errors[]` without any `data`.
+fn parse_response<T>(
+    url: &str,
+    status: StatusCode,
+    headers: HeaderMap,
+    text: &str,
+) -> Result<(T, HeaderMap)>
+where
+    T: DeserializeOwned,
+{
+    if !status.is_success() || has_only_errors(text) {
+        return Err(ApiError::new(status, url, &headers, text).into());
+    }
+
+    let parsed: T = serde_json::from_str(text)?;
+    Ok((parsed, headers))
+}
+
+fn has_only_errors(text: &str) -> bool {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(text) else {
+        return false;
+    };
+    let has_errors = value["errors"]
+        .as_array()
+        .is_some_and(|errors| !errors.is_empty());
+    let has_data = match &value["data"] {
+        serde_json::Value::Null => false,
+        serde_json::Value::Object(data) => !data.is_empty(),
+        _ => true,
+    };
+    has_errors && !has_data
+}
+
+async fn read_response(response: Response) -> Result<(StatusCode, HeaderMap, String)> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let text = response.text().await?;
+    Ok((status, headers, text))
+}
+
+/// Headers carrying the session, replaced with fresh values when a request is replayed
+/// after logging in again.
+const SESSION_HEADERS: &[&str] = &["cookie", "x-csrf-token", "x-guest-token", "authorization"];
+
+/// Runs `request` with `headers`, keeping the session usable.
+///
+/// Without a logged-in session, the request carries a guest token that is activated lazily,
+/// renewed once it is too old, and rotated when the request is rejected with 401 or 403.
+/// With OAuth 2.0, the access token is refreshed before it expires and when the request is
+/// rejected with 401. With automatic re-login configured, a request rejected for an invalid
+/// session is replayed once after logging in again.
+async fn with_session<R, F, Fut>(
+    client: &TwitterClient,
+    mut headers: HeaderMap,
+    request: F,
+) -> Result<R>
+where
+    F: Fn(HeaderMap) -> Fut,
+    Fut: Future<Output = Result<R>>,
+{
+    if let Some(auth) = client.auth.as_any().downcast_ref::<TwitterUserAuth>() {
+        if !auth.has_session().await && !relogin::is_logging_in() {
+            // A re-login clears the session before logging in; wait for it rather than
+            // sending the request as a guest.
+            if let Some(relogin) = &client.auto_relogin {
+                relogin.wait().await;
+            }
+            if !auth.has_session().await {
+                return with_guest_token(client, auth, headers, request).await;
+            }
+            headers = with_current_session(client, headers).await?;
+        }
+    }
+    if let Some(auth) = client.auth.as_any().downcast_ref::<OAuth2Auth>() {
+        return with_access_token(client, auth, headers, request).await;
+    }
+
+    let Some(relogin) = client
+        .auto_relogin
+        .as_ref()
+        .filter(|_| !relogin::is_logging_in())
+    else {
+        return request(headers).await;
+    };
+
+    let generation = relogin.generation();
+    let result = request(headers.clone()).await;
+    match &result {
+        Err(error) if relogin::is_session_rejected(error) => {}
+        _ => return result,
+    }
+    relogin.relogin(client, generation).await?;
+
+    request(with_current_session(client, headers).await?).await
+}
+
+/// `headers` with the session headers replaced by those of the current session.
+async fn with_current_session(client: &TwitterClient, mut headers: HeaderMap) -> Result<HeaderMap> {
+    let mut session = HeaderMap::new();
+    client.auth.install_headers(&mut session).await?;
+    for name in SESSION_HEADERS {
+        match session.get(*name) {
+            Some(value) => headers.insert(*name, value.clone()),
+            None => headers.remove(*name),
+        };
+    }
+    Ok(headers)
+}
+
+async fn with_guest_token<R, F, Fut>(
+    client: &TwitterClient,
+    auth: &TwitterUserAuth,
+    mut headers: HeaderMap,
+    request: F,
+) -> Result<R>
+where
+    F: Fn(HeaderMap) -> Fut,
+    Fut: Future<Output = Result<R>>,
+{
+    let token = auth.ensure_guest_token(client).await?;
+    headers.insert("x-guest-token", header_value(&token)?);
+
+    let result = request(headers.clone()).await;
+    let rejected = matches!(
+        &result,
+        Err(TwitterError::Http(error))
+            if error.status == StatusCode::FORBIDDEN || error.kind() == ApiErrorKind::AuthExpired
+    );
+    if !rejected {
+        return result;
+    }
+
+    tracing::debug!("Guest token rejected, activating a new one");
+    let token = auth.rotate_guest_token(client, &token).await?;
+    headers.insert("x-guest-token", header_value(&token)?);
+    request(headers).await
+}
+
+async fn with_access_token<R, F, Fut>(
+    client: &TwitterClient,
+    auth: &OAuth2Auth,
+    mut headers: HeaderMap,
+    request: F,
+) -> Result<R>
+where
+    F: Fn(HeaderMap) -> Fut,
+    Fut: Future<Output = Result<R>>,
+{
+    let token = auth.access_token(client).await?;
+    headers.insert(AUTHORIZATION, header_value(&format!("Bearer {}", token))?);
+
+    let result = request(headers.clone()).await;
+    let rejected = matches!(
+        &result,
+        Err(TwitterError::Http(error)) if error.status == StatusCode::UNAUTHORIZED
+    );
+    if !rejected || !auth.can_refresh() {
+        return result;
+    }
+
+    tracing::debug!("Access token rejected, refreshing it");
+    let token = auth.refresh_after(client, &token).await?;
+    headers.insert(AUTHORIZATION, header_value(&format!("Bearer {}", token))?);
+    request(headers).await
+}
+
+fn header_value(value: &str) -> Result<HeaderValue> {
+    HeaderValue::from_str(value).map_err(|e| TwitterError::Auth(e.to_string()))
+}
 
 pub async fn request_api<T>(
-    client: &Client,
+    client: &TwitterClient,
+    url: &str,
+    headers: HeaderMap,
+    method: Method,
+    body: Option<serde_json::Value>,
+) -> Result<(T, HeaderMap)>
+where
+    T: DeserializeOwned,
+{
+    with_session(client, headers, |headers| {
+        request_api_once(client, url, headers, method.clone(), body.clone())
+    })
+    .await
+}
+
+/// Sends a request as is, without guest tokens or re-login, for requests that are
+/// themselves part of managing the session.
+pub(crate) async fn request_api_once<T>(
+    client: &TwitterClient,
     url: &str,
     headers: HeaderMap,
     method: Method,
@@ -13,39 +397,109 @@ pub async fn request_api<T>(
 where
     T: DeserializeOwned,
 {
-    let mut request = client.request(method, url).headers(headers);
+    let build = || {
+        let mut request = client
+            .client
+            .request(method.clone(), url)
+            .headers(headers.clone());
+        if let Some(json_body) = &body {
+            request = request.json(json_body);
+        }
+        request.build()
+    };
 
-    if let Some(json_body) = body {
-        request = request.json(&json_body);
+    let (status, headers, text) = send(client, build, body.clone().map(CassetteBody::Json)).await?;
+
+    parse_response(url, status, headers, &text)
+}
+
+/// Sends a GraphQL request, with its parameters in the query string or body according to
+/// the endpoint's method. With `operation_discovery` enabled, a request rejected for a stale
+/// query ID is retried once after refreshing the registry from the web client bundle.
+///
+/// Fails with [`TwitterError::Unsupported`] for official API auth, which has no access to
+/// the GraphQL API.
+pub async fn request_graphql<T>(
+    client: &TwitterClient,
+    endpoint: &ApiEndpoint,
+    headers: HeaderMap,
+) -> Result<(T, HeaderMap)>
+where
+    T: DeserializeOwned,
+{
+    if client.auth.is_official_api() {
+        return Err(TwitterError::Unsupported(format!(
+            "{} is a web client operation and needs cookie authentication",
+            endpoint.operation.as_deref().unwrap_or("GraphQL request")
+        )));
     }
 
-    let response = request.send().await?;
+    let result = request_graphql_once(client, endpoint, headers.clone()).await;
 
-    if response.status().is_success() {
-        let headers = response.headers().clone();
-        let text = response.text().await?;
-        let parsed: T = serde_json::from_str(&text)?;
-        Ok((parsed, headers))
-    } else {
-        let status = response.status();
-        println!("{}", response.text().await?);
-        Err(crate::error::TwitterError::Api(format!(
-            "Request failed with status: {}",
-            status
-        )))
+    let Some(name) = &endpoint.operation else {
+        return result;
+    };
+    if !client.operation_discovery || !is_stale_query_id(&result) {
+        return result;
+    }
+    if !discovery::refresh_operations(client).await?.contains(name) {
+        return result;
     }
+
+    let mut endpoint = endpoint.clone();
+    endpoint.url = client.graphql_endpoint(name).url;
+    request_graphql_once(client, &endpoint, headers).await
 }
 
-pub async fn get_guest_token(client: &Client, bearer_token: &str) -> Result<String> {
+async fn request_graphql_once<T>(
+    client: &TwitterClient,
+    endpoint: &ApiEndpoint,
+    headers: HeaderMap,
+) -> Result<(T, HeaderMap)>
+where
+    T: DeserializeOwned,
+{
+    request_api(
+        client,
+        &endpoint.to_request_url(),
+        headers,
+        endpoint.method.clone(),
+        endpoint.to_request_body(),
+    )
+    .await
+}
+
+/// A 404 without an error body is how X answers a query ID it has rotated away.
+fn is_stale_query_id<T>(result: &Result<T>) -> bool {
+    matches!(
+        result,
+        Err(TwitterError::Http(error))
+            if error.status == StatusCode::NOT_FOUND && error.errors.is_empty()
+    )
+}
+
+/// Fetches a non-JSON resource, such as a web page or script, as text.
+pub async fn request_text(client: &TwitterClient, url: &str, headers: HeaderMap) -> Result<String> {
+    let build = || client.client.get(url).headers(headers.clone()).build();
+    let (status, headers, text) = send(client, build, None).await?;
+
+    if !status.is_success() {
+        return Err(ApiError::new(status, url, &headers, &text).into());
+    }
+    Ok(text)
+}
+
+pub async fn get_guest_token(client: &TwitterClient, bearer_token: &str) -> Result<String> {
     let mut headers = HeaderMap::new();
     headers.insert(
         "Authorization",
         format!("Bearer {}", bearer_token).parse().unwrap(),
     );
 
-    let (response, _) = request_api::<serde_json::Value>(
+    // Sent directly: activation is itself part of keeping the session usable.
+    let (response, _) = request_api_once::<serde_json::Value>(
         client,
-        "https://api.x.com/1.1/guest/activate.json",
+        &client.hosts.login_url("1.1/guest/activate.json"),
         headers,
         Method::POST,
         None,
@@ -59,37 +513,84 @@ pub async fn get_guest_token(client: &Client, bearer_token: &str) -> Result<Stri
         .ok_or_else(|| crate::error::TwitterError::Auth("Failed to get guest token".into()))
 }
 
+/// A multipart body that, unlike [`Form`], can be rebuilt for every retry.
+#[derive(Debug, Clone, Default)]
+pub struct MultipartForm {
+    fields: Vec<(String, MultipartValue)>,
+}
+
+#[derive(Debug, Clone)]
+enum MultipartValue {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl MultipartForm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields
+            .push((name.into(), MultipartValue::Text(value.into())));
+        self
+    }
+
+    pub fn bytes(mut self, name: impl Into<String>, data: Vec<u8>) -> Self {
+        self.fields.push((name.into(), MultipartValue::Bytes(data)));
+        self
+    }
+
+    fn to_form(&self) -> Form {
+        self.fields
+            .iter()
+            .fold(Form::new(), |form, (name, value)| match value {
+                MultipartValue::Text(text) => form.text(name.clone(), text.clone()),
+                MultipartValue::Bytes(data) => form.part(name.clone(), Part::bytes(data.clone())),
+            })
+    }
+}
+
 pub async fn request_multipart_api<T>(
-    client: &Client,
+    client: &TwitterClient,
+    url: &str,
+    headers: HeaderMap,
+    form: MultipartForm,
+) -> Result<(T, HeaderMap)>
+where
+    T: DeserializeOwned,
+{
+    with_session(client, headers, |headers| {
+        request_multipart_api_once(client, url, headers, &form)
+    })
+    .await
+}
+
+async fn request_multipart_api_once<T>(
+    client: &TwitterClient,
     url: &str,
     headers: HeaderMap,
-    form: Form,
+    form: &MultipartForm,
 ) -> Result<(T, HeaderMap)>
 where
     T: DeserializeOwned,
 {
-    let request = client
-        .request(Method::POST, url)
-        .headers(headers)
-        .multipart(form);
+    let build = || {
+        client
+            .client
+            .request(Method::POST, url)
+            .headers(headers.clone())
+            .multipart(form.to_form())
+            .build()
+    };
 
-    let response = request.send().await?;
+    let (status, headers, text) = send(client, build, Some(CassetteBody::Multipart)).await?;
 
-    if response.status().is_success() {
-        let headers = response.headers().clone();
-        let text = response.text().await?;
-        let parsed: T = serde_json::from_str(&text)?;
-        Ok((parsed, headers))
-    } else {
-        Err(crate::error::TwitterError::Api(format!(
-            "Request failed with status: {}",
-            response.status()
-        )))
-    }
+    parse_response(url, status, headers, &text)
 }
 
 pub async fn request_form_api<T>(
-    client: &Client,
+    client: &TwitterClient,
     url: &str,
     headers: HeaderMap,
     form_data: Vec<(String, String)>,
@@ -97,22 +598,259 @@ pub async fn request_form_api<T>(
 where
     T: DeserializeOwned,
 {
-    let request = client
-        .request(Method::POST, url)
-        .headers(headers)
-        .form(&form_data);
-
-    let response = request.send().await?;
-
-    if response.status().is_success() {
-        let headers = response.headers().clone();
-        let text = response.text().await?;
-        let parsed: T = serde_json::from_str(&text)?;
-        Ok((parsed, headers))
-    } else {
-        Err(crate::error::TwitterError::Api(format!(
-            "Request failed with status: {}",
-            response.status()
-        )))
+    with_session(client, headers, |headers| {
+        request_form_api_once(client, url, headers, &form_data)
+    })
+    .await
+}
+
+/// Sends a form request as is, like [`request_api_once`].
+pub(crate) async fn request_form_api_once<T>(
+    client: &TwitterClient,
+    url: &str,
+    headers: HeaderMap,
+    form_data: &[(String, String)],
+) -> Result<(T, HeaderMap)>
+where
+    T: DeserializeOwned,
+{
+    let build = || {
+        client
+            .client
+            .request(Method::POST, url)
+            .headers(headers.clone())
+            .form(form_data)
+            .build()
+    };
+
+    let (status, headers, text) =
+        send(client, build, Some(CassetteBody::Form(form_data.to_vec()))).await?;
+
+    parse_response(url, status, headers, &text)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::proxy::ProxyConfig;
+    use crate::api::retry::RetryPolicy;
+    use crate::auth::config::AuthConfig;
+    use crate::constants::BEARER_TOKEN;
+    use crate::error::{ApiErrorKind, TwitterError};
+    use crate::mock::{MockResponse, MockServer, MOCK_RATE_LIMIT};
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_error_response_is_structured() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+        server.mock(
+            "UserByScreenName",
+            MockResponse::json(
+                403,
+                json!({"errors": [{"code": 64, "message": "Your account is suspended."}]}),
+            )
+            .with_header("x-rate-limit-limit", "95")
+            .with_header("x-rate-limit-remaining", "0")
+            .with_header("x-rate-limit-reset", "1735689600"),
+        );
+
+        let error = scraper.get_profile("mockuser").await.unwrap_err();
+        let api_error = error.api_error().unwrap();
+
+        assert_eq!(api_error.status.as_u16(), 403);
+        assert_eq!(api_error.operation.as_deref(), Some("UserByScreenName"));
+        assert_eq!(api_error.errors[0].code, Some(64));
+        assert_eq!(api_error.kind(), ApiErrorKind::Suspended);
+        assert_eq!(api_error.rate_limit.unwrap().remaining, 0);
+    }
+
+    #[tokio::test]
+    async fn test_graphql_errors_without_data() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+        server.mock(
+            "CreateTweet",
+            MockResponse::json(
+                200,
+                json!({
+                    "errors": [{
+                        "code": 187,
+                        "message": "Authorization: Status is a duplicate. (187)",
+                        "kind": "Permissions"
+                    }],
+                    "data": {}
+                }),
+            ),
+        );
+
+        let error = scraper.send_tweet("hello", None, None).await.unwrap_err();
+
+        assert_eq!(
+            error.api_error().unwrap().kind(),
+            ApiErrorKind::DuplicateStatus
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_budget_is_tracked() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        scraper.get_profile("mockuser").await.unwrap();
+        scraper.get_profile("mockuser").await.unwrap();
+
+        let budget = scraper.rate_limit("UserByScreenName").unwrap();
+        assert_eq!(budget.limit, MOCK_RATE_LIMIT);
+        assert_eq!(budget.remaining, MOCK_RATE_LIMIT - 2);
+        assert!(scraper.rate_limit("UserTweets").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_too_many_requests() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        let reset = chrono::Utc::now().timestamp() + 1;
+        let rate_limited = MockResponse::json(429, json!({"errors": [{"code": 88}]}))
+            .with_header("x-rate-limit-limit", "95")
+            .with_header("x-rate-limit-remaining", "0")
+            .with_header("x-rate-limit-reset", &reset.to_string());
+
+        server.mock_once("UserByScreenName", rate_limited.clone());
+        match scraper.get_profile("mockuser").await {
+            Err(TwitterError::RateLimit { reset_at, endpoint }) => {
+                assert_eq!(endpoint, "UserByScreenName");
+                assert_eq!(reset_at.unwrap().timestamp(), reset);
+            }
+            other => panic!("expected rate limit error, got {:?}", other),
+        }
+
+        scraper.set_rate_limit_wait(Some(Duration::from_secs(5)));
+        server.mock_once("UserByScreenName", rate_limited);
+        scraper.get_profile("mockuser").await.unwrap();
+        assert_eq!(server.requests_to("UserByScreenName").len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper
+            .set_retry_policy(RetryPolicy::default().with_backoff(Duration::ZERO, Duration::ZERO));
+
+        server.mock_once("UserByScreenName", MockResponse::json(503, json!({})));
+        server.mock_once("UserByScreenName", MockResponse::json(502, json!({})));
+        scraper.get_profile("mockuser").await.unwrap();
+        assert_eq!(server.requests_to("UserByScreenName").len(), 3);
+
+        // CreateTweet is a POST and may already have taken effect, so it is not retried.
+        server.mock_once("CreateTweet", MockResponse::json(503, json!({})));
+        assert!(scraper.send_tweet("hello", None, None).await.is_err());
+        assert_eq!(server.requests_to("CreateTweet").len(), 1);
+    }
+
+    fn credentials() -> AuthConfig {
+        AuthConfig::new(BEARER_TOKEN.to_string()).with_credentials(
+            "mockuser".into(),
+            "password".into(),
+            Some("mock@example.com".into()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_rejected_session_logs_in_again() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        let expired = MockResponse::json(
+            401,
+            json!({"errors": [{"code": 89, "message": "Invalid or expired token."}]}),
+        );
+
+        server.mock_once("UserByScreenName", expired.clone());
+        match scraper.get_profile("mockuser").await {
+            Err(TwitterError::Http(error)) => assert_eq!(error.kind(), ApiErrorKind::AuthExpired),
+            other => panic!("expected auth error, got {:?}", other),
+        }
+        assert!(server.requests_to("/1.1/onboarding/task.json").is_empty());
+
+        scraper.enable_auto_relogin(credentials());
+        server.mock_once("UserByScreenName", expired.clone());
+        scraper.get_profile("mockuser").await.unwrap();
+        assert!(!server.requests_to("/1.1/onboarding/task.json").is_empty());
+        assert_eq!(server.requests_to("UserByScreenName").len(), 3);
+
+        // The replay happens once; a session rejected again is reported.
+        server.mock("UserByScreenName", expired);
+        assert!(scraper.get_profile("mockuser").await.is_err());
+        assert_eq!(server.requests_to("UserByScreenName").len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_failed_relogin_is_reported() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper.enable_auto_relogin(credentials());
+
+        let expired = MockResponse::json(401, json!({"errors": [{"code": 32}]}));
+        server.mock("UserByScreenName", expired.clone());
+        server.mock("/1.1/onboarding/task.json", expired);
+
+        match scraper.get_profile("mockuser").await {
+            Err(TwitterError::Http(error)) => {
+                assert_eq!(error.endpoint, "/1.1/onboarding/task.json")
+            }
+            other => panic!("expected login error, got {:?}", other),
+        }
+        assert_eq!(server.requests_to("UserByScreenName").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_wait_for_relogin() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper.enable_auto_relogin(credentials());
+        server.delay("/1.1/onboarding/task.json", Duration::from_millis(50));
+        server.mock_once(
+            "UserByScreenName",
+            MockResponse::json(401, json!({"errors": [{"code": 32}]})),
+        );
+
+        let during_login = async {
+            while server.requests_to("/1.1/onboarding/task.json").is_empty() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            scraper.get_profile("mockuser").await
+        };
+        let (first, second) = tokio::join!(scraper.get_profile("mockuser"), during_login);
+        first.unwrap();
+        second.unwrap();
+
+        // The request sent while logging in waited for the new session instead of going
+        // out as a guest; only the login activated a guest token.
+        assert_eq!(server.requests_to("/1.1/guest/activate.json").len(), 1);
+        let requests = server.requests_to("UserByScreenName");
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1..].iter().all(|request| request
+            .header("cookie")
+            .is_some_and(|cookie| cookie.contains("auth_token="))));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_proxy_rotates_to_next() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let dead = ProxyConfig::new(format!("http://{}", closed.local_addr().unwrap()));
+        drop(closed);
+        let live = ProxyConfig::new(server.url());
+        scraper
+            .set_proxies(vec![dead.clone(), live.clone()], "a")
+            .unwrap();
+        assert_eq!(scraper.current_proxy(), Some(dead));
+
+        let profile = scraper.get_profile("mockuser").await.unwrap();
+
+        assert_eq!(profile.username, "mockuser");
+        assert_eq!(scraper.current_proxy(), Some(live));
     }
 }
@@ -0,0 +1,191 @@
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+use std::time::Duration;
+
+/// When and how often failed requests are retried.
+///
+/// Requests with an idempotent method are retried on retryable statuses, timeouts and
+/// connection failures. Other requests (e.g. the POST behind `CreateTweet`) may already have
+/// taken effect when a response goes missing, so they are only retried when the connection
+/// could not be established, unless `retry_non_idempotent` is set.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts per request, including the first one.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// Randomises each delay between half and all of its backoff.
+    pub jitter: bool,
+    pub retryable_statuses: Vec<StatusCode>,
+    pub retry_on_timeout: bool,
+    pub retry_on_connect: bool,
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: true,
+            retryable_statuses: vec![
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_on_timeout: true,
+            retry_on_connect: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_retryable_statuses(mut self, statuses: Vec<StatusCode>) -> Self {
+        self.retryable_statuses = statuses;
+        self
+    }
+
+    pub fn with_retry_non_idempotent(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
+    /// Whether a request that got `status` back on attempt `attempt` (1-based) is retried.
+    pub fn should_retry_status(&self, method: &Method, status: StatusCode, attempt: u32) -> bool {
+        attempt < self.max_attempts
+            && self.retryable_statuses.contains(&status)
+            && (self.retry_non_idempotent || is_idempotent(method))
+    }
+
+    /// Whether a request that failed with `error` on attempt `attempt` (1-based) is retried.
+    pub fn should_retry_error(
+        &self,
+        method: &Method,
+        error: &reqwest::Error,
+        attempt: u32,
+    ) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+        if error.is_connect() {
+            return self.retry_on_connect;
+        }
+        let transient = (error.is_timeout() && self.retry_on_timeout)
+            || (error.is_request() && self.retry_on_connect);
+        transient && (self.retry_non_idempotent || is_idempotent(method))
+    }
+
+    /// Delay before attempt `attempt + 1`. A `Retry-After` header, when present, takes
+    /// precedence over the computed backoff; both are capped at `max_backoff`.
+    pub fn backoff(&self, attempt: u32, headers: Option<&HeaderMap>) -> Duration {
+        if let Some(retry_after) = headers.and_then(retry_after) {
+            return retry_after.min(self.max_backoff);
+        }
+
+        let exponent = attempt.saturating_sub(1).min(30) as i32;
+        let delay = self
+            .initial_backoff
+            .mul_f64(self.multiplier.powi(exponent))
+            .min(self.max_backoff);
+
+        if self.jitter {
+            delay / 2 + delay.mul_f64(fastrand::f64() / 2.0)
+        } else {
+            delay
+        }
+    }
+}
+
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE
+    )
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get("retry-after")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(500))
+            .with_jitter(false);
+
+        assert_eq!(policy.backoff(1, None), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2, None), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3, None), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4, None), Duration::from_millis(500));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", "0".parse().unwrap());
+        assert_eq!(policy.backoff(1, Some(&headers)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_jitter_stays_within_half_of_backoff() {
+        let policy =
+            RetryPolicy::default().with_backoff(Duration::from_secs(1), Duration::from_secs(1));
+
+        for _ in 0..100 {
+            let delay = policy.backoff(1, None);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn test_non_idempotent_requests_are_not_retried_on_status() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy.should_retry_status(&Method::GET, StatusCode::BAD_GATEWAY, 1));
+        assert!(!policy.should_retry_status(&Method::GET, StatusCode::BAD_GATEWAY, 3));
+        assert!(!policy.should_retry_status(&Method::GET, StatusCode::BAD_REQUEST, 1));
+        assert!(!policy.should_retry_status(&Method::POST, StatusCode::BAD_GATEWAY, 1));
+        assert!(policy.with_retry_non_idempotent(true).should_retry_status(
+            &Method::POST,
+            StatusCode::BAD_GATEWAY,
+            1
+        ));
+    }
+}
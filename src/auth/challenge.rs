@@ -0,0 +1,127 @@
+//! Letting a person answer login steps the stored credentials cannot.
+
+use crate::error::{LoginError, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeKind {
+    /// `LoginAcid` asking for the account's email.
+    Email,
+    /// `LoginAcid` or `LoginEnterAlternateIdentifierSubtask` asking for a phone number.
+    PhoneNumber,
+    /// `LoginAcid` asking for a code X sent by email or SMS.
+    VerificationCode,
+    /// `LoginEnterOtp`: a one-time code X sent by email or SMS.
+    Otp,
+    /// `LoginEnterAlternateIdentifierSubtask`: the account's email or phone number.
+    AlternateIdentifier,
+    /// `LoginTwoFactorAuthChallenge` when no TOTP secret was given.
+    TwoFactorCode,
+    /// `LoginTwoFactorAuthChallenge` asking for a backup code.
+    BackupCode,
+    /// `LoginTwoFactorAuthChooseMethod`: answer with the `id` of one of the offered choices.
+    TwoFactorMethod,
+    /// A dialog asking to confirm a login X considers suspicious.
+    SuspiciousLogin,
+    /// `ArkoseLogin`: a captcha to solve in a browser.
+    Captcha,
+    /// A subtask this crate does not know how to answer.
+    Unknown,
+}
+
+impl ChallengeKind {
+    /// The error returned when no handler answers a challenge of this kind.
+    pub fn unanswered(&self, subtask_id: &str) -> LoginError {
+        match self {
+            ChallengeKind::Email | ChallengeKind::AlternateIdentifier => LoginError::EmailRequired,
+            ChallengeKind::PhoneNumber => LoginError::PhoneNumberRequired,
+            ChallengeKind::VerificationCode => LoginError::VerificationCodeRequired,
+            ChallengeKind::Otp => LoginError::OtpRequired,
+            ChallengeKind::TwoFactorCode => LoginError::TwoFactorRequired,
+            ChallengeKind::BackupCode => LoginError::BackupCodeRequired,
+            ChallengeKind::TwoFactorMethod => LoginError::TwoFactorMethodRequired,
+            ChallengeKind::SuspiciousLogin => LoginError::SuspiciousLogin,
+            ChallengeKind::Captcha => LoginError::CaptchaRequired,
+            ChallengeKind::Unknown => LoginError::UnhandledSubtask(subtask_id.to_string()),
+        }
+    }
+}
+
+/// A login step waiting for an answer.
+#[derive(Debug, Clone)]
+pub struct LoginChallenge {
+    pub kind: ChallengeKind,
+    pub subtask_id: String,
+    /// The subtask as sent by X, including any prompt text.
+    pub subtask: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChallengeResponse {
+    /// Text entered into the step. For [`ChallengeKind::Captcha`], the solved token; for
+    /// [`ChallengeKind::TwoFactorMethod`], the id of the chosen method.
+    Text(String),
+    /// Follows the step's primary link, confirming a dialog.
+    Confirm,
+    /// A complete `subtask_inputs` entry, sent as is.
+    SubtaskInput(Value),
+    /// Stops the login with [`LoginError::Aborted`].
+    Abort,
+}
+
+/// Answers login challenges, for example by prompting on a terminal.
+#[async_trait]
+pub trait LoginChallengeHandler: Send + Sync {
+    async fn handle(&self, challenge: &LoginChallenge) -> Result<ChallengeResponse>;
+}
+
+/// Components of a dialog subtask that are confirmed by following `next_link`.
+const DIALOG_COMPONENTS: &[&str] = &["alert_dialog", "cta", "open_link", "menu_dialog"];
+
+impl LoginChallenge {
+    /// The `subtask_inputs` entry answering this challenge with `response`, or `None` for
+    /// [`ChallengeResponse::Abort`].
+    pub(crate) fn input(&self, response: ChallengeResponse) -> Option<Value> {
+        let input = match response {
+            ChallengeResponse::Text(token) if self.kind == ChallengeKind::Captcha => json!({
+                "subtask_id": self.subtask_id,
+                "web_modal": {
+                    "completion_deeplink": format!(
+                        "twitter://onboarding/web_modal/next_link?access_token={}",
+                        token
+                    ),
+                    "link": "next_link"
+                }
+            }),
+            ChallengeResponse::Text(choice) if self.kind == ChallengeKind::TwoFactorMethod => {
+                json!({
+                    "subtask_id": self.subtask_id,
+                    "choice_selection": {
+                        "selected_choices": [choice],
+                        "link": "next_link"
+                    }
+                })
+            }
+            ChallengeResponse::Text(text) => json!({
+                "subtask_id": self.subtask_id,
+                "enter_text": {
+                    "text": text,
+                    "link": "next_link"
+                }
+            }),
+            ChallengeResponse::Confirm => {
+                let component = DIALOG_COMPONENTS
+                    .iter()
+                    .find(|component| self.subtask.get(**component).is_some())
+                    .unwrap_or(&"alert_dialog");
+                let mut input = json!({ "subtask_id": self.subtask_id });
+                input[*component] = json!({ "link": "next_link" });
+                input
+            }
+            ChallengeResponse::SubtaskInput(input) => input,
+            ChallengeResponse::Abort => return None,
+        };
+        Some(input)
+    }
+}
@@ -1,3 +1,4 @@
+#[derive(Clone)]
 pub struct AuthConfig {
     pub username: Option<String>,
     pub password: Option<String>,
@@ -28,4 +29,9 @@ impl AuthConfig {
         self.email = email;
         self
     }
+
+    pub fn with_two_factor_secret(mut self, secret: String) -> Self {
+        self.two_factor_secret = Some(secret);
+        self
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,243 @@
+//! Session expiry tracking and health events.
+
+use crate::auth::session::StoredCookie;
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// Cookies a logged-in session cannot work without.
+pub const SESSION_COOKIES: &[&str] = &["auth_token", "ct0"];
+
+/// How long before expiry a session is reported as [`SessionStatus::Expiring`] by default.
+pub const DEFAULT_EXPIRY_WARNING: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionStatus {
+    Valid,
+    /// Still valid, but `auth_token` or `ct0` expires within the warning window.
+    Expiring,
+    /// The session cookies are missing or expired, or X rejected them.
+    Expired,
+    /// X reported the account as locked (code 326).
+    Locked,
+}
+
+impl SessionStatus {
+    /// Whether requests made with this session are expected to succeed.
+    pub fn is_usable(&self) -> bool {
+        matches!(self, SessionStatus::Valid | SessionStatus::Expiring)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// The session entered the expiry warning window.
+    Expiring { expires_at: DateTime<Utc> },
+    /// A usable session stopped working.
+    Invalidated { status: SessionStatus },
+}
+
+/// Status implied by the session cookies alone, and the earliest session cookie expiry.
+pub fn status_from_cookies(
+    cookies: &[StoredCookie],
+    warning: Duration,
+) -> (SessionStatus, Option<DateTime<Utc>>) {
+    let mut expires_at: Option<DateTime<Utc>> = None;
+
+    for name in SESSION_COOKIES {
+        let Some(cookie) = cookies.iter().find(|cookie| cookie.name == *name) else {
+            return (SessionStatus::Expired, None);
+        };
+        if let Some(expires) = cookie.expires {
+            expires_at = Some(expires_at.map_or(expires, |current| current.min(expires)));
+        }
+    }
+
+    let now = Utc::now();
+    let warning = chrono::Duration::from_std(warning).unwrap_or(chrono::Duration::MAX);
+    let status = match expires_at {
+        Some(expires) if expires <= now => SessionStatus::Expired,
+        Some(expires) if expires - now <= warning => SessionStatus::Expiring,
+        _ => SessionStatus::Valid,
+    };
+    (status, expires_at)
+}
+
+#[derive(Debug)]
+struct HealthState {
+    /// Set when X rejected the session; cleared when new cookies are installed.
+    rejected: Option<SessionStatus>,
+    last_status: Option<SessionStatus>,
+    expiry_warning: Duration,
+}
+
+/// Health of one session, shared by clones of the owning auth.
+#[derive(Debug, Clone)]
+pub(crate) struct SessionHealth {
+    state: Arc<Mutex<HealthState>>,
+    events: broadcast::Sender<SessionEvent>,
+}
+
+impl Default for SessionHealth {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(HealthState {
+                rejected: None,
+                last_status: None,
+                expiry_warning: DEFAULT_EXPIRY_WARNING,
+            })),
+            events: broadcast::channel(16).0,
+        }
+    }
+}
+
+impl SessionHealth {
+    pub fn expiry_warning(&self) -> Duration {
+        self.state.lock().unwrap().expiry_warning
+    }
+
+    pub fn set_expiry_warning(&self, warning: Duration) {
+        self.state.lock().unwrap().expiry_warning = warning;
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn reject(&self, status: SessionStatus) {
+        self.state.lock().unwrap().rejected = Some(status);
+    }
+
+    pub fn clear_rejection(&self) {
+        self.state.lock().unwrap().rejected = None;
+    }
+
+    /// Combines the cookie-derived status with any rejection and emits an event if the
+    /// session just entered the warning window or stopped being usable.
+    pub fn observe(
+        &self,
+        cookie_status: SessionStatus,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> SessionStatus {
+        let mut state = self.state.lock().unwrap();
+        let status = state.rejected.unwrap_or(cookie_status);
+        let previous = state.last_status.replace(status);
+        if previous == Some(status) {
+            return status;
+        }
+
+        let event = match (status, expires_at) {
+            (SessionStatus::Expiring, Some(expires_at)) => {
+                Some(SessionEvent::Expiring { expires_at })
+            }
+            (SessionStatus::Expired | SessionStatus::Locked, _)
+                if previous.is_some_and(|previous| previous.is_usable()) =>
+            {
+                Some(SessionEvent::Invalidated { status })
+            }
+            _ => None,
+        };
+        if let Some(event) = event {
+            tracing::info!(?event, "Session status changed");
+            let _ = self.events.send(event);
+        }
+        status
+    }
+}
+
+/// Background task validating a session periodically. Stops when dropped.
+#[derive(Debug)]
+pub struct SessionMonitor {
+    pub(crate) handle: JoinHandle<()>,
+}
+
+impl SessionMonitor {
+    pub fn stop(self) {}
+
+    pub fn is_running(&self) -> bool {
+        !self.handle.is_finished()
+    }
+}
+
+impl Drop for SessionMonitor {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookie(name: &str, expires_in: Option<chrono::Duration>) -> StoredCookie {
+        StoredCookie {
+            name: name.into(),
+            value: "value".into(),
+            domain: Some(".x.com".into()),
+            path: Some("/".into()),
+            expires: expires_in.map(|expires_in| Utc::now() + expires_in),
+            max_age: None,
+            secure: true,
+            http_only: true,
+            same_site: None,
+        }
+    }
+
+    #[test]
+    fn test_status_from_cookies() {
+        let day = chrono::Duration::days(1);
+        let check =
+            |cookies: &[StoredCookie]| status_from_cookies(cookies, DEFAULT_EXPIRY_WARNING).0;
+
+        assert_eq!(
+            check(&[cookie("auth_token", None), cookie("ct0", None)]),
+            SessionStatus::Valid
+        );
+        assert_eq!(
+            check(&[
+                cookie("auth_token", Some(day * 365)),
+                cookie("ct0", Some(day))
+            ]),
+            SessionStatus::Expiring
+        );
+        assert_eq!(
+            check(&[cookie("auth_token", Some(-day)), cookie("ct0", None)]),
+            SessionStatus::Expired
+        );
+        assert_eq!(check(&[cookie("ct0", None)]), SessionStatus::Expired);
+    }
+
+    #[test]
+    fn test_events_on_transitions() {
+        let health = SessionHealth::default();
+        let mut events = health.subscribe();
+        let expires_at = Utc::now();
+
+        assert_eq!(
+            health.observe(SessionStatus::Expired, None),
+            SessionStatus::Expired
+        );
+        health.observe(SessionStatus::Valid, None);
+        health.observe(SessionStatus::Expiring, Some(expires_at));
+        health.observe(SessionStatus::Expiring, Some(expires_at));
+        health.reject(SessionStatus::Locked);
+        assert_eq!(
+            health.observe(SessionStatus::Valid, None),
+            SessionStatus::Locked
+        );
+
+        assert_eq!(
+            events.try_recv().unwrap(),
+            SessionEvent::Expiring { expires_at }
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            SessionEvent::Invalidated {
+                status: SessionStatus::Locked
+            }
+        );
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -1,3 +1,10 @@
+pub mod challenge;
+pub mod health;
+pub mod oauth1;
+pub mod oauth2;
+pub mod relogin;
+pub mod session;
+pub mod store;
 pub mod user_auth;
 pub mod config;
 
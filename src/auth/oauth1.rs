@@ -0,0 +1,240 @@
+//! OAuth 1.0a user-context auth for the official X API.
+
+use crate::api::redact::REDACTED;
+use crate::auth::user_auth::TwitterAuth;
+use crate::error::{Result, TwitterError};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Method, Request};
+use sha1::Sha1;
+use std::any::Any;
+use std::fmt;
+use url::Url;
+
+/// Signs every request with HMAC-SHA1 using an app's consumer key and a user's access token.
+#[derive(Clone)]
+pub struct OAuth1Auth {
+    consumer_key: String,
+    consumer_secret: String,
+    access_token: String,
+    access_token_secret: String,
+}
+
+impl OAuth1Auth {
+    pub fn new(
+        consumer_key: impl Into<String>,
+        consumer_secret: impl Into<String>,
+        access_token: impl Into<String>,
+        access_token_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            consumer_key: consumer_key.into(),
+            consumer_secret: consumer_secret.into(),
+            access_token: access_token.into(),
+            access_token_secret: access_token_secret.into(),
+        }
+    }
+
+    /// The `Authorization` header for a request. `params` are the decoded query and
+    /// url-encoded form parameters; the query of `url` itself is ignored.
+    pub fn authorization_header(
+        &self,
+        method: &Method,
+        url: &Url,
+        params: &[(String, String)],
+        nonce: &str,
+        timestamp: i64,
+    ) -> String {
+        let mut oauth_params = vec![
+            ("oauth_consumer_key", self.consumer_key.clone()),
+            ("oauth_nonce", nonce.to_string()),
+            ("oauth_signature_method", "HMAC-SHA1".to_string()),
+            ("oauth_timestamp", timestamp.to_string()),
+            ("oauth_token", self.access_token.clone()),
+            ("oauth_version", "1.0".to_string()),
+        ];
+
+        let mut encoded: Vec<(String, String)> = params
+            .iter()
+            .map(|(name, value)| (encode(name), encode(value)))
+            .chain(
+                oauth_params
+                    .iter()
+                    .map(|(name, value)| (encode(name), encode(value))),
+            )
+            .collect();
+        encoded.sort();
+        let parameter_string = encoded
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let base_string = format!(
+            "{}&{}&{}",
+            method.as_str().to_uppercase(),
+            encode(&base_url(url)),
+            encode(&parameter_string)
+        );
+        let key = format!(
+            "{}&{}",
+            encode(&self.consumer_secret),
+            encode(&self.access_token_secret)
+        );
+        let mut mac = Hmac::<Sha1>::new_from_slice(key.as_bytes()).expect("HMAC takes any key");
+        mac.update(base_string.as_bytes());
+        oauth_params.push((
+            "oauth_signature",
+            STANDARD.encode(mac.finalize().into_bytes()),
+        ));
+
+        let fields = oauth_params
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"", encode(name), encode(value)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("OAuth {}", fields)
+    }
+}
+
+impl fmt::Debug for OAuth1Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth1Auth")
+            .field("consumer_key", &self.consumer_key)
+            .field("consumer_secret", &REDACTED)
+            .field("access_token", &REDACTED)
+            .field("access_token_secret", &REDACTED)
+            .finish()
+    }
+}
+
+/// RFC 3986 percent-encoding, as required for every part of the signature base string.
+fn encode(value: &str) -> String {
+    urlencoding::encode(value).into_owned()
+}
+
+/// Scheme, host, non-default port and path, without query or fragment.
+fn base_url(url: &Url) -> String {
+    let mut base = format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default());
+    if let Some(port) = url.port() {
+        base.push_str(&format!(":{}", port));
+    }
+    base.push_str(url.path());
+    base
+}
+
+/// Query parameters, plus the body's parameters when it is url-encoded.
+fn signed_params(request: &Request) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = request
+        .url()
+        .query_pairs()
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+
+    let is_form = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+    if let Some(body) = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .filter(|_| is_form)
+    {
+        params.extend(
+            url::form_urlencoded::parse(body)
+                .map(|(name, value)| (name.into_owned(), value.into_owned())),
+        );
+    }
+    params
+}
+
+#[async_trait]
+impl TwitterAuth for OAuth1Auth {
+    async fn install_headers(&self, headers: &mut HeaderMap) -> Result<()> {
+        headers.insert("accept", HeaderValue::from_static("*/*"));
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        Ok(())
+    }
+
+    async fn get_cookies(&self) -> Result<Vec<cookie::Cookie<'_>>> {
+        Ok(Vec::new())
+    }
+
+    fn delete_token(&mut self) {}
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn sign_request(&self, request: &mut Request) -> Result<()> {
+        let nonce: String = std::iter::repeat_with(fastrand::alphanumeric)
+            .take(32)
+            .collect();
+        let header = self.authorization_header(
+            request.method(),
+            request.url(),
+            &signed_params(request),
+            &nonce,
+            chrono::Utc::now().timestamp(),
+        );
+        request.headers_mut().insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&header).map_err(|e| TwitterError::Auth(e.to_string()))?,
+        );
+        Ok(())
+    }
+
+    fn is_official_api(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth() -> OAuth1Auth {
+        OAuth1Auth::new(
+            "xvz1evFS4wEEPTGEFPHBog",
+            "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw",
+            "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb",
+            "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE",
+        )
+    }
+
+    #[test]
+    fn test_signature_matches_reference() {
+        let client = reqwest::Client::new();
+        let request = client
+            .post("https://api.twitter.com/1.1/statuses/update.json?include_entities=true")
+            .form(&[(
+                "status",
+                "Hello Ladies + Gentlemen, a signed OAuth request!",
+            )])
+            .build()
+            .unwrap();
+
+        let header = auth().authorization_header(
+            request.method(),
+            request.url(),
+            &signed_params(&request),
+            "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg",
+            1318622958,
+        );
+
+        assert!(header.starts_with("OAuth oauth_consumer_key=\"xvz1evFS4wEEPTGEFPHBog\", "));
+        assert!(header.contains("oauth_signature=\"hCtSmYh%2BiHYCEqBWrE7C7hYmtUk%3D\""));
+    }
+
+    #[test]
+    fn test_base_url_keeps_non_default_port() {
+        let url = Url::parse("https://api.x.com:443/2/tweets?a=1").unwrap();
+        assert_eq!(base_url(&url), "https://api.x.com/2/tweets");
+        let url = Url::parse("http://127.0.0.1:8080/2/tweets").unwrap();
+        assert_eq!(base_url(&url), "http://127.0.0.1:8080/2/tweets");
+    }
+}
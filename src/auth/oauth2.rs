@@ -0,0 +1,398 @@
+//! OAuth 2.0 user-context auth for the official X API: the PKCE authorization flow and
+//! access tokens that are refreshed before they expire.
+
+use crate::api::client::TwitterClient;
+use crate::api::hosts::Hosts;
+use crate::api::redact::REDACTED;
+use crate::api::requests::request_form_api_once;
+use crate::auth::user_auth::TwitterAuth;
+use crate::error::{Result, TwitterError};
+use async_trait::async_trait;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// How long before its expiry an access token is refreshed.
+pub const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OAuth2Token {
+    pub access_token: String,
+    /// Only issued when the `offline.access` scope was granted.
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scope: Option<String>,
+}
+
+impl fmt::Debug for OAuth2Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth2Token")
+            .field("access_token", &REDACTED)
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_ref().map(|_| REDACTED),
+            )
+            .field("expires_at", &self.expires_at)
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+    scope: Option<String>,
+}
+
+impl TokenResponse {
+    /// X rotates refresh tokens, but keeps the previous one if a response omits it.
+    fn into_token(self, previous_refresh_token: Option<String>) -> OAuth2Token {
+        OAuth2Token {
+            access_token: self.access_token,
+            refresh_token: self.refresh_token.or(previous_refresh_token),
+            expires_at: self
+                .expires_in
+                .map(|seconds| Utc::now() + chrono::Duration::seconds(seconds)),
+            scope: self.scope,
+        }
+    }
+}
+
+/// Sends a request to the token endpoint. Confidential clients authenticate with HTTP
+/// Basic auth; public clients only send their id.
+async fn request_token(
+    client: &TwitterClient,
+    client_id: &str,
+    client_secret: Option<&str>,
+    mut form: Vec<(String, String)>,
+) -> Result<TokenResponse> {
+    let mut headers = HeaderMap::new();
+    if let Some(secret) = client_secret {
+        let credentials = STANDARD.encode(format!("{}:{}", client_id, secret));
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Basic {}", credentials))
+                .map_err(|e| TwitterError::Auth(e.to_string()))?,
+        );
+    }
+    form.push(("client_id".into(), client_id.into()));
+
+    let url = client.hosts.api_url("2/oauth2/token");
+    let (response, _) = request_form_api_once(client, &url, headers, &form).await?;
+    Ok(response)
+}
+
+/// Sends a bearer access token, refreshing it shortly before it expires and when X rejects
+/// it. Clones share the token.
+#[derive(Clone)]
+pub struct OAuth2Auth {
+    client_id: String,
+    client_secret: Option<String>,
+    token: Arc<Mutex<OAuth2Token>>,
+    refresh_lock: Arc<tokio::sync::Mutex<()>>,
+    refreshed: broadcast::Sender<OAuth2Token>,
+}
+
+impl OAuth2Auth {
+    pub fn new(client_id: impl Into<String>, token: OAuth2Token) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client_secret: None,
+            token: Arc::new(Mutex::new(token)),
+            refresh_lock: Arc::new(tokio::sync::Mutex::new(())),
+            refreshed: broadcast::channel(16).0,
+        }
+    }
+
+    /// Authenticates token requests as a confidential client.
+    pub fn with_client_secret(mut self, client_secret: impl Into<String>) -> Self {
+        self.client_secret = Some(client_secret.into());
+        self
+    }
+
+    /// The current token, for saving it.
+    pub fn token(&self) -> OAuth2Token {
+        self.token.lock().unwrap().clone()
+    }
+
+    /// Receives every refreshed token. The previous refresh token stops working once a new
+    /// one is issued, so save each of them.
+    pub fn subscribe_token_refresh(&self) -> broadcast::Receiver<OAuth2Token> {
+        self.refreshed.subscribe()
+    }
+
+    pub(crate) fn can_refresh(&self) -> bool {
+        self.token.lock().unwrap().refresh_token.is_some()
+    }
+
+    /// The access token to send, refreshed first if it expires within [`REFRESH_MARGIN`].
+    pub(crate) async fn access_token(&self, client: &TwitterClient) -> Result<String> {
+        let token = self.token();
+        let margin = chrono::Duration::from_std(REFRESH_MARGIN).unwrap_or_default();
+        let expiring = token
+            .expires_at
+            .is_some_and(|expires_at| expires_at - Utc::now() <= margin);
+        if expiring && token.refresh_token.is_some() {
+            return self.refresh_after(client, &token.access_token).await;
+        }
+        Ok(token.access_token)
+    }
+
+    /// Refreshes the token unless another request has already replaced `rejected`.
+    pub(crate) async fn refresh_after(
+        &self,
+        client: &TwitterClient,
+        rejected: &str,
+    ) -> Result<String> {
+        let _guard = self.refresh_lock.lock().await;
+        let current = self.token();
+        if current.access_token != rejected {
+            return Ok(current.access_token);
+        }
+        Ok(self.refresh_locked(client, current).await?.access_token)
+    }
+
+    /// Exchanges the refresh token for a new token.
+    pub async fn refresh(&self, client: &TwitterClient) -> Result<OAuth2Token> {
+        let _guard = self.refresh_lock.lock().await;
+        self.refresh_locked(client, self.token()).await
+    }
+
+    async fn refresh_locked(
+        &self,
+        client: &TwitterClient,
+        current: OAuth2Token,
+    ) -> Result<OAuth2Token> {
+        let refresh_token = current
+            .refresh_token
+            .clone()
+            .ok_or_else(|| TwitterError::Auth("No refresh token; request offline.access".into()))?;
+
+        tracing::debug!("Refreshing OAuth 2.0 access token");
+        let response = request_token(
+            client,
+            &self.client_id,
+            self.client_secret.as_deref(),
+            vec![
+                ("grant_type".into(), "refresh_token".into()),
+                ("refresh_token".into(), refresh_token.clone()),
+            ],
+        )
+        .await?;
+
+        let token = response.into_token(Some(refresh_token));
+        *self.token.lock().unwrap() = token.clone();
+        let _ = self.refreshed.send(token.clone());
+        Ok(token)
+    }
+}
+
+impl fmt::Debug for OAuth2Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth2Auth")
+            .field("client_id", &self.client_id)
+            .field(
+                "client_secret",
+                &self.client_secret.as_ref().map(|_| REDACTED),
+            )
+            .field("token", &self.token())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl TwitterAuth for OAuth2Auth {
+    async fn install_headers(&self, headers: &mut HeaderMap) -> Result<()> {
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", self.token().access_token))
+                .map_err(|e| TwitterError::Auth(e.to_string()))?,
+        );
+        headers.insert("accept", HeaderValue::from_static("*/*"));
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        Ok(())
+    }
+
+    async fn get_cookies(&self) -> Result<Vec<cookie::Cookie<'_>>> {
+        Ok(Vec::new())
+    }
+
+    fn delete_token(&mut self) {}
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn is_official_api(&self) -> bool {
+        true
+    }
+}
+
+/// One run of the authorization code flow with PKCE: send the user to [`Self::url`], then
+/// exchange the `code` X redirects back with, after checking its `state` matches.
+#[derive(Clone)]
+pub struct AuthorizationRequest {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    pub state: String,
+    pub code_verifier: String,
+}
+
+impl AuthorizationRequest {
+    /// Starts a flow with a random state and code verifier. Include `offline.access` in
+    /// `scopes` to get a refresh token.
+    pub fn new(
+        client_id: impl Into<String>,
+        redirect_uri: impl Into<String>,
+        scopes: &[&str],
+    ) -> Self {
+        Self {
+            client_id: client_id.into(),
+            redirect_uri: redirect_uri.into(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            state: random_string(32),
+            code_verifier: random_string(64),
+        }
+    }
+
+    /// The S256 challenge derived from the code verifier.
+    pub fn code_challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.code_verifier.as_bytes()))
+    }
+
+    /// The page the user authorizes the app on.
+    pub fn url(&self, hosts: &Hosts) -> String {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &self.redirect_uri)
+            .append_pair("scope", &self.scopes.join(" "))
+            .append_pair("state", &self.state)
+            .append_pair("code_challenge", &self.code_challenge())
+            .append_pair("code_challenge_method", "S256")
+            .finish();
+        format!("{}?{}", hosts.web_url("i/oauth2/authorize"), query)
+    }
+
+    /// Exchanges the authorization `code` for a token, sending the request through `client`.
+    pub async fn exchange_code(
+        &self,
+        client: &TwitterClient,
+        code: &str,
+        client_secret: Option<&str>,
+    ) -> Result<OAuth2Auth> {
+        let response = request_token(
+            client,
+            &self.client_id,
+            client_secret,
+            vec![
+                ("grant_type".into(), "authorization_code".into()),
+                ("code".into(), code.into()),
+                ("redirect_uri".into(), self.redirect_uri.clone()),
+                ("code_verifier".into(), self.code_verifier.clone()),
+            ],
+        )
+        .await?;
+
+        let auth = OAuth2Auth::new(self.client_id.clone(), response.into_token(None));
+        Ok(match client_secret {
+            Some(secret) => auth.with_client_secret(secret),
+            None => auth,
+        })
+    }
+}
+
+impl fmt::Debug for AuthorizationRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthorizationRequest")
+            .field("client_id", &self.client_id)
+            .field("redirect_uri", &self.redirect_uri)
+            .field("scopes", &self.scopes)
+            .field("state", &self.state)
+            .field("code_verifier", &REDACTED)
+            .finish()
+    }
+}
+
+fn random_string(len: usize) -> String {
+    std::iter::repeat_with(fastrand::alphanumeric)
+        .take(len)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockResponse, MockServer};
+    use serde_json::json;
+    use url::Url;
+
+    #[test]
+    fn test_code_challenge() {
+        let mut request = AuthorizationRequest::new("client", "http://localhost/cb", &[]);
+        request.code_verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".into();
+        assert_eq!(
+            request.code_challenge(),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+
+        let url = Url::parse(&request.url(&Hosts::default())).unwrap();
+        assert_eq!(url.path(), "/i/oauth2/authorize");
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        assert_eq!(param("code_challenge_method").as_deref(), Some("S256"));
+        assert_eq!(param("state"), Some(request.state.clone()));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_code_and_refresh() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.guest_scraper().await.unwrap();
+        let request = AuthorizationRequest::new(
+            "client",
+            "http://localhost/cb",
+            &["tweet.read", "offline.access"],
+        );
+
+        let auth = request
+            .exchange_code(&scraper.twitter_client, "the-code", None)
+            .await
+            .unwrap();
+        let exchange = &server.requests_to("/2/oauth2/token")[0];
+        assert!(exchange
+            .body_text()
+            .contains("grant_type=authorization_code"));
+        assert!(exchange
+            .body_text()
+            .contains(&format!("code_verifier={}", request.code_verifier)));
+        assert!(auth.token().expires_at.is_some());
+
+        let mut refreshed = auth.subscribe_token_refresh();
+        server.mock(
+            "/2/oauth2/token",
+            MockResponse::json(
+                200,
+                json!({"access_token": "second", "token_type": "bearer", "expires_in": 7200}),
+            ),
+        );
+        let token = auth.refresh(&scraper.twitter_client).await.unwrap();
+        assert_eq!(token.access_token, "second");
+        // The refresh token is kept when the response does not rotate it.
+        assert!(token.refresh_token.is_some());
+        assert_eq!(refreshed.try_recv().unwrap(), token);
+    }
+}
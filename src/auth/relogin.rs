@@ -0,0 +1,105 @@
+//! Logging in again when X rejects the session.
+
+use crate::api::client::TwitterClient;
+use crate::auth::config::AuthConfig;
+use crate::auth::user_auth::TwitterUserAuth;
+use crate::error::{ApiErrorKind, Result, TwitterError};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+
+tokio::task_local! {
+    static LOGGING_IN: ();
+}
+
+/// Credentials used to log in again, shared by every request of a client.
+///
+/// Requests that fail while another request is logging in wait for it and then replay,
+/// so a rejected session triggers a single login however many requests saw it. Requests
+/// sent during the login wait for it too, rather than going out without a session.
+pub struct AutoRelogin {
+    config: AuthConfig,
+    /// Number of logins performed so far.
+    generation: AtomicU64,
+    lock: Mutex<()>,
+}
+
+impl AutoRelogin {
+    pub fn new(config: AuthConfig) -> Self {
+        Self {
+            config,
+            generation: AtomicU64::new(0),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        self.config.username.as_deref()
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Waits for a login in progress, if any, to finish.
+    pub(crate) async fn wait(&self) {
+        drop(self.lock.lock().await);
+    }
+
+    /// Logs in again with the stored credentials, unless another request has done so since
+    /// generation `seen` was read.
+    ///
+    /// Returns a boxed future because the login's own requests go through the request path
+    /// that calls this.
+    pub(crate) fn relogin<'a>(
+        &'a self,
+        client: &'a TwitterClient,
+        seen: u64,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            let _guard = self.lock.lock().await;
+            if self.generation() != seen {
+                return Ok(());
+            }
+
+            let (Some(username), Some(password)) = (&self.config.username, &self.config.password)
+            else {
+                return Err(TwitterError::Auth(
+                    "Automatic re-login requires a username and password".into(),
+                ));
+            };
+            let mut auth = client
+                .auth
+                .as_any()
+                .downcast_ref::<TwitterUserAuth>()
+                .ok_or_else(|| TwitterError::Auth("Invalid auth type".into()))?
+                .clone();
+
+            tracing::info!(username = %username, "Session rejected, logging in again");
+            auth.replace_cookies(Vec::new()).await;
+            let login = auth.login(
+                client,
+                username,
+                password,
+                self.config.email.as_deref(),
+                self.config.two_factor_secret.as_deref(),
+            );
+            LOGGING_IN.scope((), login).await?;
+
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        })
+    }
+}
+
+/// Whether the current task is performing a re-login, whose own requests must not trigger
+/// another one.
+pub(crate) fn is_logging_in() -> bool {
+    LOGGING_IN.try_with(|_| ()).is_ok()
+}
+
+/// Whether `error` means the session was rejected: HTTP 401 or error codes 32, 89 and 239.
+pub fn is_session_rejected(error: &TwitterError) -> bool {
+    matches!(error, TwitterError::Http(error) if error.kind() == ApiErrorKind::AuthExpired)
+}
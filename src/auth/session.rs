@@ -0,0 +1,467 @@
+//! On-disk session formats.
+//!
+//! Sessions are saved as a versioned JSON [`SessionFile`] that keeps every cookie attribute.
+//! Cookies can also be imported from and exported to the Netscape `cookies.txt` format and
+//! the JSON written by browser cookie extensions (EditThisCookie, Cookie-Editor), so a session
+//! captured in a browser can be loaded directly. Files written by older versions of this
+//! crate, a plain list of `[name, value]` pairs, are still accepted.
+
+use crate::error::{Result, TwitterError};
+use chrono::{DateTime, TimeZone, Utc};
+use cookie::time::{Duration, OffsetDateTime};
+use cookie::{Cookie, SameSite};
+use serde::{Deserialize, Serialize};
+
+pub const SESSION_FILE_VERSION: u32 = 1;
+
+/// Domain given to cookies restored from formats that do not record one.
+const DEFAULT_DOMAIN: &str = "x.com";
+
+/// A cookie with all the attributes needed to restore it faithfully.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredCookie {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+    /// `Max-Age` in seconds, as originally set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<i64>,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    /// `Strict`, `Lax` or `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_site: Option<String>,
+}
+
+impl StoredCookie {
+    pub fn from_cookie(cookie: &Cookie<'_>) -> Self {
+        Self {
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            domain: cookie.domain().map(String::from),
+            path: cookie.path().map(String::from),
+            expires: cookie
+                .expires_datetime()
+                .and_then(|expires| Utc.timestamp_opt(expires.unix_timestamp(), 0).single()),
+            max_age: cookie.max_age().map(|max_age| max_age.whole_seconds()),
+            secure: cookie.secure().unwrap_or(false),
+            http_only: cookie.http_only().unwrap_or(false),
+            same_site: cookie.same_site().map(|same_site| same_site.to_string()),
+        }
+    }
+
+    pub fn to_cookie(&self) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.name.clone(), self.value.clone());
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        if let Some(path) = &self.path {
+            cookie.set_path(path.clone());
+        }
+        if let Some(expires) = self
+            .expires
+            .and_then(|expires| OffsetDateTime::from_unix_timestamp(expires.timestamp()).ok())
+        {
+            cookie.set_expires(expires);
+        }
+        if let Some(max_age) = self.max_age {
+            cookie.set_max_age(Duration::seconds(max_age));
+        }
+        cookie.set_secure(self.secure);
+        cookie.set_http_only(self.http_only);
+        cookie.set_same_site(self.same_site.as_deref().and_then(parse_same_site));
+        cookie
+    }
+
+    /// A cookie restored from a name/value pair only, with the attributes X sets.
+    fn with_defaults(name: String, value: String) -> Self {
+        Self {
+            name,
+            value,
+            domain: Some(DEFAULT_DOMAIN.to_string()),
+            path: Some("/".to_string()),
+            expires: None,
+            max_age: None,
+            secure: true,
+            http_only: true,
+            same_site: None,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|expires| expires <= Utc::now())
+    }
+}
+
+fn parse_same_site(value: &str) -> Option<SameSite> {
+    match value.to_ascii_lowercase().as_str() {
+        "strict" => Some(SameSite::Strict),
+        "lax" => Some(SameSite::Lax),
+        "none" | "no_restriction" => Some(SameSite::None),
+        _ => None,
+    }
+}
+
+/// The versioned session file written by `save_cookies_to_file`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionFile {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_at: Option<DateTime<Utc>>,
+    pub cookies: Vec<StoredCookie>,
+}
+
+impl SessionFile {
+    pub fn new(cookies: Vec<StoredCookie>) -> Self {
+        Self {
+            version: SESSION_FILE_VERSION,
+            saved_at: Some(Utc::now()),
+            cookies,
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: SessionFile = serde_json::from_str(json)
+            .map_err(|e| TwitterError::Cookie(format!("Failed to parse session file: {}", e)))?;
+        if file.version > SESSION_FILE_VERSION {
+            return Err(TwitterError::Cookie(format!(
+                "Unsupported session file version {}",
+                file.version
+            )));
+        }
+        Ok(file)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| TwitterError::Cookie(format!("Failed to serialize session: {}", e)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookieFormat {
+    /// The versioned [`SessionFile`] JSON.
+    Session,
+    /// `[["name", "value"], ...]`, as written by earlier versions of this crate.
+    LegacyPairs,
+    /// Netscape `cookies.txt`, as used by curl, wget and yt-dlp.
+    Netscape,
+    /// The JSON array exported by browser cookie extensions.
+    BrowserJson,
+}
+
+impl CookieFormat {
+    pub fn detect(content: &str) -> Self {
+        let content = content.trim_start();
+        if content.starts_with('{') {
+            Self::Session
+        } else if let Some(array) = content.strip_prefix('[') {
+            if array.trim_start().starts_with('[') {
+                Self::LegacyPairs
+            } else {
+                Self::BrowserJson
+            }
+        } else {
+            Self::Netscape
+        }
+    }
+}
+
+/// Parses cookies in `format`, or in the detected format when `format` is `None`.
+pub fn import_cookies(content: &str, format: Option<CookieFormat>) -> Result<Vec<StoredCookie>> {
+    match format.unwrap_or_else(|| CookieFormat::detect(content)) {
+        CookieFormat::Session => Ok(SessionFile::from_json(content)?.cookies),
+        CookieFormat::LegacyPairs => {
+            let pairs: Vec<(String, String)> = serde_json::from_str(content)
+                .map_err(|e| TwitterError::Cookie(format!("Failed to parse cookie JSON: {}", e)))?;
+            Ok(pairs
+                .into_iter()
+                .map(|(name, value)| StoredCookie::with_defaults(name, value))
+                .collect())
+        }
+        CookieFormat::Netscape => parse_netscape(content),
+        CookieFormat::BrowserJson => parse_browser_json(content),
+    }
+}
+
+pub fn export_cookies(cookies: &[StoredCookie], format: CookieFormat) -> Result<String> {
+    match format {
+        CookieFormat::Session => SessionFile::new(cookies.to_vec()).to_json(),
+        CookieFormat::LegacyPairs => serde_json::to_string_pretty(
+            &cookies
+                .iter()
+                .map(|cookie| (&cookie.name, &cookie.value))
+                .collect::<Vec<_>>(),
+        )
+        .map_err(|e| TwitterError::Cookie(format!("Failed to serialize cookies: {}", e))),
+        CookieFormat::Netscape => Ok(to_netscape(cookies)),
+        CookieFormat::BrowserJson => to_browser_json(cookies),
+    }
+}
+
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+fn parse_netscape(content: &str) -> Result<Vec<StoredCookie>> {
+    let mut cookies = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [domain, _include_subdomains, path, secure, expires, name, value] = fields[..] else {
+            return Err(TwitterError::Cookie(format!(
+                "Invalid cookies.txt line {}: expected 7 tab-separated fields",
+                index + 1
+            )));
+        };
+
+        let expires = expires.trim().parse::<i64>().map_err(|_| {
+            TwitterError::Cookie(format!("Invalid expiry on cookies.txt line {}", index + 1))
+        })?;
+
+        cookies.push(StoredCookie {
+            name: name.to_string(),
+            value: value.trim_end_matches('\r').to_string(),
+            domain: Some(domain.to_string()),
+            path: Some(path.to_string()),
+            expires: (expires > 0)
+                .then(|| Utc.timestamp_opt(expires, 0).single())
+                .flatten(),
+            max_age: None,
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            http_only,
+            same_site: None,
+        });
+    }
+    Ok(cookies)
+}
+
+fn to_netscape(cookies: &[StoredCookie]) -> String {
+    let mut out = String::from("# Netscape HTTP Cookie File\n");
+    for cookie in cookies {
+        let domain = cookie.domain.as_deref().unwrap_or(DEFAULT_DOMAIN);
+        let flag = |value: bool| if value { "TRUE" } else { "FALSE" };
+        out.push_str(&format!(
+            "{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            if cookie.http_only {
+                HTTP_ONLY_PREFIX
+            } else {
+                ""
+            },
+            domain,
+            flag(domain.starts_with('.')),
+            cookie.path.as_deref().unwrap_or("/"),
+            flag(cookie.secure),
+            cookie
+                .expires
+                .map(|expires| expires.timestamp())
+                .unwrap_or(0),
+            cookie.name,
+            cookie.value
+        ));
+    }
+    out
+}
+
+/// One entry of a browser extension export.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BrowserCookie {
+    name: String,
+    value: String,
+    #[serde(default)]
+    domain: Option<String>,
+    #[serde(default)]
+    path: Option<String>,
+    /// Seconds since the epoch, possibly fractional.
+    #[serde(default)]
+    expiration_date: Option<f64>,
+    #[serde(default)]
+    host_only: bool,
+    #[serde(default)]
+    http_only: bool,
+    #[serde(default)]
+    secure: bool,
+    #[serde(default)]
+    session: bool,
+    /// `no_restriction`, `lax`, `strict` or `unspecified`.
+    #[serde(default)]
+    same_site: Option<String>,
+}
+
+fn parse_browser_json(content: &str) -> Result<Vec<StoredCookie>> {
+    let entries: Vec<BrowserCookie> = serde_json::from_str(content)
+        .map_err(|e| TwitterError::Cookie(format!("Failed to parse cookie export: {}", e)))?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| StoredCookie {
+            name: entry.name,
+            value: entry.value,
+            domain: entry.domain,
+            path: entry.path,
+            expires: entry
+                .expiration_date
+                .filter(|_| !entry.session)
+                .and_then(|expires| Utc.timestamp_opt(expires as i64, 0).single()),
+            max_age: None,
+            secure: entry.secure,
+            http_only: entry.http_only,
+            same_site: entry
+                .same_site
+                .as_deref()
+                .and_then(parse_same_site)
+                .map(|same_site| same_site.to_string()),
+        })
+        .collect())
+}
+
+fn to_browser_json(cookies: &[StoredCookie]) -> Result<String> {
+    let entries: Vec<BrowserCookie> = cookies
+        .iter()
+        .map(|cookie| BrowserCookie {
+            name: cookie.name.clone(),
+            value: cookie.value.clone(),
+            domain: cookie.domain.clone(),
+            path: cookie.path.clone(),
+            expiration_date: cookie.expires.map(|expires| expires.timestamp() as f64),
+            host_only: !cookie
+                .domain
+                .as_deref()
+                .unwrap_or_default()
+                .starts_with('.'),
+            http_only: cookie.http_only,
+            secure: cookie.secure,
+            session: cookie.expires.is_none(),
+            same_site: Some(
+                match cookie.same_site.as_deref().and_then(parse_same_site) {
+                    Some(SameSite::Strict) => "strict",
+                    Some(SameSite::Lax) => "lax",
+                    Some(SameSite::None) => "no_restriction",
+                    None => "unspecified",
+                }
+                .to_string(),
+            ),
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&entries)
+        .map_err(|e| TwitterError::Cookie(format!("Failed to serialize cookies: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth_token() -> StoredCookie {
+        StoredCookie {
+            name: "auth_token".into(),
+            value: "abc".into(),
+            domain: Some(".x.com".into()),
+            path: Some("/".into()),
+            expires: Utc.timestamp_opt(1893456000, 0).single(),
+            max_age: Some(157680000),
+            secure: true,
+            http_only: true,
+            same_site: Some("None".into()),
+        }
+    }
+
+    #[test]
+    fn test_cookie_attributes_round_trip() {
+        let cookie = Cookie::parse(
+            "ct0=def; Max-Age=157680000; Expires=Tue, 01 Jan 2030 00:00:00 GMT; Path=/; Domain=.x.com; Secure; SameSite=Lax",
+        )
+        .unwrap();
+
+        let stored = StoredCookie::from_cookie(&cookie);
+        assert_eq!(stored.domain.as_deref(), Some("x.com"));
+        assert_eq!(stored.expires.unwrap().timestamp(), 1893456000);
+        assert_eq!(stored.max_age, Some(157680000));
+        assert_eq!(stored.same_site.as_deref(), Some("Lax"));
+        assert!(!stored.http_only);
+
+        let restored = stored.to_cookie();
+        assert_eq!(StoredCookie::from_cookie(&restored), stored);
+    }
+
+    #[test]
+    fn test_session_file_round_trip() {
+        let json = export_cookies(&[auth_token()], CookieFormat::Session).unwrap();
+        assert_eq!(CookieFormat::detect(&json), CookieFormat::Session);
+        assert_eq!(import_cookies(&json, None).unwrap(), vec![auth_token()]);
+
+        let future = json.replace("\"version\": 1", "\"version\": 99");
+        assert!(import_cookies(&future, None).is_err());
+    }
+
+    #[test]
+    fn test_legacy_pairs() {
+        let cookies = import_cookies(r#"[["auth_token", "abc"], ["ct0", "def"]]"#, None).unwrap();
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies[0].domain.as_deref(), Some("x.com"));
+        assert!(cookies[0].secure && cookies[0].http_only);
+    }
+
+    #[test]
+    fn test_netscape_round_trip() {
+        let content = "# Netscape HTTP Cookie File\n\
+            #HttpOnly_.x.com\tTRUE\t/\tTRUE\t1893456000\tauth_token\tabc\n\
+            .x.com\tTRUE\t/\tTRUE\t0\tct0\tdef\n";
+
+        let cookies = import_cookies(content, None).unwrap();
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies[0].name, "auth_token");
+        assert!(cookies[0].http_only);
+        assert_eq!(cookies[0].expires.unwrap().timestamp(), 1893456000);
+        assert_eq!(cookies[1].expires, None);
+        assert!(!cookies[1].http_only);
+
+        assert_eq!(
+            export_cookies(&cookies, CookieFormat::Netscape).unwrap(),
+            content
+        );
+        assert!(import_cookies(".x.com\tTRUE\t/\n", Some(CookieFormat::Netscape)).is_err());
+    }
+
+    #[test]
+    fn test_browser_json_round_trip() {
+        let content = r#"[
+            {
+                "domain": ".x.com",
+                "expirationDate": 1893456000.5,
+                "hostOnly": false,
+                "httpOnly": true,
+                "name": "auth_token",
+                "path": "/",
+                "sameSite": "no_restriction",
+                "secure": true,
+                "session": false,
+                "storeId": "0",
+                "value": "abc"
+            }
+        ]"#;
+
+        let mut expected = auth_token();
+        expected.max_age = None;
+
+        let cookies = import_cookies(content, None).unwrap();
+        assert_eq!(cookies, vec![expected]);
+
+        let exported = export_cookies(&cookies, CookieFormat::BrowserJson).unwrap();
+        assert_eq!(import_cookies(&exported, None).unwrap(), cookies);
+    }
+}
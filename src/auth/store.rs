@@ -0,0 +1,423 @@
+//! Pluggable persistence for login sessions, keyed by account.
+
+use crate::auth::session::SessionFile;
+use crate::error::{Result, TwitterError};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use async_trait::async_trait;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// The saved session for `account`, or `None` if there is none.
+    async fn load(&self, account: &str) -> Result<Option<SessionFile>>;
+    async fn save(&self, account: &str, session: &SessionFile) -> Result<()>;
+    /// Removes the saved session for `account`. Deleting a missing session is not an error.
+    async fn delete(&self, account: &str) -> Result<()>;
+}
+
+/// Keeps sessions in memory, shared by clones. Useful in tests.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySessionStore {
+    inner: Arc<Mutex<HashMap<String, SessionFile>>>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn accounts(&self) -> Vec<String> {
+        self.inner.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn load(&self, account: &str) -> Result<Option<SessionFile>> {
+        Ok(self.inner.lock().unwrap().get(account).cloned())
+    }
+
+    async fn save(&self, account: &str, session: &SessionFile) -> Result<()> {
+        self.inner
+            .lock()
+            .unwrap()
+            .insert(account.to_string(), session.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, account: &str) -> Result<()> {
+        self.inner.lock().unwrap().remove(account);
+        Ok(())
+    }
+}
+
+/// Stores each session as `<dir>/<account>.json`.
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    dir: PathBuf,
+}
+
+impl FileSessionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn path_for(&self, account: &str) -> PathBuf {
+        session_path(&self.dir, account, "json")
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileSessionStore {
+    async fn load(&self, account: &str) -> Result<Option<SessionFile>> {
+        match fs::read_to_string(self.path_for(account)) {
+            Ok(json) => SessionFile::from_json(&json).map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, account: &str, session: &SessionFile) -> Result<()> {
+        write_private(&self.path_for(account), session.to_json()?.as_bytes())
+    }
+
+    async fn delete(&self, account: &str) -> Result<()> {
+        remove_if_exists(&self.path_for(account))
+    }
+}
+
+const ENCRYPTED_MAGIC: &[u8; 4] = b"XSES";
+const ENCRYPTED_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = ENCRYPTED_MAGIC.len() + 1 + 4 + SALT_LEN + NONCE_LEN;
+
+/// Stores each session as `<dir>/<account>.session`, encrypted with AES-256-GCM under a key
+/// derived from a passphrase with PBKDF2-HMAC-SHA256.
+///
+/// The key is derived once per store, from a random salt or the salt of the first file
+/// loaded, and every save uses a fresh nonce. The salt and iteration count are recorded in
+/// each file, so files stay readable after [`EncryptedSessionStore::with_iterations`]
+/// changes.
+#[derive(Clone)]
+pub struct EncryptedSessionStore {
+    dir: PathBuf,
+    passphrase: Arc<String>,
+    iterations: u32,
+    key: Arc<tokio::sync::Mutex<Option<DerivedKey>>>,
+}
+
+/// A key derived from the passphrase, with the salt and iteration count it was derived with.
+#[derive(Clone)]
+struct DerivedKey {
+    salt: [u8; SALT_LEN],
+    iterations: u32,
+    key: Key<Aes256Gcm>,
+}
+
+impl EncryptedSessionStore {
+    pub const DEFAULT_ITERATIONS: u32 = 600_000;
+
+    pub fn new(dir: impl Into<PathBuf>, passphrase: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            passphrase: Arc::new(passphrase.into()),
+            iterations: Self::DEFAULT_ITERATIONS,
+            key: Arc::default(),
+        }
+    }
+
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations.max(1);
+        self.key = Arc::default();
+        self
+    }
+
+    pub fn path_for(&self, account: &str) -> PathBuf {
+        session_path(&self.dir, account, "session")
+    }
+
+    /// The key for `salt` and `iterations`, reusing the store's key when they match. The
+    /// first key derived becomes the store's key. Without a salt, the store's key is used,
+    /// derived from a random salt if there is none yet.
+    async fn key(&self, salt: Option<([u8; SALT_LEN], u32)>) -> Result<DerivedKey> {
+        let mut cached = self.key.lock().await;
+        let (salt, iterations) = match (salt, cached.as_ref()) {
+            (None, Some(key)) => return Ok(key.clone()),
+            (Some((salt, iterations)), Some(key))
+                if key.salt == salt && key.iterations == iterations =>
+            {
+                return Ok(key.clone())
+            }
+            (Some(salt), _) => salt,
+            (None, None) => {
+                let mut salt = [0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                (salt, self.iterations)
+            }
+        };
+
+        let passphrase = self.passphrase.clone();
+        let key = tokio::task::spawn_blocking(move || DerivedKey {
+            salt,
+            iterations,
+            key: derive_key(&passphrase, &salt, iterations),
+        })
+        .await
+        .map_err(|e| TwitterError::Session(e.to_string()))?;
+        if cached.is_none() {
+            *cached = Some(key.clone());
+        }
+        Ok(key)
+    }
+}
+
+impl std::fmt::Debug for EncryptedSessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedSessionStore")
+            .field("dir", &self.dir)
+            .field("iterations", &self.iterations)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl SessionStore for EncryptedSessionStore {
+    async fn load(&self, account: &str) -> Result<Option<SessionFile>> {
+        let data = match fs::read(self.path_for(account)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let file = parse_header(&data)?;
+        let key = self.key(Some((file.salt, file.iterations))).await?;
+        let plaintext = decrypt(&key, file.nonce, file.ciphertext)?;
+
+        let json = String::from_utf8(plaintext)
+            .map_err(|_| TwitterError::Session("Decrypted session is not UTF-8".into()))?;
+        SessionFile::from_json(&json).map(Some)
+    }
+
+    async fn save(&self, account: &str, session: &SessionFile) -> Result<()> {
+        let json = session.to_json()?;
+        let key = self.key(None).await?;
+        let data = encrypt(&key, json.as_bytes())?;
+        write_private(&self.path_for(account), &data)
+    }
+
+    async fn delete(&self, account: &str) -> Result<()> {
+        remove_if_exists(&self.path_for(account))
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Key<Aes256Gcm> {
+    let mut key = Key::<Aes256Gcm>::default();
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+fn encrypt(key: &DerivedKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let cipher = Aes256Gcm::new(&key.key);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| TwitterError::Session("Failed to encrypt session".into()))?;
+
+    let mut data = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    data.extend_from_slice(ENCRYPTED_MAGIC);
+    data.push(ENCRYPTED_VERSION);
+    data.extend_from_slice(&key.iterations.to_be_bytes());
+    data.extend_from_slice(&key.salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// The parts of an encrypted session file.
+struct EncryptedFile<'a> {
+    salt: [u8; SALT_LEN],
+    iterations: u32,
+    nonce: &'a [u8],
+    ciphertext: &'a [u8],
+}
+
+fn parse_header(data: &[u8]) -> Result<EncryptedFile<'_>> {
+    if data.len() < HEADER_LEN || !data.starts_with(ENCRYPTED_MAGIC) {
+        return Err(TwitterError::Session(
+            "Not an encrypted session file".into(),
+        ));
+    }
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+    let (version, rest) = header[ENCRYPTED_MAGIC.len()..].split_at(1);
+    if version[0] != ENCRYPTED_VERSION {
+        return Err(TwitterError::Session(format!(
+            "Unsupported encrypted session version {}",
+            version[0]
+        )));
+    }
+    let (iterations, rest) = rest.split_at(4);
+    let (salt, nonce) = rest.split_at(SALT_LEN);
+    let iterations = u32::from_be_bytes(iterations.try_into().unwrap());
+    Ok(EncryptedFile {
+        salt: salt.try_into().unwrap(),
+        iterations,
+        nonce,
+        ciphertext,
+    })
+}
+
+fn decrypt(key: &DerivedKey, nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    Aes256Gcm::new(&key.key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| {
+            TwitterError::Session(
+                "Failed to decrypt session: wrong passphrase or corrupt file".into(),
+            )
+        })
+}
+
+/// `<dir>/<account>.<extension>`, with characters that are unsafe in file names replaced.
+fn session_path(dir: &Path, account: &str, extension: &str) -> PathBuf {
+    let name: String = account
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '@') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_start_matches('.');
+    dir.join(format!("{}.{}", name, extension))
+}
+
+/// Writes `data` to `path`, readable only by the owner on Unix.
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::session::StoredCookie;
+
+    fn session() -> SessionFile {
+        SessionFile::new(vec![StoredCookie {
+            name: "auth_token".into(),
+            value: "abc".into(),
+            domain: Some(".x.com".into()),
+            path: Some("/".into()),
+            expires: None,
+            max_age: None,
+            secure: true,
+            http_only: true,
+            same_site: None,
+        }])
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", name, std::process::id()))
+    }
+
+    async fn assert_round_trip(store: &dyn SessionStore) {
+        assert_eq!(store.load("alice").await.unwrap(), None);
+
+        let saved = session();
+        store.save("alice", &saved).await.unwrap();
+        assert_eq!(store.load("alice").await.unwrap(), Some(saved));
+        assert_eq!(store.load("bob").await.unwrap(), None);
+
+        store.delete("alice").await.unwrap();
+        store.delete("alice").await.unwrap();
+        assert_eq!(store.load("alice").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        assert_round_trip(&MemorySessionStore::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_file_store() {
+        let dir = temp_dir("sessions");
+        let store = FileSessionStore::new(&dir);
+        assert_eq!(store.path_for("../alice"), dir.join("_alice.json"));
+
+        assert_round_trip(&store).await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store() {
+        let dir = temp_dir("encrypted-sessions");
+        let store = EncryptedSessionStore::new(&dir, "hunter2").with_iterations(1_000);
+        assert_round_trip(&store).await;
+
+        store.save("alice", &session()).await.unwrap();
+        let data = fs::read(store.path_for("alice")).unwrap();
+        assert!(!String::from_utf8_lossy(&data).contains("auth_token"));
+
+        let wrong = EncryptedSessionStore::new(&dir, "hunter3");
+        assert!(matches!(
+            wrong.load("alice").await,
+            Err(TwitterError::Session(_))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store_derives_key_once() {
+        let dir = temp_dir("encrypted-sessions-key");
+        let salt = |account: &str| {
+            let data = fs::read(session_path(&dir, account, "session")).unwrap();
+            data[ENCRYPTED_MAGIC.len() + 5..][..SALT_LEN].to_vec()
+        };
+
+        let store = EncryptedSessionStore::new(&dir, "hunter2").with_iterations(1_000);
+        store.save("alice", &session()).await.unwrap();
+        store.save("bob", &session()).await.unwrap();
+        assert_eq!(salt("alice"), salt("bob"));
+        let first = fs::read(store.path_for("alice")).unwrap();
+        store.save("alice", &session()).await.unwrap();
+        assert_ne!(fs::read(store.path_for("alice")).unwrap(), first);
+
+        // A new store reuses the salt of the first file it loads.
+        let reopened = EncryptedSessionStore::new(&dir, "hunter2").with_iterations(1_000);
+        let loaded = reopened.load("alice").await.unwrap().unwrap();
+        assert_eq!(loaded.cookies, session().cookies);
+        reopened.save("carol", &session()).await.unwrap();
+        assert_eq!(salt("carol"), salt("alice"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
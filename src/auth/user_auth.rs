@@ -1,20 +1,33 @@
-use crate::api::requests::request_api;
-use crate::error::{Result, TwitterError};
+use crate::api::client::TwitterClient;
+use crate::api::redact::{redacted_json, REDACTED};
+use crate::api::requests::{get_guest_token, request_api, request_api_once, request_form_api};
+use crate::auth::challenge::{ChallengeKind, LoginChallenge, LoginChallengeHandler};
+use crate::auth::health::{
+    status_from_cookies, SessionEvent, SessionHealth, SessionMonitor, SessionStatus,
+    SESSION_COOKIES,
+};
+use crate::auth::relogin;
+use crate::auth::session::{self, CookieFormat, SessionFile, StoredCookie};
+use crate::auth::store::SessionStore;
+use crate::error::{ApiErrorKind, LoginError, Result, TwitterError};
+use crate::models::AccountSession;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use cookie::time::OffsetDateTime;
 use cookie::CookieJar;
 use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::any::Any;
+use std::fmt;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::{broadcast, Mutex};
 use totp_rs::{Algorithm, TOTP};
-use tracing;
+use tracing::Instrument;
 
 #[derive(Debug)]
 enum SubtaskType {
@@ -23,11 +36,14 @@ enum SubtaskType {
     LoginEnterPassword,
     LoginAcid,
     AccountDuplicationCheck,
+    LoginTwoFactorAuthChooseMethod,
     LoginTwoFactorAuthChallenge,
+    LoginEnterOtp,
     LoginEnterAlternateIdentifier,
+    ArkoseLogin,
     LoginSuccess,
     DenyLogin,
-    Unknown(String),
+    Unknown,
 }
 
 impl From<&str> for SubtaskType {
@@ -38,11 +54,14 @@ impl From<&str> for SubtaskType {
             "LoginEnterPassword" => Self::LoginEnterPassword,
             "LoginAcid" => Self::LoginAcid,
             "AccountDuplicationCheck" => Self::AccountDuplicationCheck,
+            "LoginTwoFactorAuthChooseMethod" => Self::LoginTwoFactorAuthChooseMethod,
             "LoginTwoFactorAuthChallenge" => Self::LoginTwoFactorAuthChallenge,
+            "LoginEnterOtp" => Self::LoginEnterOtp,
             "LoginEnterAlternateIdentifierSubtask" => Self::LoginEnterAlternateIdentifier,
+            "ArkoseLogin" => Self::ArkoseLogin,
             "LoginSuccessSubtask" => Self::LoginSuccess,
             "DenyLoginSubtask" => Self::DenyLogin,
-            other => Self::Unknown(other.to_string()),
+            _ => Self::Unknown,
         }
     }
 }
@@ -53,6 +72,18 @@ pub trait TwitterAuth: Send + Sync + Any {
     async fn get_cookies(&self) -> Result<Vec<cookie::Cookie<'_>>>;
     fn delete_token(&mut self);
     fn as_any(&self) -> &dyn Any;
+
+    /// Signs a built request, for schemes such as OAuth 1.0a whose credentials cover the
+    /// method, URL and body. Called again for every retry.
+    fn sign_request(&self, _request: &mut reqwest::Request) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether this auth is for the official X API, whose v2 endpoints then replace the
+    /// web client's GraphQL ones.
+    fn is_official_api(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -62,75 +93,328 @@ struct FlowInitRequest {
     // subtask_versions: serde_json::Value,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Serialize)]
 struct FlowTaskRequest {
     flow_token: String,
     subtask_inputs: Vec<serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
+impl fmt::Debug for FlowTaskRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlowTaskRequest")
+            .field("flow_token", &REDACTED)
+            .field(
+                "subtask_inputs",
+                &redacted_json(&serde_json::Value::from(self.subtask_inputs.clone())),
+            )
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
 struct FlowResponse {
     flow_token: String,
     subtasks: Option<Vec<Subtask>>,
 }
 
+impl fmt::Debug for FlowResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlowResponse")
+            .field("flow_token", &REDACTED)
+            .field("subtasks", &self.subtasks)
+            .finish()
+    }
+}
+
+/// What `login` was given to answer the flow with.
+struct LoginCredentials<'a> {
+    username: &'a str,
+    password: &'a str,
+    email: Option<&'a str>,
+    two_factor_secret: Option<&'a str>,
+}
+
 #[derive(Debug, Deserialize)]
 struct Subtask {
     subtask_id: String,
+    #[serde(flatten)]
+    details: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Subtask {
+    fn to_value(&self) -> serde_json::Value {
+        let mut subtask = self.details.clone();
+        subtask.insert("subtask_id".into(), self.subtask_id.clone().into());
+        serde_json::Value::Object(subtask)
+    }
+
+    /// Text shown to the user by this step, lowercased: headers, hints and dialog text.
+    fn prompt(&self) -> String {
+        fn collect(value: &serde_json::Value, out: &mut Vec<String>) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, value) in map {
+                        match value {
+                            serde_json::Value::String(text)
+                                if key == "text" || key == "hint_text" =>
+                            {
+                                out.push(text.to_lowercase())
+                            }
+                            _ => collect(value, out),
+                        }
+                    }
+                }
+                serde_json::Value::Array(values) => {
+                    values.iter().for_each(|value| collect(value, out))
+                }
+                _ => {}
+            }
+        }
+
+        let mut texts = Vec::new();
+        collect(&serde_json::Value::Object(self.details.clone()), &mut texts);
+        texts.join(" ")
+    }
+
+    /// What an identifier step asks for, judging by its prompt: a code, a phone number, or
+    /// `default`.
+    fn identifier_kind(&self, default: ChallengeKind) -> ChallengeKind {
+        let prompt = self.prompt();
+        if prompt.contains("code") {
+            ChallengeKind::VerificationCode
+        } else if prompt.contains("phone") && !prompt.contains("email") {
+            ChallengeKind::PhoneNumber
+        } else {
+            default
+        }
+    }
+
+    /// The `id` of the authenticator app choice of a `LoginTwoFactorAuthChooseMethod` step.
+    fn authenticator_app_choice(&self) -> Option<String> {
+        self.details
+            .get("choice_selection")?
+            .get("choices")?
+            .as_array()?
+            .iter()
+            .find(|choice| {
+                let id = choice["id"].as_str().unwrap_or_default().to_lowercase();
+                let text = choice["text"]["text"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_lowercase();
+                id.contains("totp") || text.contains("authentication app")
+            })
+            .and_then(|choice| choice["id"].as_str().map(String::from))
+    }
+
+    fn is_suspicious_login_prompt(&self) -> bool {
+        let prompt = self.prompt();
+        prompt.contains("suspicious") || prompt.contains("unusual")
+    }
+}
+
+/// How long a guest token is used before a new one is activated.
+pub const DEFAULT_GUEST_TOKEN_MAX_AGE: std::time::Duration =
+    std::time::Duration::from_secs(3 * 60 * 60);
+
+/// A guest token issued by `1.1/guest/activate.json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuestToken {
+    pub token: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl GuestToken {
+    pub fn age(&self) -> std::time::Duration {
+        (Utc::now() - self.created_at).to_std().unwrap_or_default()
+    }
 }
 
 #[derive(Clone)]
 pub struct TwitterUserAuth {
     bearer_token: String,
-    guest_token: Option<String>,
+    guest_token: Arc<std::sync::Mutex<Option<GuestToken>>>,
+    /// Serializes guest token activation so concurrent requests share one new token.
+    guest_refresh: Arc<Mutex<()>>,
+    guest_token_max_age: std::time::Duration,
     cookie_jar: Arc<Mutex<CookieJar>>,
-    created_at: Option<DateTime<Utc>>,
+    session_store: Option<(Arc<dyn SessionStore>, String)>,
+    challenge_handler: Option<Arc<dyn LoginChallengeHandler>>,
+    health: SessionHealth,
+}
+
+/// Whether `a` and `b` set the same cookie. `expires` is left out, since it is derived from
+/// `Max-Age` when the cookie arrives and so differs on every response.
+fn same_cookie(a: &cookie::Cookie, b: &cookie::Cookie) -> bool {
+    a.name() == b.name()
+        && a.value() == b.value()
+        && a.domain() == b.domain()
+        && a.path() == b.path()
+        && a.max_age() == b.max_age()
 }
 
 impl TwitterUserAuth {
-    async fn store_cookies_from_headers(&self, headers: &HeaderMap) {
-        let mut cookie_jar = self.cookie_jar.lock().await;
-        for cookie_header in headers.get_all("set-cookie") {
-            if let Ok(cookie_str) = cookie_header.to_str() {
-                let lowercase = cookie_str.to_ascii_lowercase();
-                if lowercase.contains("max-age=0")
-                    || lowercase.contains("max-age=-")
-                    || lowercase.contains("expires=thu, 01 jan 1970")
-                {
-                    continue;
-                }
+    pub(crate) async fn store_cookies_from_headers(&self, headers: &HeaderMap) {
+        let mut changed = false;
+        {
+            let mut cookie_jar = self.cookie_jar.lock().await;
+            for cookie_header in headers.get_all("set-cookie") {
+                if let Ok(cookie_str) = cookie_header.to_str() {
+                    let lowercase = cookie_str.to_ascii_lowercase();
+                    if lowercase.contains("max-age=0")
+                        || lowercase.contains("max-age=-")
+                        || lowercase.contains("expires=thu, 01 jan 1970")
+                    {
+                        continue;
+                    }
 
-                if let Ok(cookie) = cookie::Cookie::parse(cookie_str) {
-                    cookie_jar.add(cookie.into_owned());
+                    if let Ok(mut cookie) = cookie::Cookie::parse(cookie_str) {
+                        if let (Some(max_age), None) = (cookie.max_age(), cookie.expires()) {
+                            cookie.set_expires(OffsetDateTime::now_utc() + max_age);
+                        }
+                        // Only a new login cookie is worth saving the session for.
+                        if SESSION_COOKIES.contains(&cookie.name()) {
+                            changed |= cookie_jar
+                                .get(cookie.name())
+                                .is_none_or(|stored| !same_cookie(stored, &cookie));
+                        }
+                        cookie_jar.add(cookie.into_owned());
+                    }
                 }
             }
         }
-    }
 
-    async fn serialized_cookie_header(&self) -> Option<String> {
-        let cookie_jar = self.cookie_jar.lock().await;
-        let cookies: Vec<String> = cookie_jar
-            .iter()
-            .map(|c| format!("{}={}", c.name(), c.value()))
-            .collect();
-
-        if cookies.is_empty() {
-            None
-        } else {
-            Some(cookies.join("; "))
+        if changed && self.session_store.is_some() {
+            if let Err(e) = self.save_session().await {
+                tracing::warn!(error = %e, "Failed to save session");
+            }
         }
     }
 
     pub async fn new(bearer_token: String) -> Result<Self> {
         Ok(Self {
             bearer_token,
-            guest_token: None,
+            guest_token: Arc::new(std::sync::Mutex::new(None)),
+            guest_refresh: Arc::new(Mutex::new(())),
+            guest_token_max_age: DEFAULT_GUEST_TOKEN_MAX_AGE,
             cookie_jar: Arc::new(Mutex::new(CookieJar::new())),
-            created_at: None,
+            session_store: None,
+            challenge_handler: None,
+            health: SessionHealth::default(),
         })
     }
 
-    async fn init_login(&mut self, client: &Client) -> Result<FlowResponse> {
+    /// Asks `handler` to answer login steps the credentials passed to `login` cannot, instead
+    /// of failing the login.
+    pub fn with_challenge_handler(mut self, handler: Arc<dyn LoginChallengeHandler>) -> Self {
+        self.challenge_handler = Some(handler);
+        self
+    }
+
+    /// Reports the session as expiring once `auth_token` or `ct0` expires within `warning`.
+    pub fn with_expiry_warning(self, warning: std::time::Duration) -> Self {
+        self.health.set_expiry_warning(warning);
+        self
+    }
+
+    /// Status of the session from its cookie expiry and the last validation result.
+    pub async fn session_status(&self) -> SessionStatus {
+        let (status, expires_at) =
+            status_from_cookies(&self.stored_cookies().await, self.health.expiry_warning());
+        self.health.observe(status, expires_at)
+    }
+
+    /// Earliest expiry of the `auth_token` and `ct0` cookies.
+    pub async fn session_expires_at(&self) -> Option<DateTime<Utc>> {
+        status_from_cookies(&self.stored_cookies().await, self.health.expiry_warning()).1
+    }
+
+    /// Checks the session against verify_credentials. Errors other than an expired session
+    /// or a locked account are returned without changing the status. The check never logs
+    /// in again, even with auto re-login enabled.
+    pub async fn validate_session(&self, client: &TwitterClient) -> Result<SessionStatus> {
+        match self.is_logged_in(client).await {
+            Ok(_) => self.health.clear_rejection(),
+            Err(TwitterError::Http(error)) => match error.kind() {
+                ApiErrorKind::AuthExpired => self.health.reject(SessionStatus::Expired),
+                ApiErrorKind::Locked => self.health.reject(SessionStatus::Locked),
+                _ => return Err(TwitterError::Http(error)),
+            },
+            Err(e) => return Err(e),
+        }
+        Ok(self.session_status().await)
+    }
+
+    pub fn subscribe_session_events(&self) -> broadcast::Receiver<SessionEvent> {
+        self.health.subscribe()
+    }
+
+    /// Validates the session every `interval` on a background task, using `client`.
+    pub fn spawn_session_monitor(
+        &self,
+        client: TwitterClient,
+        interval: std::time::Duration,
+    ) -> SessionMonitor {
+        let auth = self.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if !auth.session_status().await.is_usable() {
+                    continue;
+                }
+                if let Err(e) = auth.validate_session(&client).await {
+                    tracing::warn!(error = %e, "Session validation failed");
+                }
+            }
+        });
+        SessionMonitor { handle }
+    }
+
+    /// Saves the session to `store` under `account` whenever a response changes the cookies.
+    pub fn with_session_store(mut self, store: Arc<dyn SessionStore>, account: &str) -> Self {
+        self.session_store = Some((store, account.to_string()));
+        self
+    }
+
+    pub fn session_account(&self) -> Option<&str> {
+        self.session_store
+            .as_ref()
+            .map(|(_, account)| account.as_str())
+    }
+
+    /// Writes the current cookies to the configured session store.
+    pub async fn save_session(&self) -> Result<()> {
+        let (store, account) = self
+            .session_store
+            .as_ref()
+            .ok_or_else(|| TwitterError::Session("No session store configured".into()))?;
+        let session = SessionFile::new(self.stored_cookies().await);
+        store.save(account, &session).await
+    }
+
+    /// Replaces the cookies with the stored session. Returns `false` if none was saved.
+    pub async fn load_session(&self) -> Result<bool> {
+        let (store, account) = self
+            .session_store
+            .as_ref()
+            .ok_or_else(|| TwitterError::Session("No session store configured".into()))?;
+        match store.load(account).await? {
+            Some(session) => {
+                self.replace_cookies(session.cookies).await;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub async fn delete_session(&self) -> Result<()> {
+        if let Some((store, account)) = &self.session_store {
+            store.delete(account).await?;
+        }
+        Ok(())
+    }
+
+    async fn init_login(&mut self, client: &TwitterClient) -> Result<FlowResponse> {
         self.update_guest_token(client).await?;
 
         let init_request = FlowInitRequest {
@@ -191,42 +475,42 @@ impl TwitterUserAuth {
         let mut headers = HeaderMap::new();
         self.install_headers(&mut headers).await?;
 
-        let (response, raw_headers) = request_api(
+        // Login steps are sent once: X ties the flow to the guest token it started with,
+        // so a replay with a new token would only count as another password attempt.
+        let (response, _) = request_api_once(
             client,
-            "https://api.x.com/1.1/onboarding/task.json?flow_name=login",
+            &client
+                .hosts
+                .login_url("1.1/onboarding/task.json?flow_name=login"),
             headers,
             reqwest::Method::POST,
             Some(json!(init_request)),
         )
         .await?;
 
-        self.store_cookies_from_headers(&raw_headers).await;
-
         Ok(response)
     }
 
     async fn execute_flow_task(
         &self,
-        client: &Client,
+        client: &TwitterClient,
         request: FlowTaskRequest,
     ) -> Result<FlowResponse> {
         let mut headers = HeaderMap::new();
         self.install_headers(&mut headers).await?;
 
-        let (flow_response, raw_headers) = request_api::<FlowResponse>(
+        let (flow_response, _) = request_api_once::<FlowResponse>(
             client,
-            "https://api.x.com/1.1/onboarding/task.json",
+            &client.hosts.login_url("1.1/onboarding/task.json"),
             headers,
             reqwest::Method::POST,
             Some(json!(request)),
         )
         .await?;
 
-        self.store_cookies_from_headers(&raw_headers).await;
-
         if let Some(subtasks) = &flow_response.subtasks {
             if subtasks.iter().any(|s| s.subtask_id == "DenyLoginSubtask") {
-                return Err(TwitterError::Auth("Login denied".into()));
+                return Err(TwitterError::Login(LoginError::Denied));
             }
         }
 
@@ -235,87 +519,183 @@ impl TwitterUserAuth {
 
     pub async fn login(
         &mut self,
-        client: &Client,
+        client: &TwitterClient,
         username: &str,
         password: &str,
         email: Option<&str>,
         two_factor_secret: Option<&str>,
     ) -> Result<()> {
+        let credentials = LoginCredentials {
+            username,
+            password,
+            email,
+            two_factor_secret,
+        };
+        let span = tracing::info_span!("login", username = %username);
+        self.run_login_flow(client, &credentials)
+            .instrument(span)
+            .await
+    }
+
+    /// Walks the login flow one subtask at a time, each in its own span with its timing.
+    async fn run_login_flow(
+        &mut self,
+        client: &TwitterClient,
+        credentials: &LoginCredentials<'_>,
+    ) -> Result<()> {
+        let started = Instant::now();
         let mut flow_response = self.init_login(client).await?;
-        println!("flow_response: {flow_response:?}");
         let mut flow_token = flow_response.flow_token;
 
         while let Some(subtasks) = &flow_response.subtasks {
             if let Some(subtask) = subtasks.first() {
-                flow_response = match SubtaskType::from(subtask.subtask_id.as_str()) {
-                    SubtaskType::LoginJsInstrumentation => {
-                        self.handle_js_instrumentation_subtask(client, flow_token)
-                            .await?
+                let span = tracing::debug_span!("login_subtask", subtask_id = %subtask.subtask_id);
+                let subtask_started = Instant::now();
+                let result = self
+                    .handle_subtask(client, flow_token, subtask, credentials)
+                    .instrument(span.clone())
+                    .await;
+                span.in_scope(|| {
+                    let elapsed_ms = subtask_started.elapsed().as_millis() as u64;
+                    match &result {
+                        Ok(_) => tracing::debug!(elapsed_ms, "Login subtask completed"),
+                        Err(e) => tracing::warn!(elapsed_ms, error = %e, "Login subtask failed"),
                     }
-                    SubtaskType::LoginEnterUserIdentifier => {
-                        self.handle_username_input(client, flow_token, username)
+                });
+                flow_response = result?;
+                flow_token = flow_response.flow_token;
+            } else {
+                break;
+            }
+        }
+
+        tracing::info!(
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "Logged in"
+        );
+        self.health.clear_rejection();
+        Ok(())
+    }
+
+    async fn handle_subtask(
+        &self,
+        client: &TwitterClient,
+        flow_token: String,
+        subtask: &Subtask,
+        credentials: &LoginCredentials<'_>,
+    ) -> Result<FlowResponse> {
+        let response = match SubtaskType::from(subtask.subtask_id.as_str()) {
+            SubtaskType::LoginJsInstrumentation => {
+                self.handle_js_instrumentation_subtask(client, flow_token)
+                    .await?
+            }
+            SubtaskType::LoginEnterUserIdentifier => {
+                self.handle_username_input(client, flow_token, credentials.username)
+                    .await?
+            }
+            SubtaskType::LoginEnterPassword => {
+                self.handle_password_input(client, flow_token, credentials.password)
+                    .await?
+            }
+            SubtaskType::LoginAcid => {
+                match (
+                    subtask.identifier_kind(ChallengeKind::Email),
+                    credentials.email,
+                ) {
+                    (ChallengeKind::Email, Some(email_str)) => {
+                        self.handle_email_verification(client, flow_token, email_str)
                             .await?
                     }
-                    SubtaskType::LoginEnterPassword => {
-                        self.handle_password_input(client, flow_token, password)
+                    (kind, _) => {
+                        self.handle_challenge(client, flow_token, subtask, kind)
                             .await?
                     }
-                    SubtaskType::LoginAcid => {
-                        if let Some(email_str) = email {
-                            self.handle_email_verification(client, flow_token, email_str)
-                                .await?
-                        } else {
-                            return Err(TwitterError::Auth(
-                                "Email required for verification".into(),
-                            ));
-                        }
-                    }
-                    SubtaskType::AccountDuplicationCheck => {
-                        self.handle_account_duplication_check(client, flow_token)
+                }
+            }
+            SubtaskType::AccountDuplicationCheck => {
+                self.handle_account_duplication_check(client, flow_token)
+                    .await?
+            }
+            SubtaskType::LoginTwoFactorAuthChooseMethod => {
+                match (
+                    credentials.two_factor_secret,
+                    subtask.authenticator_app_choice(),
+                ) {
+                    (Some(_), Some(choice)) => {
+                        self.handle_two_factor_method(client, flow_token, &choice)
                             .await?
                     }
-                    SubtaskType::LoginTwoFactorAuthChallenge => {
-                        if let Some(secret) = two_factor_secret {
-                            self.handle_two_factor_auth(client, flow_token, secret)
-                                .await?
-                        } else {
-                            return Err(TwitterError::Auth(
-                                "Two factor authentication required".into(),
-                            ));
-                        }
+                    _ => {
+                        self.handle_challenge(
+                            client,
+                            flow_token,
+                            subtask,
+                            ChallengeKind::TwoFactorMethod,
+                        )
+                        .await?
                     }
-                    SubtaskType::LoginEnterAlternateIdentifier => {
-                        if let Some(email_str) = email {
-                            self.handle_alternate_identifier(client, flow_token, email_str)
-                                .await?
-                        } else {
-                            return Err(TwitterError::Auth(
-                                "Email required for alternate identifier".into(),
-                            ));
-                        }
+                }
+            }
+            SubtaskType::LoginTwoFactorAuthChallenge => {
+                let kind = if subtask.prompt().contains("backup") {
+                    ChallengeKind::BackupCode
+                } else {
+                    ChallengeKind::TwoFactorCode
+                };
+                match (kind, credentials.two_factor_secret) {
+                    (ChallengeKind::TwoFactorCode, Some(secret)) => {
+                        self.handle_two_factor_auth(client, flow_token, secret)
+                            .await?
                     }
-                    SubtaskType::LoginSuccess => {
-                        self.handle_success_subtask(client, flow_token).await?
+                    (kind, _) => {
+                        self.handle_challenge(client, flow_token, subtask, kind)
+                            .await?
                     }
-                    SubtaskType::DenyLogin => {
-                        return Err(TwitterError::Auth("Login denied".into()));
+                }
+            }
+            SubtaskType::LoginEnterOtp => {
+                self.handle_challenge(client, flow_token, subtask, ChallengeKind::Otp)
+                    .await?
+            }
+            SubtaskType::LoginEnterAlternateIdentifier => {
+                match (
+                    subtask.identifier_kind(ChallengeKind::AlternateIdentifier),
+                    credentials.email,
+                ) {
+                    (ChallengeKind::AlternateIdentifier, Some(email_str)) => {
+                        self.handle_alternate_identifier(client, flow_token, email_str)
+                            .await?
                     }
-                    SubtaskType::Unknown(id) => {
-                        return Err(TwitterError::Auth(format!("Unhandled subtask: {}", id)));
+                    (kind, _) => {
+                        self.handle_challenge(client, flow_token, subtask, kind)
+                            .await?
                     }
+                }
+            }
+            SubtaskType::ArkoseLogin => {
+                self.handle_challenge(client, flow_token, subtask, ChallengeKind::Captcha)
+                    .await?
+            }
+            SubtaskType::LoginSuccess => self.handle_success_subtask(client, flow_token).await?,
+            SubtaskType::DenyLogin => {
+                return Err(TwitterError::Login(LoginError::Denied));
+            }
+            SubtaskType::Unknown => {
+                let kind = if subtask.is_suspicious_login_prompt() {
+                    ChallengeKind::SuspiciousLogin
+                } else {
+                    ChallengeKind::Unknown
                 };
-                flow_token = flow_response.flow_token;
-            } else {
-                break;
+                self.handle_challenge(client, flow_token, subtask, kind)
+                    .await?
             }
-        }
-
-        Ok(())
+        };
+        Ok(response)
     }
 
     async fn handle_js_instrumentation_subtask(
         &self,
-        client: &Client,
+        client: &TwitterClient,
         flow_token: String,
     ) -> Result<FlowResponse> {
         let request = FlowTaskRequest {
@@ -333,7 +713,7 @@ impl TwitterUserAuth {
 
     async fn handle_username_input(
         &self,
-        client: &Client,
+        client: &TwitterClient,
         flow_token: String,
         username: &str,
     ) -> Result<FlowResponse> {
@@ -361,7 +741,7 @@ impl TwitterUserAuth {
 
     async fn handle_password_input(
         &self,
-        client: &Client,
+        client: &TwitterClient,
         flow_token: String,
         password: &str,
     ) -> Result<FlowResponse> {
@@ -380,7 +760,7 @@ impl TwitterUserAuth {
 
     async fn handle_email_verification(
         &self,
-        client: &Client,
+        client: &TwitterClient,
         flow_token: String,
         email: &str,
     ) -> Result<FlowResponse> {
@@ -399,7 +779,7 @@ impl TwitterUserAuth {
 
     async fn handle_account_duplication_check(
         &self,
-        client: &Client,
+        client: &TwitterClient,
         flow_token: String,
     ) -> Result<FlowResponse> {
         let request = FlowTaskRequest {
@@ -416,7 +796,7 @@ impl TwitterUserAuth {
 
     async fn handle_two_factor_auth(
         &self,
-        client: &Client,
+        client: &TwitterClient,
         flow_token: String,
         secret: &str,
     ) -> Result<FlowResponse> {
@@ -442,7 +822,7 @@ impl TwitterUserAuth {
 
     async fn handle_alternate_identifier(
         &self,
-        client: &Client,
+        client: &TwitterClient,
         flow_token: String,
         email: &str,
     ) -> Result<FlowResponse> {
@@ -459,58 +839,95 @@ impl TwitterUserAuth {
         self.execute_flow_task(client, request).await
     }
 
-    async fn handle_success_subtask(
+    async fn handle_two_factor_method(
         &self,
-        client: &Client,
+        client: &TwitterClient,
         flow_token: String,
+        choice: &str,
     ) -> Result<FlowResponse> {
         let request = FlowTaskRequest {
             flow_token,
-            subtask_inputs: vec![],
+            subtask_inputs: vec![json!({
+                "subtask_id": "LoginTwoFactorAuthChooseMethod",
+                "choice_selection": {
+                    "selected_choices": [choice],
+                    "link": "next_link"
+                }
+            })],
         };
         self.execute_flow_task(client, request).await
     }
 
-    async fn update_guest_token(&mut self, client: &Client) -> Result<()> {
-        let url = "https://api.x.com/1.1/guest/activate.json";
+    /// Asks the challenge handler to answer `subtask`, failing with the [`LoginError`] for
+    /// `kind` if there is no handler.
+    async fn handle_challenge(
+        &self,
+        client: &TwitterClient,
+        flow_token: String,
+        subtask: &Subtask,
+        kind: ChallengeKind,
+    ) -> Result<FlowResponse> {
+        let Some(handler) = &self.challenge_handler else {
+            return Err(TwitterError::Login(kind.unanswered(&subtask.subtask_id)));
+        };
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "Authorization",
-            HeaderValue::from_str(&format!("Bearer {}", self.bearer_token))
-                .map_err(|e| TwitterError::Auth(e.to_string()))?,
-        );
+        let challenge = LoginChallenge {
+            kind,
+            subtask_id: subtask.subtask_id.clone(),
+            subtask: subtask.to_value(),
+        };
+        tracing::debug!(subtask_id = %challenge.subtask_id, ?kind, "Asking for login challenge");
+        let input = challenge
+            .input(handler.handle(&challenge).await?)
+            .ok_or(TwitterError::Login(LoginError::Aborted))?;
 
-        if let Some(cookie_header) = self.serialized_cookie_header().await {
-            headers.insert(
-                "Cookie",
-                HeaderValue::from_str(&cookie_header)
-                    .map_err(|e| TwitterError::Auth(e.to_string()))?,
-            );
-        }
+        let request = FlowTaskRequest {
+            flow_token,
+            subtask_inputs: vec![input],
+        };
+        self.execute_flow_task(client, request).await
+    }
 
-        let (response, raw_headers) = request_api::<serde_json::Value>(
-            client,
-            url,
-            headers,
-            reqwest::Method::POST,
-            None,
-        )
-        .await?;
+    async fn handle_success_subtask(
+        &self,
+        client: &TwitterClient,
+        flow_token: String,
+    ) -> Result<FlowResponse> {
+        let request = FlowTaskRequest {
+            flow_token,
+            subtask_inputs: vec![],
+        };
+        self.execute_flow_task(client, request).await
+    }
+
+    /// Uses a guest token for at most `max_age` before activating a new one.
+    pub fn with_guest_token_max_age(mut self, max_age: std::time::Duration) -> Self {
+        self.guest_token_max_age = max_age;
+        self
+    }
 
-        self.store_cookies_from_headers(&raw_headers).await;
+    pub fn guest_token(&self) -> Option<GuestToken> {
+        self.guest_token.lock().unwrap().clone()
+    }
 
-        let guest_token = response
-            .get("guest_token")
-            .and_then(|token| token.as_str())
-            .ok_or_else(|| TwitterError::Auth("Failed to get guest token".into()))?;
+    /// Whether the jar holds a logged-in session rather than only guest cookies.
+    pub async fn has_session(&self) -> bool {
+        self.cookie_jar.lock().await.get("auth_token").is_some()
+    }
 
-        self.guest_token = Some(guest_token.to_string());
-        self.created_at = Some(Utc::now());
+    /// Activates a new guest token and stores it along with the `gt` cookie.
+    pub async fn update_guest_token(&self, client: &TwitterClient) -> Result<String> {
+        let token = get_guest_token(client, &self.bearer_token).await?;
+        tracing::debug!("Activated guest token");
+
+        *self.guest_token.lock().unwrap() = Some(GuestToken {
+            token: token.clone(),
+            created_at: Utc::now(),
+        });
 
         {
             let mut cookie_jar = self.cookie_jar.lock().await;
-            let cookie = cookie::Cookie::build("gt", guest_token)
+            let cookie = cookie::Cookie::build("gt", token.clone())
                 .path("/")
                 .domain("x.com")
                 .secure(true)
@@ -519,7 +936,30 @@ impl TwitterUserAuth {
             cookie_jar.add(cookie.into_owned());
         }
 
-        Ok(())
+        Ok(token)
+    }
+
+    /// The current guest token, activating a new one if there is none or it is older than
+    /// the configured maximum age.
+    pub async fn ensure_guest_token(&self, client: &TwitterClient) -> Result<String> {
+        let _refresh = self.guest_refresh.lock().await;
+        match self.guest_token() {
+            Some(guest) if guest.age() < self.guest_token_max_age => Ok(guest.token),
+            _ => self.update_guest_token(client).await,
+        }
+    }
+
+    /// Replaces `rejected` with a new guest token, unless another request already has.
+    pub async fn rotate_guest_token(
+        &self,
+        client: &TwitterClient,
+        rejected: &str,
+    ) -> Result<String> {
+        let _refresh = self.guest_refresh.lock().await;
+        match self.guest_token() {
+            Some(guest) if guest.token != rejected => Ok(guest.token),
+            _ => self.update_guest_token(client).await,
+        }
     }
 
     pub async fn update_cookies(&self, response: &reqwest::Response) -> Result<()> {
@@ -528,18 +968,51 @@ impl TwitterUserAuth {
         Ok(())
     }
 
-    pub async fn save_cookies_to_file(&self, file_path: &str) -> Result<()> {
-        tracing::trace!("Saving cookies - attempting to lock");
+    /// Every cookie in the jar, with its attributes.
+    pub async fn stored_cookies(&self) -> Vec<StoredCookie> {
         let cookie_jar = self.cookie_jar.lock().await;
-        let cookies: Vec<_> = cookie_jar.iter().collect();
+        cookie_jar.iter().map(StoredCookie::from_cookie).collect()
+    }
 
-        let cookie_data: Vec<(String, String)> = cookies
-            .iter()
-            .map(|cookie| (cookie.name().to_string(), cookie.value().to_string()))
-            .collect();
+    /// Replaces the jar with `cookies`, skipping any that have already expired.
+    pub async fn replace_cookies(&self, cookies: Vec<StoredCookie>) {
+        self.health.clear_rejection();
+        let mut cookie_jar = self.cookie_jar.lock().await;
+        *cookie_jar = CookieJar::new();
+        for cookie in cookies.iter().filter(|cookie| !cookie.is_expired()) {
+            cookie_jar.add(cookie.to_cookie());
+        }
+    }
+
+    /// Serializes the jar in `format`.
+    pub async fn export_cookies(&self, format: CookieFormat) -> Result<String> {
+        session::export_cookies(&self.stored_cookies().await, format)
+    }
 
-        let json = serde_json::to_string_pretty(&cookie_data)
-            .map_err(|e| TwitterError::Cookie(format!("Failed to serialize cookies: {}", e)))?;
+    /// Replaces the jar with cookies parsed from `content`. The format is detected when
+    /// `format` is `None`.
+    pub async fn import_cookies(
+        &mut self,
+        content: &str,
+        format: Option<CookieFormat>,
+    ) -> Result<()> {
+        let cookies = session::import_cookies(content, format)?;
+        tracing::trace!(count = cookies.len(), "Imported cookies");
+        self.replace_cookies(cookies).await;
+        Ok(())
+    }
+
+    pub async fn save_cookies_to_file(&self, file_path: &str) -> Result<()> {
+        self.export_cookies_to_file(file_path, CookieFormat::Session)
+            .await
+    }
+
+    pub async fn export_cookies_to_file(
+        &self,
+        file_path: &str,
+        format: CookieFormat,
+    ) -> Result<()> {
+        let content = self.export_cookies(format).await?;
 
         let mut file = OpenOptions::new()
             .write(true)
@@ -548,12 +1021,13 @@ impl TwitterUserAuth {
             .open(file_path)
             .map_err(|e| TwitterError::Cookie(format!("Failed to open cookie file: {}", e)))?;
 
-        file.write_all(json.as_bytes())
+        file.write_all(content.as_bytes())
             .map_err(|e| TwitterError::Cookie(format!("Failed to write cookies: {}", e)))?;
 
         Ok(())
     }
 
+    /// Loads a session file, a Netscape `cookies.txt` or a browser extension export.
     pub async fn load_cookies_from_file(&mut self, file_path: &str) -> Result<()> {
         tracing::trace!("Loading cookies - attempting to lock");
 
@@ -566,27 +1040,8 @@ impl TwitterUserAuth {
         let mut contents = String::new();
         file.read_to_string(&mut contents)
             .map_err(|e| TwitterError::Cookie(format!("Failed to read cookie file: {}", e)))?;
-        let cookie_data: Vec<(String, String)> = serde_json::from_str(&contents)
-            .map_err(|e| TwitterError::Cookie(format!("Failed to parse cookie file: {}", e)))?;
-
-        tracing::trace!(?cookie_data, "Loaded cookie data");
 
-        let mut cookie_jar = self.cookie_jar.lock().await;
-
-        *cookie_jar = CookieJar::new();
-
-        for (name, value) in cookie_data {
-            let cookie = cookie::Cookie::build(name, value)
-                .path("/")
-                .domain("x.com")
-                .secure(true)
-                .http_only(true)
-                .finish();
-            cookie_jar.add(cookie.into_owned());
-        }
-        let mut headers = HeaderMap::new();
-        self.install_headers(&mut headers).await?;
-        Ok(())
+        self.import_cookies(&contents, None).await
     }
 
     pub async fn get_cookie_string(&self) -> Result<String> {
@@ -602,30 +1057,18 @@ impl TwitterUserAuth {
         Ok(cookie_string)
     }
 
+    /// Accepts any of the JSON formats understood by [`Self::import_cookies`].
     pub async fn set_cookies(&mut self, json_str: &str) -> Result<()> {
-        let cookie_data: Vec<(String, String)> = serde_json::from_str(json_str)
-            .map_err(|e| TwitterError::Cookie(format!("Failed to parse cookie JSON: {}", e)))?;
-
-        let mut cookie_jar = self.cookie_jar.lock().await;
-
-        *cookie_jar = CookieJar::new();
-
-        for (name, value) in cookie_data {
-            let cookie = cookie::Cookie::build(name, value)
-                .path("/")
-                .domain("x.com")
-                .secure(true)
-                .http_only(true)
-                .finish();
-            cookie_jar.add(cookie.into_owned());
+        match CookieFormat::detect(json_str) {
+            CookieFormat::Netscape => Err(TwitterError::Cookie(
+                "Failed to parse cookie JSON: expected an array or object".into(),
+            )),
+            format => self.import_cookies(json_str, Some(format)).await,
         }
-
-        let mut headers = HeaderMap::new();
-        self.install_headers(&mut headers).await?;
-        Ok(())
     }
 
     pub async fn set_from_cookie_string(&mut self, cookie_string: &str) -> Result<()> {
+        self.health.clear_rejection();
         let mut cookie_jar = self.cookie_jar.lock().await;
         *cookie_jar = CookieJar::new();
         for cookie_str in cookie_string.split(';') {
@@ -652,13 +1095,15 @@ impl TwitterUserAuth {
         Ok(())
     }
 
-    pub async fn is_logged_in(&self, client: &Client) -> Result<bool> {
+    /// Probes verify_credentials with the current session only: a rejected session is
+    /// reported rather than triggering auto re-login.
+    pub async fn is_logged_in(&self, client: &TwitterClient) -> Result<bool> {
         let mut headers = HeaderMap::new();
         self.install_headers(&mut headers).await?;
 
-        let (response, _) = request_api::<serde_json::Value>(
+        let (response, _) = request_api_once::<serde_json::Value>(
             client,
-            "https://api.x.com/1.1/account/verify_credentials.json",
+            &client.hosts.api_url("1.1/account/verify_credentials.json"),
             headers,
             reqwest::Method::GET,
             None,
@@ -679,6 +1124,91 @@ impl TwitterUserAuth {
         }
         Ok(true)
     }
+
+    /// Ends the session on X, then clears the cookies and guest token and deletes the
+    /// session from the session store.
+    ///
+    /// The local session is cleared even if X rejects the logout. A rejection because the
+    /// session had already ended is not an error.
+    pub async fn logout(&self, client: &TwitterClient) -> Result<()> {
+        let result = if self.has_session().await {
+            let mut headers = HeaderMap::new();
+            self.install_headers(&mut headers).await?;
+            request_api_once::<serde_json::Value>(
+                client,
+                &client.hosts.api_url("1.1/account/logout.json"),
+                headers,
+                reqwest::Method::POST,
+                None,
+            )
+            .await
+            .map(|_| ())
+        } else {
+            Ok(())
+        };
+
+        self.replace_cookies(Vec::new()).await;
+        *self.guest_token.lock().unwrap() = None;
+        self.delete_session().await?;
+        tracing::info!("Logged out");
+
+        match result {
+            Err(e) if relogin::is_session_rejected(&e) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Devices and apps logged in to the account.
+    pub async fn list_sessions(&self, client: &TwitterClient) -> Result<Vec<AccountSession>> {
+        #[derive(Deserialize)]
+        struct SessionList {
+            #[serde(default)]
+            sessions: Vec<AccountSession>,
+        }
+
+        let mut headers = HeaderMap::new();
+        self.install_headers(&mut headers).await?;
+        let (list, _) = request_api::<SessionList>(
+            client,
+            &client.hosts.web_api_url("1.1/account/sessions/list.json"),
+            headers,
+            reqwest::Method::GET,
+            None,
+        )
+        .await?;
+        Ok(list.sessions)
+    }
+
+    /// Logs out the session identified by `hashed_token`.
+    pub async fn revoke_session(&self, client: &TwitterClient, hashed_token: &str) -> Result<()> {
+        let mut headers = HeaderMap::new();
+        self.install_headers(&mut headers).await?;
+        request_form_api::<serde_json::Value>(
+            client,
+            &client.hosts.web_api_url("1.1/account/sessions/revoke.json"),
+            headers,
+            vec![("hashed_token".to_string(), hashed_token.to_string())],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Logs out every session of the account except this one.
+    pub async fn revoke_other_sessions(&self, client: &TwitterClient) -> Result<()> {
+        let mut headers = HeaderMap::new();
+        self.install_headers(&mut headers).await?;
+        request_form_api::<serde_json::Value>(
+            client,
+            &client
+                .hosts
+                .web_api_url("1.1/account/sessions/revoke_all.json"),
+            headers,
+            Vec::new(),
+        )
+        .await?;
+        tracing::info!("Revoked other sessions");
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -712,45 +1242,24 @@ impl TwitterAuth for TwitterUserAuth {
             HeaderValue::from_str(&format!("Bearer {}", self.bearer_token))
                 .map_err(|e| TwitterError::Auth(e.to_string()))?,
         );
-        if let Some(token) = &self.guest_token {
+        if let Some(guest) = self.guest_token() {
             headers.insert(
                 "x-guest-token",
-                HeaderValue::from_str(token).map_err(|e| TwitterError::Auth(e.to_string()))?,
+                HeaderValue::from_str(&guest.token)
+                    .map_err(|e| TwitterError::Auth(e.to_string()))?,
             );
         }
+        // The user agent, client hints and languages come from the client's header profile.
         headers.insert("accept", HeaderValue::from_static("*/*"));
-        headers.insert(
-            "accept-language",
-            HeaderValue::from_static("en-US,en;q=0.9"),
-        );
         headers.insert("content-type", HeaderValue::from_static("application/json"));
         headers.insert("cache-control", HeaderValue::from_static("no-cache"));
         headers.insert("origin", HeaderValue::from_static("https://x.com"));
         headers.insert("pragma", HeaderValue::from_static("no-cache"));
-        headers.insert("priority", HeaderValue::from_static("u=1, i"));
         headers.insert("referer", HeaderValue::from_static("https://x.com/"));
-        headers.insert(
-            "sec-ch-ua",
-            HeaderValue::from_static(
-                "\"Google Chrome\";v=\"135\", \"Not-A.Brand\";v=\"8\", \"Chromium\";v=\"135\"",
-            ),
-        );
-        headers.insert("sec-ch-ua-mobile", HeaderValue::from_static("?0"));
-        headers.insert(
-            "sec-ch-ua-platform",
-            HeaderValue::from_static("\"Windows\""),
-        );
         headers.insert("sec-fetch-dest", HeaderValue::from_static("empty"));
         headers.insert("sec-fetch-mode", HeaderValue::from_static("cors"));
         headers.insert("sec-fetch-site", HeaderValue::from_static("same-origin"));
-        headers.insert(
-            "user-agent",
-            HeaderValue::from_static(
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/135.0.0.0 Safari/537.36",
-            ),
-        );
         headers.insert("x-twitter-active-user", HeaderValue::from_static("yes"));
-        headers.insert("x-twitter-client-language", HeaderValue::from_static("en"));
         headers.insert(
             "x-twitter-auth-type",
             HeaderValue::from_static("OAuth2Client"),
@@ -765,11 +1274,740 @@ impl TwitterAuth for TwitterUserAuth {
     }
 
     fn delete_token(&mut self) {
-        self.guest_token = None;
-        self.created_at = None;
+        *self.guest_token.lock().unwrap() = None;
     }
 
     fn as_any(&self) -> &dyn Any {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::challenge::{
+        ChallengeKind, ChallengeResponse, LoginChallenge, LoginChallengeHandler,
+    };
+    use crate::auth::config::AuthConfig;
+    use crate::auth::health::{SessionEvent, SessionStatus};
+    use crate::auth::session::CookieFormat;
+    use crate::auth::store::{MemorySessionStore, SessionStore};
+    use crate::constants::BEARER_TOKEN;
+    use crate::error::{LoginError, Result, TwitterError};
+    use crate::mock::{MockResponse, MockServer, MOCK_AUTH_TOKEN, MOCK_CT0, MOCK_GUEST_TOKEN};
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_login_flow() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.guest_scraper().await.unwrap();
+
+        scraper
+            .login(
+                "mockuser".to_string(),
+                "password".to_string(),
+                Some("mock@example.com".to_string()),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let cookies = scraper.get_cookie_string().await.unwrap();
+        assert!(cookies.contains(&format!("auth_token={}", MOCK_AUTH_TOKEN)));
+        assert!(cookies.contains(&format!("ct0={}", MOCK_CT0)));
+
+        let steps = server.requests_to("/1.1/onboarding/task.json");
+        let password_step = steps
+            .iter()
+            .filter_map(|step| step.body_json())
+            .find(|body| body["subtask_inputs"][0]["subtask_id"] == "LoginEnterPassword")
+            .unwrap();
+        assert_eq!(
+            password_step["subtask_inputs"][0]["enter_password"]["password"],
+            "password"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejected_login_step_is_not_replayed() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.guest_scraper().await.unwrap();
+        server.mock_once(
+            "/1.1/onboarding/task.json",
+            MockResponse::json(403, json!({"errors": [{"code": 399}]})),
+        );
+
+        assert!(scraper
+            .login("mockuser".into(), "password".into(), None, None)
+            .await
+            .is_err());
+
+        assert_eq!(server.requests_to("/1.1/onboarding/task.json").len(), 1);
+        assert_eq!(server.requests_to("/1.1/guest/activate.json").len(), 1);
+    }
+
+    struct ScriptedHandler {
+        response: ChallengeResponse,
+        seen: Mutex<Vec<LoginChallenge>>,
+    }
+
+    #[async_trait]
+    impl LoginChallengeHandler for ScriptedHandler {
+        async fn handle(&self, challenge: &LoginChallenge) -> Result<ChallengeResponse> {
+            self.seen.lock().unwrap().push(challenge.clone());
+            Ok(self.response.clone())
+        }
+    }
+
+    const LOGIN_CHALLENGES: &str = include_str!("../mock/fixtures/login_challenges.json");
+    const TOTP_SECRET: &str = "JBSWY3DPEHPK3PXPJBSWY3DP";
+
+    struct LoginAttempt {
+        result: Result<()>,
+        challenges: Vec<LoginChallenge>,
+        /// The first `subtask_inputs` entry of every submitted step.
+        steps: Vec<serde_json::Value>,
+        logged_in: bool,
+    }
+
+    /// Logs in against a flow whose first step is the recorded `fixture`, answering
+    /// challenges with `response` if given.
+    async fn login_with_challenge(
+        fixture: &str,
+        email: Option<&str>,
+        two_factor_secret: Option<&str>,
+        response: Option<ChallengeResponse>,
+    ) -> LoginAttempt {
+        let challenges: serde_json::Value = serde_json::from_str(LOGIN_CHALLENGES).unwrap();
+        let server = MockServer::start().await.unwrap();
+        server.mock_once(
+            "/1.1/onboarding/task.json",
+            MockResponse::json(200, challenges[fixture].clone()),
+        );
+
+        let mut scraper = server.guest_scraper().await.unwrap();
+        let handler = response.map(|response| {
+            Arc::new(ScriptedHandler {
+                response,
+                seen: Mutex::new(Vec::new()),
+            })
+        });
+        if let Some(handler) = &handler {
+            scraper
+                .set_login_challenge_handler(handler.clone())
+                .unwrap();
+        }
+
+        let result = scraper
+            .login(
+                "mockuser".into(),
+                "password".into(),
+                email.map(String::from),
+                two_factor_secret.map(String::from),
+            )
+            .await;
+        let steps = server
+            .requests_to("/1.1/onboarding/task.json")
+            .iter()
+            .filter_map(|step| step.body_json())
+            .filter_map(|body| body["subtask_inputs"].get(0).cloned())
+            .collect();
+        LoginAttempt {
+            result,
+            challenges: handler
+                .map(|handler| handler.seen.lock().unwrap().clone())
+                .unwrap_or_default(),
+            steps,
+            logged_in: scraper
+                .get_cookie_string()
+                .await
+                .unwrap()
+                .contains(MOCK_AUTH_TOKEN),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login_challenges_without_handler() {
+        let email = Some("mock@example.com");
+        let secret = Some(TOTP_SECRET);
+        let cases = [
+            ("email_confirmation", email, None, None),
+            (
+                "email_confirmation",
+                None,
+                None,
+                Some(LoginError::EmailRequired),
+            ),
+            (
+                "phone_confirmation",
+                email,
+                None,
+                Some(LoginError::PhoneNumberRequired),
+            ),
+            (
+                "verification_code",
+                email,
+                None,
+                Some(LoginError::VerificationCodeRequired),
+            ),
+            ("otp", email, secret, Some(LoginError::OtpRequired)),
+            ("alternate_identifier", email, None, None),
+            (
+                "alternate_identifier",
+                None,
+                None,
+                Some(LoginError::EmailRequired),
+            ),
+            ("two_factor", None, secret, None),
+            (
+                "two_factor",
+                email,
+                None,
+                Some(LoginError::TwoFactorRequired),
+            ),
+            (
+                "backup_code",
+                None,
+                secret,
+                Some(LoginError::BackupCodeRequired),
+            ),
+            ("choose_method", None, secret, None),
+            (
+                "choose_method",
+                None,
+                None,
+                Some(LoginError::TwoFactorMethodRequired),
+            ),
+            (
+                "suspicious_login",
+                email,
+                secret,
+                Some(LoginError::SuspiciousLogin),
+            ),
+            ("arkose", email, secret, Some(LoginError::CaptchaRequired)),
+            ("denied", email, secret, Some(LoginError::Denied)),
+            (
+                "unknown",
+                email,
+                secret,
+                Some(LoginError::UnhandledSubtask("LoginEnterRecaptcha".into())),
+            ),
+        ];
+
+        for (fixture, email, secret, expected) in cases {
+            let attempt = login_with_challenge(fixture, email, secret, None).await;
+            match (attempt.result, expected) {
+                (Ok(()), None) => assert!(attempt.logged_in, "{}", fixture),
+                (Err(TwitterError::Login(error)), Some(expected)) => {
+                    assert_eq!(error, expected, "{}", fixture)
+                }
+                (result, expected) => panic!("{}: got {:?}, want {:?}", fixture, result, expected),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login_challenges_with_handler() {
+        let text = |text: &str| ChallengeResponse::Text(text.into());
+        let cases = [
+            (
+                "verification_code",
+                text("123456"),
+                ChallengeKind::VerificationCode,
+                json!({
+                    "subtask_id": "LoginAcid",
+                    "enter_text": { "text": "123456", "link": "next_link" }
+                }),
+            ),
+            (
+                "phone_confirmation",
+                text("+15555550100"),
+                ChallengeKind::PhoneNumber,
+                json!({
+                    "subtask_id": "LoginAcid",
+                    "enter_text": { "text": "+15555550100", "link": "next_link" }
+                }),
+            ),
+            (
+                "otp",
+                text("654321"),
+                ChallengeKind::Otp,
+                json!({
+                    "subtask_id": "LoginEnterOtp",
+                    "enter_text": { "text": "654321", "link": "next_link" }
+                }),
+            ),
+            (
+                "backup_code",
+                text("abcd1234efgh"),
+                ChallengeKind::BackupCode,
+                json!({
+                    "subtask_id": "LoginTwoFactorAuthChallenge",
+                    "enter_text": { "text": "abcd1234efgh", "link": "next_link" }
+                }),
+            ),
+            (
+                "choose_method",
+                text("2"),
+                ChallengeKind::TwoFactorMethod,
+                json!({
+                    "subtask_id": "LoginTwoFactorAuthChooseMethod",
+                    "choice_selection": { "selected_choices": ["2"], "link": "next_link" }
+                }),
+            ),
+            (
+                "suspicious_login",
+                ChallengeResponse::Confirm,
+                ChallengeKind::SuspiciousLogin,
+                json!({
+                    "subtask_id": "LoginSuspiciousActivityPrompt",
+                    "alert_dialog": { "link": "next_link" }
+                }),
+            ),
+            (
+                "arkose",
+                text("solved"),
+                ChallengeKind::Captcha,
+                json!({
+                    "subtask_id": "ArkoseLogin",
+                    "web_modal": {
+                        "completion_deeplink":
+                            "twitter://onboarding/web_modal/next_link?access_token=solved",
+                        "link": "next_link"
+                    }
+                }),
+            ),
+            (
+                "unknown",
+                ChallengeResponse::SubtaskInput(json!({ "subtask_id": "LoginEnterOtp" })),
+                ChallengeKind::Unknown,
+                json!({ "subtask_id": "LoginEnterOtp" }),
+            ),
+        ];
+
+        for (fixture, response, kind, input) in cases {
+            let attempt = login_with_challenge(fixture, None, None, Some(response)).await;
+            assert!(attempt.result.is_ok(), "{}: {:?}", fixture, attempt.result);
+            assert_eq!(attempt.challenges[0].kind, kind, "{}", fixture);
+            assert_eq!(attempt.steps[0], input, "{}", fixture);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_challenge_handler_sees_prompt_and_can_abort() {
+        let attempt = login_with_challenge(
+            "verification_code",
+            None,
+            None,
+            Some(ChallengeResponse::Abort),
+        )
+        .await;
+
+        assert!(matches!(
+            attempt.result,
+            Err(TwitterError::Login(LoginError::Aborted))
+        ));
+        assert_eq!(
+            attempt.challenges[0].subtask["enter_text"]["hint_text"],
+            "Confirmation code"
+        );
+        assert!(attempt.steps.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_login_logs_subtasks_without_secrets() {
+        let (logs, _guard) = crate::tests::capture_logs();
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.guest_scraper().await.unwrap();
+        scraper
+            .login("mockuser".into(), "s3cret-pass".into(), None, None)
+            .await
+            .unwrap();
+        let attempt = login_with_challenge(
+            "two_factor",
+            Some("mock@example.com"),
+            Some(TOTP_SECRET),
+            None,
+        )
+        .await;
+        attempt.result.unwrap();
+
+        let logs = logs.text();
+        assert!(logs.contains("subtask_id=LoginEnterPassword"));
+        assert!(logs.contains("subtask_id=LoginTwoFactorAuthChallenge"));
+        assert!(logs.contains("Login subtask completed"));
+        assert!(logs.contains("elapsed_ms="));
+        let code = attempt
+            .steps
+            .iter()
+            .find(|step| step["subtask_id"] == "LoginTwoFactorAuthChallenge")
+            .and_then(|step| step["enter_text"]["text"].as_str())
+            .unwrap()
+            .to_string();
+        for secret in [
+            "s3cret-pass",
+            code.as_str(),
+            "g;mock:",
+            MOCK_AUTH_TOKEN,
+            MOCK_CT0,
+        ] {
+            assert!(!logs.contains(secret), "{} was logged", secret);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_saved_session_keeps_cookie_attributes() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.guest_scraper().await.unwrap();
+        scraper
+            .login("mockuser".into(), "password".into(), None, None)
+            .await
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!("session-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        scraper.save_cookies(path).await.unwrap();
+
+        let mut restored = server.guest_scraper().await.unwrap();
+        restored.load_cookies(path).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let netscape = restored
+            .export_cookies(CookieFormat::Netscape)
+            .await
+            .unwrap();
+        assert!(netscape.contains(&format!("\tct0\t{}", MOCK_CT0)));
+        assert!(netscape
+            .lines()
+            .any(|line| line.starts_with("#HttpOnly_") && line.ends_with(MOCK_AUTH_TOKEN)));
+        assert!(!netscape
+            .lines()
+            .any(|line| line.starts_with("#HttpOnly_") && line.ends_with(MOCK_CT0)));
+    }
+
+    #[tokio::test]
+    async fn test_session_is_saved_when_cookies_change() {
+        let server = MockServer::start().await.unwrap();
+        let store = MemorySessionStore::new();
+
+        let mut scraper = server.guest_scraper().await.unwrap();
+        scraper
+            .set_session_store(std::sync::Arc::new(store.clone()), "mockuser")
+            .unwrap();
+        scraper
+            .login("mockuser".into(), "password".into(), None, None)
+            .await
+            .unwrap();
+
+        let saved = store.load("mockuser").await.unwrap().unwrap();
+        assert!(saved
+            .cookies
+            .iter()
+            .any(|cookie| cookie.name == "auth_token" && cookie.value == MOCK_AUTH_TOKEN));
+
+        let mut restored = server.guest_scraper().await.unwrap();
+        restored
+            .set_session_store(std::sync::Arc::new(store.clone()), "mockuser")
+            .unwrap();
+        assert!(restored.load_session().await.unwrap());
+        let cookies = restored.get_cookie_string().await.unwrap();
+        assert!(cookies.contains(&format!("auth_token={}", MOCK_AUTH_TOKEN)));
+
+        restored
+            .set_session_store(std::sync::Arc::new(store), "otheruser")
+            .unwrap();
+        assert!(!restored.load_session().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_session_is_saved_only_when_login_cookies_change() {
+        let server = MockServer::start().await.unwrap();
+        let store = MemorySessionStore::new();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper
+            .set_session_store(std::sync::Arc::new(store.clone()), "mockuser")
+            .unwrap();
+        let saved = store.load("mockuser").await.unwrap();
+
+        let verify_credentials = "/1.1/account/verify_credentials.json";
+        server.mock_once(
+            verify_credentials,
+            MockResponse::json(200, json!({})).with_header("set-cookie", "lang=en; Path=/"),
+        );
+        scraper.validate_session().await.unwrap();
+        assert_eq!(store.load("mockuser").await.unwrap(), saved);
+
+        server.mock_once(
+            verify_credentials,
+            MockResponse::json(200, json!({})).with_header("set-cookie", "ct0=rotated; Path=/"),
+        );
+        scraper.validate_session().await.unwrap();
+        let saved = store.load("mockuser").await.unwrap().unwrap();
+        assert!(saved
+            .cookies
+            .iter()
+            .any(|cookie| cookie.name == "ct0" && cookie.value == "rotated"));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_max_age_cookie_is_not_a_change() {
+        let server = MockServer::start().await.unwrap();
+        let store = MemorySessionStore::new();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper
+            .set_session_store(std::sync::Arc::new(store.clone()), "mockuser")
+            .unwrap();
+
+        server.mock(
+            "/1.1/account/verify_credentials.json",
+            MockResponse::json(200, json!({}))
+                .with_header("set-cookie", "ct0=rotated; Max-Age=3600; Path=/"),
+        );
+        scraper.validate_session().await.unwrap();
+        let saved = store.load("mockuser").await.unwrap().unwrap();
+        scraper.validate_session().await.unwrap();
+        assert_eq!(store.load("mockuser").await.unwrap(), Some(saved));
+    }
+
+    #[tokio::test]
+    async fn test_validate_session_tracks_rejections() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+        let mut events = scraper.subscribe_session_events().unwrap();
+
+        assert_eq!(
+            scraper.validate_session().await.unwrap(),
+            SessionStatus::Valid
+        );
+
+        server.mock_once(
+            "/1.1/account/verify_credentials.json",
+            MockResponse::json(
+                401,
+                json!({"errors": [{"code": 89, "message": "Invalid or expired token."}]}),
+            ),
+        );
+        assert_eq!(
+            scraper.validate_session().await.unwrap(),
+            SessionStatus::Expired
+        );
+        assert_eq!(
+            scraper.session_status().await.unwrap(),
+            SessionStatus::Expired
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            SessionEvent::Invalidated {
+                status: SessionStatus::Expired
+            }
+        );
+
+        assert_eq!(
+            scraper.validate_session().await.unwrap(),
+            SessionStatus::Valid
+        );
+        server.mock_once(
+            "/1.1/account/verify_credentials.json",
+            MockResponse::json(
+                403,
+                json!({"errors": [{"code": 326, "message": "To protect our users from spam and other malicious activity, this account is temporarily locked."}]}),
+            ),
+        );
+        assert_eq!(
+            scraper.validate_session().await.unwrap(),
+            SessionStatus::Locked
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_session_does_not_log_in_again() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper.enable_auto_relogin(
+            AuthConfig::new(BEARER_TOKEN.to_string()).with_credentials(
+                "mockuser".into(),
+                "password".into(),
+                Some("mock@example.com".into()),
+            ),
+        );
+
+        server.mock(
+            "/1.1/account/verify_credentials.json",
+            MockResponse::json(
+                401,
+                json!({"errors": [{"code": 89, "message": "Invalid or expired token."}]}),
+            ),
+        );
+        assert_eq!(
+            scraper.validate_session().await.unwrap(),
+            SessionStatus::Expired
+        );
+        assert!(server.requests_to("/1.1/onboarding/task.json").is_empty());
+        assert_eq!(
+            server
+                .requests_to("/1.1/account/verify_credentials.json")
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_monitor_reports_invalid_session() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+        let mut events = scraper.subscribe_session_events().unwrap();
+        assert_eq!(
+            scraper.session_status().await.unwrap(),
+            SessionStatus::Valid
+        );
+
+        server.mock(
+            "/1.1/account/verify_credentials.json",
+            MockResponse::json(
+                401,
+                json!({"errors": [{"code": 32, "message": "Could not authenticate you."}]}),
+            ),
+        );
+        let monitor = scraper
+            .start_session_monitor(Duration::from_millis(10))
+            .unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            event,
+            SessionEvent::Invalidated {
+                status: SessionStatus::Expired
+            }
+        );
+        assert!(monitor.is_running());
+        monitor.stop();
+    }
+
+    #[tokio::test]
+    async fn test_guest_token_is_activated_lazily() {
+        let server = MockServer::start().await.unwrap();
+        let mut scraper = server.guest_scraper().await.unwrap();
+        let activate = "/1.1/guest/activate.json";
+        assert!(server.requests_to(activate).is_empty());
+
+        scraper.get_profile("mockuser").await.unwrap();
+        scraper.get_profile("mockuser").await.unwrap();
+        assert_eq!(server.requests_to(activate).len(), 1);
+        for request in server.requests_to("UserByScreenName") {
+            assert_eq!(request.header("x-guest-token"), Some(MOCK_GUEST_TOKEN));
+        }
+
+        // A rejected token is replaced and the request replayed once.
+        server.mock_once(
+            "UserByScreenName",
+            MockResponse::json(
+                403,
+                json!({"errors": [{"code": 200, "message": "Forbidden."}]}),
+            ),
+        );
+        scraper.get_profile("mockuser").await.unwrap();
+        assert_eq!(server.requests_to(activate).len(), 2);
+        assert_eq!(server.requests_to("UserByScreenName").len(), 4);
+
+        // Tokens older than the maximum age are renewed before use.
+        scraper.set_guest_token_max_age(Duration::ZERO).unwrap();
+        scraper.get_profile("mockuser").await.unwrap();
+        assert_eq!(server.requests_to(activate).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_logged_in_requests_skip_guest_activation() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        scraper.get_profile("mockuser").await.unwrap();
+        assert!(server.requests_to("/1.1/guest/activate.json").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_logout_ends_session() {
+        let server = MockServer::start().await.unwrap();
+        let store = MemorySessionStore::new();
+        let mut scraper = server.scraper().await.unwrap();
+        scraper
+            .set_session_store(Arc::new(store.clone()), "mockuser")
+            .unwrap();
+        scraper.save_session().await.unwrap();
+
+        scraper.logout().await.unwrap();
+
+        let logout = server.requests_to("/1.1/account/logout.json");
+        assert_eq!(logout.len(), 1);
+        assert!(logout[0]
+            .header("cookie")
+            .unwrap()
+            .contains(&format!("auth_token={}", MOCK_AUTH_TOKEN)));
+        assert_eq!(scraper.get_cookie_string().await.unwrap(), "");
+        assert_eq!(store.load("mockuser").await.unwrap(), None);
+        assert_eq!(
+            scraper.session_status().await.unwrap(),
+            SessionStatus::Expired
+        );
+
+        scraper.get_profile("mockuser").await.unwrap();
+        assert_eq!(server.requests_to("/1.1/guest/activate.json").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_logout_of_ended_session_clears_cookies() {
+        let server = MockServer::start().await.unwrap();
+        server.mock(
+            "/1.1/account/logout.json",
+            MockResponse::json(
+                401,
+                json!({ "errors": [{ "message": "Could not authenticate you", "code": 32 }] }),
+            ),
+        );
+        let scraper = server.scraper().await.unwrap();
+
+        scraper.logout().await.unwrap();
+        assert_eq!(scraper.get_cookie_string().await.unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn test_list_and_revoke_sessions() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        let sessions = scraper.list_sessions().await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions[0].is_current);
+        assert_eq!(
+            sessions[1].client_application_name.as_deref(),
+            Some("X for iPhone")
+        );
+
+        scraper
+            .revoke_session(&sessions[1].hashed_token)
+            .await
+            .unwrap();
+        let revoke = server.requests_to("/i/api/1.1/account/sessions/revoke.json");
+        assert_eq!(
+            revoke[0].body_text(),
+            format!("hashed_token={}", sessions[1].hashed_token)
+        );
+
+        scraper.revoke_other_sessions().await.unwrap();
+        assert_eq!(
+            server
+                .requests_to("/i/api/1.1/account/sessions/revoke_all.json")
+                .len(),
+            1
+        );
+        assert!(scraper
+            .get_cookie_string()
+            .await
+            .unwrap()
+            .contains(MOCK_AUTH_TOKEN));
+    }
+}
@@ -1,10 +1,19 @@
-use serde::Deserialize;
+use crate::api::rate_limit::RateLimitInfo;
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use thiserror::Error;
 #[derive(Debug, Error, Deserialize)]
 pub enum TwitterError {
     #[error("API error: {0}")]
     Api(String),
 
+    #[error("{0}")]
+    #[serde(skip)]
+    Http(Box<ApiError>),
+
     #[error("Authentication error: {0}")]
     Auth(String),
 
@@ -12,8 +21,13 @@ pub enum TwitterError {
     #[serde(skip)]
     Network(#[from] reqwest::Error),
 
-    #[error("Rate limit exceeded")]
-    RateLimit,
+    #[error("Rate limit exceeded for {endpoint}")]
+    #[serde(skip)]
+    RateLimit {
+        /// When the budget resets, if X reported it.
+        reset_at: Option<DateTime<Utc>>,
+        endpoint: String,
+    },
 
     #[error("Invalid response format: {0}")]
     InvalidResponse(String),
@@ -24,6 +38,21 @@ pub enum TwitterError {
     #[error("Cookie error: {0}")]
     Cookie(String),
 
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("Session store error: {0}")]
+    Session(String),
+
+    /// The operation is not available with the configured auth, such as a GraphQL request
+    /// made with official API credentials.
+    #[error("Not supported: {0}")]
+    Unsupported(String),
+
+    #[error("Login failed: {0}")]
+    #[serde(skip)]
+    Login(LoginError),
+
     #[error("JSON error: {0}")]
     #[serde(skip)]
     Json(#[from] serde_json::Error),
@@ -33,4 +62,209 @@ pub enum TwitterError {
     Io(#[from] std::io::Error),
 }
 
+impl TwitterError {
+    /// The structured API error, if this error came from an X error response.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            TwitterError::Http(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Why the login flow stopped: a step nothing could answer, or X refusing the login.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LoginError {
+    #[error("email required to confirm the account")]
+    EmailRequired,
+    #[error("phone number required to confirm the account")]
+    PhoneNumberRequired,
+    #[error("verification code required")]
+    VerificationCodeRequired,
+    /// `LoginEnterOtp`: a one-time code sent by email or SMS.
+    #[error("one-time code required")]
+    OtpRequired,
+    #[error("two factor authentication code required")]
+    TwoFactorRequired,
+    #[error("two factor backup code required")]
+    BackupCodeRequired,
+    /// `LoginTwoFactorAuthChooseMethod` without a TOTP secret to pick the authenticator app.
+    #[error("two factor method must be chosen")]
+    TwoFactorMethodRequired,
+    #[error("suspicious login must be confirmed")]
+    SuspiciousLogin,
+    /// `ArkoseLogin`: X wants a captcha solved.
+    #[error("captcha required")]
+    CaptchaRequired,
+    #[error("login denied")]
+    Denied,
+    #[error("challenge handler aborted the login")]
+    Aborted,
+    #[error("unhandled subtask {0}")]
+    UnhandledSubtask(String),
+}
+
+/// One entry of the `errors[]` array returned by the REST and GraphQL APIs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiErrorDetail {
+    #[serde(default)]
+    pub code: Option<i64>,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub kind: Option<String>,
+}
+
+/// Well-known failure categories derived from the X error codes and HTTP status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiErrorKind {
+    /// Codes 32, 89 and 239, or HTTP 401.
+    AuthExpired,
+    /// Codes 63 and 64.
+    Suspended,
+    /// Code 326.
+    Locked,
+    /// Codes 34, 50 and 144, or HTTP 404.
+    NotFound,
+    /// Code 187.
+    DuplicateStatus,
+    /// Code 226.
+    AutomationBlocked,
+    /// Code 88, or HTTP 429.
+    RateLimited,
+    Other,
+}
+
+/// An error response from X, keeping everything needed to handle it programmatically.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub errors: Vec<ApiErrorDetail>,
+    /// URL path of the request, without query.
+    pub endpoint: String,
+    /// GraphQL operation name, for requests to a GraphQL endpoint.
+    pub operation: Option<String>,
+    pub rate_limit: Option<RateLimitInfo>,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(default)]
+    errors: Vec<ApiErrorDetail>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, url: &str, headers: &HeaderMap, body: &str) -> Self {
+        let path = url::Url::parse(url)
+            .map(|url| url.path().to_string())
+            .unwrap_or_else(|_| url.split('?').next().unwrap_or_default().to_string());
+        let operation = path
+            .contains("/graphql/")
+            .then(|| path.rsplit('/').next().unwrap_or_default().to_string());
+        let errors = serde_json::from_str::<ErrorBody>(body)
+            .map(|body| body.errors)
+            .unwrap_or_default();
+
+        Self {
+            status,
+            errors,
+            endpoint: path,
+            operation,
+            rate_limit: RateLimitInfo::from_headers(headers),
+            body: body.to_string(),
+        }
+    }
+
+    /// Error codes reported in `errors[]`.
+    pub fn codes(&self) -> impl Iterator<Item = i64> + '_ {
+        self.errors.iter().filter_map(|error| error.code)
+    }
+
+    pub fn has_code(&self, code: i64) -> bool {
+        self.codes().any(|c| c == code)
+    }
+
+    pub fn kind(&self) -> ApiErrorKind {
+        for code in self.codes() {
+            match code {
+                32 | 89 | 239 => return ApiErrorKind::AuthExpired,
+                63 | 64 => return ApiErrorKind::Suspended,
+                326 => return ApiErrorKind::Locked,
+                34 | 50 | 144 => return ApiErrorKind::NotFound,
+                187 => return ApiErrorKind::DuplicateStatus,
+                226 => return ApiErrorKind::AutomationBlocked,
+                88 => return ApiErrorKind::RateLimited,
+                _ => {}
+            }
+        }
+
+        match self.status {
+            StatusCode::UNAUTHORIZED => ApiErrorKind::AuthExpired,
+            StatusCode::NOT_FOUND => ApiErrorKind::NotFound,
+            StatusCode::TOO_MANY_REQUESTS => ApiErrorKind::RateLimited,
+            _ => ApiErrorKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "API error: {} from {}",
+            self.status,
+            self.operation.as_deref().unwrap_or(&self.endpoint)
+        )?;
+        for error in &self.errors {
+            match error.code {
+                Some(code) => write!(f, "; {} ({})", error.message, code)?,
+                None => write!(f, "; {}", error.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<ApiError> for TwitterError {
+    fn from(error: ApiError) -> Self {
+        TwitterError::Http(Box::new(error))
+    }
+}
+
 pub type Result<T> = std::result::Result<T, TwitterError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_error_from_graphql_response() {
+        let body = r#"{"errors":[{"message":"Authorization: Status is a duplicate. (187)","code":187,"kind":"Permissions"}],"data":{}}"#;
+        let error = ApiError::new(
+            StatusCode::OK,
+            "https://x.com/i/api/graphql/abc/CreateTweet?variables=%7B%7D",
+            &HeaderMap::new(),
+            body,
+        );
+
+        assert_eq!(error.endpoint, "/i/api/graphql/abc/CreateTweet");
+        assert_eq!(error.operation.as_deref(), Some("CreateTweet"));
+        assert_eq!(error.errors[0].kind.as_deref(), Some("Permissions"));
+        assert_eq!(error.kind(), ApiErrorKind::DuplicateStatus);
+    }
+
+    #[test]
+    fn test_api_error_kind_falls_back_to_status() {
+        let error = ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "https://api.x.com/1.1/account/verify_credentials.json",
+            &HeaderMap::new(),
+            "Unauthorized",
+        );
+
+        assert!(error.errors.is_empty());
+        assert_eq!(error.operation, None);
+        assert_eq!(error.kind(), ApiErrorKind::AuthExpired);
+    }
+}
@@ -4,7 +4,11 @@ pub mod constants;
 pub mod error;
 pub mod explore;
 pub mod messages;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod models;
+pub mod official_api;
+pub mod pool;
 pub mod profile;
 pub mod relationships;
 pub mod scraper;
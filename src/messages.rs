@@ -141,15 +141,15 @@ pub async fn get_direct_message_conversations(
     let mut headers = HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
 
-    let message_list_url = "https://x.com/i/api/1.1/dm/inbox_initial_state.json";
+    let message_list_url = client.hosts.web_api_url("1.1/dm/inbox_initial_state.json");
     let url = if let Some(cursor_val) = cursor {
         format!("{}?cursor={}", message_list_url, cursor_val)
     } else {
-        message_list_url.to_string()
+        message_list_url
     };
 
     let (data, _) = crate::api::requests::request_api::<Value>(
-        &client.client,
+        client,
         &url,
         headers,
         Method::GET,
@@ -168,7 +168,7 @@ pub async fn send_direct_message(
     let mut headers = HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
 
-    let message_dm_url = "https://x.com/i/api/1.1/dm/new2.json";
+    let message_dm_url = client.hosts.web_api_url("1.1/dm/new2.json");
 
     let payload = json!({
         "conversation_id": conversation_id,
@@ -181,8 +181,8 @@ pub async fn send_direct_message(
     });
 
     let (response, _) = crate::api::requests::request_api::<Value>(
-        &client.client,
-        message_dm_url,
+        client,
+        &message_dm_url,
         headers,
         Method::POST,
         Some(payload),
@@ -429,3 +429,29 @@ fn parse_timeline_status(timeline: Option<&Value>) -> Option<TimelineStatus> {
             .map(String::from),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::MockServer;
+
+    #[tokio::test]
+    async fn test_direct_messages() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        let response = scraper
+            .get_direct_message_conversations("mockuser", None)
+            .await
+            .unwrap();
+        assert_eq!(response.conversations.len(), 1);
+
+        let conversation_id = response.conversations[0].conversation_id.clone();
+        scraper
+            .send_direct_message(&conversation_id, "hi")
+            .await
+            .unwrap();
+
+        let requests = server.requests_to("/i/api/1.1/dm/new2.json");
+        assert_eq!(requests[0].body_json().unwrap()["text"], "hi");
+    }
+}
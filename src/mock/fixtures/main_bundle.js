@@ -0,0 +1,7 @@
+"use strict";(self.webpackChunk_twitter_responsive_web=self.webpackChunk_twitter_responsive_web||[]).push([["main"],{
+12345:e=>{e.exports={queryId:"mockUserTweetsQueryId",operationName:"UserTweets",operationType:"query",metadata:{featureSwitches:["rweb_tipjar_consumption_enabled","responsive_web_graphql_exclude_directive_enabled","verified_phone_label_enabled","creator_subscriptions_tweet_preview_api_enabled","responsive_web_graphql_timeline_navigation_enabled","responsive_web_graphql_skip_user_profile_image_extensions_enabled","communities_web_enable_tweet_community_results_fetch","c9s_tweet_anatomy_moderator_badge_enabled","articles_preview_enabled","responsive_web_edit_tweet_api_enabled","graphql_is_translatable_rweb_tweet_is_translatable_enabled","view_counts_everywhere_api_enabled","longform_notetweets_consumption_enabled","responsive_web_twitter_article_tweet_consumption_enabled","tweet_awards_web_tipping_enabled","responsive_web_grok_share_attachment_enabled","creator_subscriptions_quote_tweet_preview_enabled","freedom_of_speech_not_reach_fetch_enabled","standardized_nudges_misinfo","tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled","rweb_video_timestamps_enabled","longform_notetweets_rich_text_read_enabled","longform_notetweets_inline_media_enabled","responsive_web_enhance_cards_enabled"],fieldToggles:["withArticlePlainText"]}}},
+23456:e=>{e.exports={queryId:"mockUserByScreenNameQueryId",operationName:"UserByScreenName",operationType:"query",metadata:{featureSwitches:["hidden_profile_likes_enabled","hidden_profile_subscriptions_enabled","responsive_web_graphql_exclude_directive_enabled","verified_phone_label_enabled","subscriptions_verification_info_is_identity_verified_enabled","subscriptions_verification_info_verified_since_enabled","highlights_tweets_tab_ui_enabled","creator_subscriptions_tweet_preview_api_enabled","responsive_web_graphql_skip_user_profile_image_extensions_enabled","responsive_web_graphql_timeline_navigation_enabled"],fieldToggles:["withAuxiliaryUserLabels"]}}},
+34567:e=>{e.exports={queryId:"lI07N6Otwv1PhnEgXILM7A",operationName:"FavoriteTweet",operationType:"mutation",metadata:{featureSwitches:[]}}},
+45678:e=>{e.exports={queryId:"QUjXply7fA7fk05FRyajEg",operationName:"BookmarksAll",operationType:"query",metadata:{featureSwitches:["graphql_timeline_v2_bookmark_timeline","rweb_tipjar_consumption_enabled"],fieldToggles:["withArticlePlainText"]}}},
+56789:(e,t,n)=>{"use strict";n.d(t,{Z:()=>a});const a=n(12345)}
+}]);
@@ -0,0 +1,708 @@
+//! In-process HTTP server that emulates the X endpoints used by this crate from recorded
+//! fixtures, so `Scraper` methods can be exercised end-to-end without network access.
+//!
+//! Available in this crate's own tests and to downstream crates through the `mock` feature.
+
+use crate::api::client::TwitterClient;
+use crate::api::hosts::Hosts;
+use crate::auth::user_auth::TwitterAuth;
+use crate::error::Result;
+use crate::scraper::Scraper;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const MOCK_AUTH_TOKEN: &str = "mock_auth_token";
+pub const MOCK_CT0: &str = "mock_ct0";
+pub const MOCK_GUEST_TOKEN: &str = "1700000000000000000";
+/// Access token issued by the mock OAuth 2.0 token endpoint.
+pub const MOCK_ACCESS_TOKEN: &str = "mock_access_token";
+pub const MOCK_MEDIA_ID: &str = "4000";
+/// Per-route request budget advertised in the `x-rate-limit-*` headers of fixture responses.
+pub const MOCK_RATE_LIMIT: u32 = 150;
+
+const GRAPHQL_FIXTURES: &[(&str, &str)] = &[
+    (
+        "UserByScreenName",
+        include_str!("fixtures/user_by_screen_name.json"),
+    ),
+    (
+        "UserByRestId",
+        include_str!("fixtures/user_by_rest_id.json"),
+    ),
+    ("UserTweets", include_str!("fixtures/user_tweets.json")),
+    (
+        "UserTweetsAndReplies",
+        include_str!("fixtures/user_tweets.json"),
+    ),
+    ("Likes", include_str!("fixtures/user_tweets.json")),
+    (
+        "ListLatestTweetsTimeline",
+        include_str!("fixtures/user_tweets.json"),
+    ),
+    (
+        "SearchTimeline",
+        include_str!("fixtures/search_timeline.json"),
+    ),
+    ("TweetDetail", include_str!("fixtures/tweet_detail.json")),
+    (
+        "TweetResultByRestId",
+        include_str!("fixtures/tweet_result_by_rest_id.json"),
+    ),
+    ("CreateTweet", include_str!("fixtures/create_tweet.json")),
+    (
+        "CreateNoteTweet",
+        include_str!("fixtures/create_note_tweet.json"),
+    ),
+    (
+        "FavoriteTweet",
+        include_str!("fixtures/favorite_tweet.json"),
+    ),
+    (
+        "CreateRetweet",
+        include_str!("fixtures/create_retweet.json"),
+    ),
+    ("Following", include_str!("fixtures/following.json")),
+    ("HomeTimeline", include_str!("fixtures/home_timeline.json")),
+    (
+        "GenericTimelineById",
+        include_str!("fixtures/generic_timeline_by_id.json"),
+    ),
+    ("ExplorePage", include_str!("fixtures/explore_page.json")),
+];
+
+const REST_FIXTURES: &[(&str, &str)] = &[
+    (
+        "/1.1/account/verify_credentials.json",
+        include_str!("fixtures/verify_credentials.json"),
+    ),
+    (
+        "/i/api/1.1/dm/inbox_initial_state.json",
+        include_str!("fixtures/dm_inbox_initial_state.json"),
+    ),
+    (
+        "/i/api/1.1/dm/new2.json",
+        include_str!("fixtures/dm_new.json"),
+    ),
+    (
+        "/1.1/friendships/create.json",
+        include_str!("fixtures/friendship.json"),
+    ),
+    (
+        "/1.1/friendships/destroy.json",
+        include_str!("fixtures/friendship.json"),
+    ),
+    (
+        "/i/api/1.1/account/sessions/list.json",
+        include_str!("fixtures/account_sessions.json"),
+    ),
+];
+
+const V2_USER: &str = include_str!("fixtures/v2_user.json");
+const V2_TWEET: &str = include_str!("fixtures/v2_tweet.json");
+const V2_USER_TWEETS: &str = include_str!("fixtures/v2_user_tweets.json");
+
+const LOGIN_FLOW: &str = include_str!("fixtures/login_flow.json");
+
+/// Path of the web client bundle linked from the mock x.com page.
+pub const MOCK_BUNDLE_PATH: &str = "/responsive-web/client-web/main.mock.js";
+const WEB_PAGE: &str = include_str!("fixtures/web_home.html");
+const MAIN_BUNDLE: &str = include_str!("fixtures/main_bundle.js");
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    pub fn text(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    /// GraphQL operation name for `/graphql/<query id>/<operation>` paths, otherwise the path.
+    pub fn route(&self) -> String {
+        route_key(&self.path)
+    }
+
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Parses a JSON-encoded query parameter such as `variables` or `features`.
+    pub fn query_json(&self, name: &str) -> Option<Value> {
+        self.query_param(name)
+            .and_then(|value| serde_json::from_str(value).ok())
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    pub fn body_json(&self) -> Option<Value> {
+        serde_json::from_slice(&self.body).ok()
+    }
+
+    pub fn body_text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    overrides: HashMap<String, MockResponse>,
+    queued: HashMap<String, VecDeque<MockResponse>>,
+    delays: HashMap<String, Duration>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// The server keeps running until the tokio runtime that started it shuts down, so a
+/// `Scraper` built from it stays usable even after the handle itself is dropped.
+#[derive(Clone)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockServer {
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let accept_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(stream, accept_state.clone()));
+            }
+        });
+
+        Ok(Self { addr, state })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn hosts(&self) -> Hosts {
+        Hosts::with_base_url(&self.url())
+    }
+
+    /// A scraper pointed at this server that has not logged in yet.
+    pub async fn guest_scraper(&self) -> Result<Scraper> {
+        Scraper::with_hosts(self.hosts()).await
+    }
+
+    /// A scraper pointed at this server with the mock session cookies already set.
+    pub async fn scraper(&self) -> Result<Scraper> {
+        let mut scraper = self.guest_scraper().await?;
+        scraper
+            .set_from_cookie_string(&format!("auth_token={}; ct0={}", MOCK_AUTH_TOKEN, MOCK_CT0))
+            .await?;
+        Ok(scraper)
+    }
+
+    /// A scraper pointed at this server that authenticates with `auth`, e.g. official API
+    /// credentials.
+    pub fn scraper_with_auth(&self, auth: impl TwitterAuth) -> Result<Scraper> {
+        let client = TwitterClient::new(Box::new(auth))?.with_hosts(self.hosts());
+        Ok(Scraper::from_client(client))
+    }
+
+    /// Replaces the fixture served for `route`, which is either a GraphQL operation name
+    /// (`"CreateTweet"`) or a request path (`"/1.1/onboarding/task.json"`).
+    pub fn mock(&self, route: &str, response: MockResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .overrides
+            .insert(route.to_string(), response);
+    }
+
+    /// Queues a response that is served once for `route` before falling back to
+    /// `mock` overrides or the bundled fixture.
+    pub fn mock_once(&self, route: &str, response: MockResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .queued
+            .entry(route.to_string())
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Holds every response for `route` back by `delay`, e.g. to keep a login running while
+    /// other requests are sent.
+    pub fn delay(&self, route: &str, delay: Duration) {
+        self.state
+            .lock()
+            .unwrap()
+            .delays
+            .insert(route.to_string(), delay);
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn requests_to(&self, route: &str) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.route() == route)
+            .collect()
+    }
+}
+
+fn route_key(path: &str) -> String {
+    match path.split_once("/graphql/") {
+        Some((_, operation_path)) => operation_path
+            .rsplit('/')
+            .next()
+            .unwrap_or(operation_path)
+            .to_string(),
+        None => path.to_string(),
+    }
+}
+
+async fn serve_connection(stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    while let Ok(Some(request)) = read_request(&mut reader).await {
+        let response = respond(&state, &request);
+        let delay = {
+            let mut state = state.lock().unwrap();
+            let delay = state.delays.get(&request.route()).copied();
+            state.requests.push(request);
+            delay
+        };
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        if writer.write_all(&encode_response(&response)).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn read_request<R>(reader: &mut R) -> std::io::Result<Option<RecordedRequest>>
+where
+    R: AsyncBufReadExt + Unpin,
+{
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).await? == 0 {
+        return Ok(None);
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or("/").to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let body = if headers
+        .get("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"))
+    {
+        read_chunked_body(reader).await?
+    } else {
+        let length = headers
+            .get("content-length")
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await?;
+        body
+    };
+
+    // Requests sent through a proxy name the full URL.
+    let target = match url::Url::parse(&target) {
+        Ok(url) => url[url::Position::BeforePath..].to_string(),
+        Err(_) => target,
+    };
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (
+            path.to_string(),
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect(),
+        ),
+        None => (target, Vec::new()),
+    };
+
+    Ok(Some(RecordedRequest {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    }))
+}
+
+async fn read_chunked_body<R>(reader: &mut R) -> std::io::Result<Vec<u8>>
+where
+    R: AsyncBufReadExt + Unpin,
+{
+    let mut body = Vec::new();
+    loop {
+        let mut size_line = String::new();
+        reader.read_line(&mut size_line).await?;
+        let size = usize::from_str_radix(size_line.trim(), 16).unwrap_or(0);
+
+        let mut chunk = vec![0; size + 2];
+        reader.read_exact(&mut chunk).await?;
+        if size == 0 {
+            return Ok(body);
+        }
+        body.extend_from_slice(&chunk[..size]);
+    }
+}
+
+fn encode_response(response: &MockResponse) -> Vec<u8> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\ncontent-length: {}\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response.body.len()
+    );
+    if !response
+        .headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+    {
+        head.push_str("content-type: application/json;charset=utf-8\r\n");
+    }
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    let mut bytes = head.into_bytes();
+    bytes.extend_from_slice(response.body.as_bytes());
+    bytes
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+fn respond(state: &Mutex<MockState>, request: &RecordedRequest) -> MockResponse {
+    let route = request.route();
+
+    {
+        let mut state = state.lock().unwrap();
+        if let Some(response) = state
+            .queued
+            .get_mut(&route)
+            .and_then(|queue| queue.pop_front())
+        {
+            return response;
+        }
+        if let Some(response) = state.overrides.get(&route) {
+            return response.clone();
+        }
+    }
+
+    if let Some((_, fixture)) = GRAPHQL_FIXTURES
+        .iter()
+        .find(|(operation, _)| *operation == route)
+    {
+        return with_rate_limit(state, &route, MockResponse::text(200, fixture));
+    }
+
+    if let Some((_, fixture)) = REST_FIXTURES.iter().find(|(path, _)| *path == route) {
+        return with_rate_limit(state, &route, MockResponse::text(200, fixture));
+    }
+
+    match route.as_str() {
+        "/1.1/guest/activate.json" => {
+            MockResponse::json(200, json!({ "guest_token": MOCK_GUEST_TOKEN }))
+        }
+        "/1.1/onboarding/task.json" => login_flow_response(request),
+        "/1.1/account/logout.json"
+        | "/i/api/1.1/account/sessions/revoke.json"
+        | "/i/api/1.1/account/sessions/revoke_all.json" => {
+            MockResponse::json(200, json!({ "status": "ok" }))
+        }
+        "/1.1/media/upload.json" => media_upload_response(request),
+        "/2/oauth2/token" => MockResponse::json(
+            200,
+            json!({
+                "token_type": "bearer",
+                "expires_in": 7200,
+                "access_token": MOCK_ACCESS_TOKEN,
+                "refresh_token": "mock_refresh_token",
+                "scope": "tweet.read tweet.write users.read like.write follows.write offline.access"
+            }),
+        ),
+        path if path.starts_with("/2/") => {
+            with_rate_limit(state, &route, official_api_response(request))
+        }
+        "/" => MockResponse::text(200, WEB_PAGE).with_header("content-type", "text/html"),
+        MOCK_BUNDLE_PATH => MockResponse::text(200, MAIN_BUNDLE)
+            .with_header("content-type", "application/javascript"),
+        _ => not_found(),
+    }
+}
+
+/// Adds rate-limit headers counting down from [`MOCK_RATE_LIMIT`] for each request
+/// served on `route`, resetting fifteen minutes from now.
+fn with_rate_limit(state: &Mutex<MockState>, route: &str, response: MockResponse) -> MockResponse {
+    let served = state
+        .lock()
+        .unwrap()
+        .requests
+        .iter()
+        .filter(|request| request.route() == route)
+        .count() as u32;
+    let reset = chrono::Utc::now().timestamp() + 15 * 60;
+
+    response
+        .with_header("x-rate-limit-limit", &MOCK_RATE_LIMIT.to_string())
+        .with_header(
+            "x-rate-limit-remaining",
+            &MOCK_RATE_LIMIT.saturating_sub(served + 1).to_string(),
+        )
+        .with_header("x-rate-limit-reset", &reset.to_string())
+}
+
+fn not_found() -> MockResponse {
+    MockResponse::json(
+        404,
+        json!({ "errors": [{ "message": "Sorry, that page does not exist", "code": 34 }] }),
+    )
+}
+
+fn login_flow_response(request: &RecordedRequest) -> MockResponse {
+    let flow: Value = serde_json::from_str(LOGIN_FLOW).expect("valid login flow fixture");
+
+    let step = if request.query_param("flow_name") == Some("login") {
+        "init".to_string()
+    } else {
+        request
+            .body_json()
+            .and_then(|body| {
+                body["subtask_inputs"][0]["subtask_id"]
+                    .as_str()
+                    .map(String::from)
+            })
+            .unwrap_or_else(|| "LoginSuccessSubtask".to_string())
+    };
+
+    let Some(next) = flow.get(&step) else {
+        return MockResponse::json(
+            400,
+            json!({ "errors": [{ "message": format!("Unexpected subtask {}", step), "code": 399 }] }),
+        );
+    };
+
+    let mut response = MockResponse::json(200, next.clone());
+    let completes_login = next["subtasks"].as_array().is_some_and(|subtasks| {
+        subtasks
+            .iter()
+            .any(|subtask| subtask["subtask_id"] == "LoginSuccessSubtask")
+    });
+    if completes_login {
+        response = response
+            .with_header(
+                "set-cookie",
+                &format!("auth_token={}; Path=/; Secure; HttpOnly", MOCK_AUTH_TOKEN),
+            )
+            .with_header("set-cookie", &format!("ct0={}; Path=/; Secure", MOCK_CT0));
+    }
+    response
+}
+
+/// The official v2 endpoints: users, tweets and the like, retweet and follow actions.
+fn official_api_response(request: &RecordedRequest) -> MockResponse {
+    let segments: Vec<&str> = request.path.trim_start_matches("/2/").split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["users", "me"] | ["users", "by", "username", _]) => {
+            MockResponse::text(200, V2_USER)
+        }
+        ("GET", ["users", _, "tweets"]) => MockResponse::text(200, V2_USER_TWEETS),
+        ("GET", ["tweets", _]) => MockResponse::text(200, V2_TWEET),
+        ("POST", ["tweets"]) => {
+            let text = request.body_json().map(|body| body["text"].clone());
+            MockResponse::json(
+                201,
+                json!({ "data": { "id": "1800000000000000003", "text": text } }),
+            )
+        }
+        ("POST", ["users", _, "likes"]) => {
+            MockResponse::json(200, json!({ "data": { "liked": true } }))
+        }
+        ("POST", ["users", _, "retweets"]) => {
+            MockResponse::json(200, json!({ "data": { "retweeted": true } }))
+        }
+        ("POST", ["users", _, "following"]) => MockResponse::json(
+            200,
+            json!({ "data": { "following": true, "pending_follow": false } }),
+        ),
+        ("DELETE", ["users", _, "following", _]) => {
+            MockResponse::json(200, json!({ "data": { "following": false } }))
+        }
+        _ => not_found(),
+    }
+}
+
+fn media_upload_response(request: &RecordedRequest) -> MockResponse {
+    let command = request
+        .query_param("command")
+        .map(String::from)
+        .or_else(|| {
+            request
+                .body_json()
+                .and_then(|body| body["command"].as_str().map(String::from))
+        })
+        .or_else(|| multipart_field(&request.body_text(), "command"));
+
+    match command.as_deref() {
+        Some("INIT") => MockResponse::json(
+            202,
+            json!({
+                "media_id": MOCK_MEDIA_ID.parse::<u64>().unwrap(),
+                "media_id_string": MOCK_MEDIA_ID,
+                "expires_after_secs": 86399
+            }),
+        ),
+        Some("APPEND") => MockResponse::json(200, json!({})),
+        Some("FINALIZE") => MockResponse::json(
+            201,
+            json!({
+                "media_id": MOCK_MEDIA_ID.parse::<u64>().unwrap(),
+                "media_id_string": MOCK_MEDIA_ID,
+                "size": 1024,
+                "expires_after_secs": 86400
+            }),
+        ),
+        Some("STATUS") => MockResponse::json(
+            200,
+            json!({
+                "media_id_string": MOCK_MEDIA_ID,
+                "processing_info": { "state": "succeeded", "progress_percent": 100 }
+            }),
+        ),
+        _ => MockResponse::json(
+            200,
+            json!({
+                "media_id": MOCK_MEDIA_ID.parse::<u64>().unwrap(),
+                "media_id_string": MOCK_MEDIA_ID,
+                "size": 1024,
+                "image": { "image_type": "image/jpeg", "w": 1, "h": 1 }
+            }),
+        ),
+    }
+}
+
+fn multipart_field(body: &str, name: &str) -> Option<String> {
+    let marker = format!("name=\"{}\"", name);
+    let start = body.find(&marker)? + marker.len();
+    let value_start = start + body[start..].find("\r\n\r\n")? + 4;
+    let value_end = value_start + body[value_start..].find("\r\n")?;
+    Some(body[value_start..value_end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_key() {
+        assert_eq!(
+            route_key("/i/api/graphql/G3KGOASz96M-Qu0nwmGXNg/UserByScreenName"),
+            "UserByScreenName"
+        );
+        assert_eq!(
+            route_key("/1.1/onboarding/task.json"),
+            "/1.1/onboarding/task.json"
+        );
+    }
+
+    #[test]
+    fn test_fixtures_are_valid_json() {
+        for (name, fixture) in GRAPHQL_FIXTURES.iter().chain(REST_FIXTURES) {
+            assert!(
+                serde_json::from_str::<Value>(fixture).is_ok(),
+                "invalid fixture for {}",
+                name
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queued_responses_are_served_once() {
+        let server = MockServer::start().await.unwrap();
+        server.mock_once("UserByScreenName", MockResponse::json(403, json!({})));
+        let scraper = server.scraper().await.unwrap();
+
+        assert!(scraper.get_profile("mockuser").await.is_err());
+        let profile = scraper.get_profile("mockuser").await.unwrap();
+        assert_eq!(profile.username, "mockuser");
+        assert_eq!(server.requests_to("UserByScreenName").len(), 2);
+    }
+}
@@ -2,3 +2,5 @@ pub mod tweets;
 pub use tweets::*;
 pub mod profile;
 pub use profile::Profile;
+pub mod session;
+pub use session::AccountSession;
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// A device or app logged in to the account, as listed under "Sessions" in the settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountSession {
+    /// Identifies the session when revoking it.
+    pub hashed_token: String,
+    #[serde(default)]
+    pub client_application_name: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub last_active_at: Option<String>,
+    /// Whether this is the session making the request.
+    #[serde(default)]
+    pub is_current: bool,
+}
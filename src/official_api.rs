@@ -0,0 +1,490 @@
+//! The official X API v2, used in place of the web client's GraphQL API when the scraper
+//! authenticates with [`OAuth1Auth`](crate::auth::oauth1::OAuth1Auth) or
+//! [`OAuth2Auth`](crate::auth::oauth2::OAuth2Auth).
+
+use crate::api::client::TwitterClient;
+use crate::api::requests::request_api;
+use crate::error::Result;
+use crate::models::{Mention, Profile, Tweet};
+use crate::timeline::v2::QueryTweetsResponse;
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const USER_FIELDS: &str = "created_at,description,location,pinned_tweet_id,profile_image_url,protected,public_metrics,url,verified";
+const TWEET_FIELDS: &str =
+    "author_id,conversation_id,created_at,entities,public_metrics,referenced_tweets";
+
+#[derive(Debug, Deserialize)]
+struct Response<T> {
+    data: T,
+    #[serde(default)]
+    includes: Includes,
+}
+
+/// A page of tweets; `data` is missing when there are none.
+#[derive(Debug, Deserialize)]
+struct TweetsPage {
+    #[serde(default)]
+    data: Vec<V2Tweet>,
+    #[serde(default)]
+    includes: Includes,
+    #[serde(default)]
+    meta: Meta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Includes {
+    #[serde(default)]
+    users: Vec<User>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Meta {
+    next_token: Option<String>,
+    previous_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: String,
+    username: String,
+    name: String,
+    description: Option<String>,
+    location: Option<String>,
+    url: Option<String>,
+    #[serde(default)]
+    protected: bool,
+    #[serde(default)]
+    verified: bool,
+    created_at: Option<DateTime<Utc>>,
+    profile_image_url: Option<String>,
+    pinned_tweet_id: Option<String>,
+    #[serde(default)]
+    public_metrics: UserMetrics,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct UserMetrics {
+    followers_count: i32,
+    following_count: i32,
+    tweet_count: i32,
+    listed_count: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct V2Tweet {
+    id: String,
+    text: String,
+    author_id: Option<String>,
+    conversation_id: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+    public_metrics: Option<TweetMetrics>,
+    #[serde(default)]
+    referenced_tweets: Vec<ReferencedTweet>,
+    #[serde(default)]
+    entities: Entities,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TweetMetrics {
+    retweet_count: i32,
+    reply_count: i32,
+    like_count: i32,
+    quote_count: i32,
+    bookmark_count: Option<i32>,
+    impression_count: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReferencedTweet {
+    #[serde(rename = "type")]
+    kind: String,
+    id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Entities {
+    hashtags: Vec<Hashtag>,
+    mentions: Vec<EntityMention>,
+    urls: Vec<EntityUrl>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Hashtag {
+    tag: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EntityMention {
+    id: Option<String>,
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EntityUrl {
+    expanded_url: Option<String>,
+}
+
+impl From<User> for Profile {
+    fn from(user: User) -> Self {
+        Profile {
+            id: user.id,
+            username: user.username,
+            name: user.name,
+            description: user.description,
+            location: user.location,
+            url: user.url.filter(|url| !url.is_empty()),
+            protected: user.protected,
+            verified: user.verified,
+            followers_count: user.public_metrics.followers_count,
+            following_count: user.public_metrics.following_count,
+            tweets_count: user.public_metrics.tweet_count,
+            listed_count: user.public_metrics.listed_count,
+            created_at: user.created_at.unwrap_or_else(Utc::now),
+            profile_image_url: user.profile_image_url.map(|url| url.replace("_normal", "")),
+            profile_banner_url: None,
+            pinned_tweet_id: user.pinned_tweet_id,
+            is_blue_verified: None,
+        }
+    }
+}
+
+fn parse_tweet(tweet: V2Tweet, users: &[User]) -> Tweet {
+    let author = users
+        .iter()
+        .find(|user| Some(&user.id) == tweet.author_id.as_ref());
+    let referenced = |kind: &str| {
+        tweet
+            .referenced_tweets
+            .iter()
+            .find(|referenced| referenced.kind == kind)
+            .map(|referenced| referenced.id.clone())
+    };
+    let in_reply_to_status_id = referenced("replied_to");
+    let quoted_status_id = referenced("quoted");
+    let retweeted_status_id = referenced("retweeted");
+    let metrics = tweet.public_metrics.unwrap_or_default();
+    let username = author.map(|user| user.username.clone());
+
+    Tweet {
+        ext_views: None,
+        created_at: tweet.created_at.map(|time| time.to_rfc3339()),
+        bookmark_count: metrics.bookmark_count,
+        conversation_id: tweet.conversation_id,
+        hashtags: tweet
+            .entities
+            .hashtags
+            .into_iter()
+            .map(|tag| tag.tag)
+            .collect(),
+        html: None,
+        id: Some(tweet.id.clone()),
+        in_reply_to_status: None,
+        is_quoted: Some(quoted_status_id.is_some()),
+        is_pin: None,
+        is_reply: Some(in_reply_to_status_id.is_some()),
+        is_retweet: Some(retweeted_status_id.is_some()),
+        is_self_thread: None,
+        in_reply_to_status_id,
+        likes: Some(metrics.like_count),
+        name: author.map(|user| user.name.clone()),
+        mentions: tweet
+            .entities
+            .mentions
+            .into_iter()
+            .map(|mention| Mention {
+                id: mention.id.unwrap_or_default(),
+                username: Some(mention.username),
+                name: None,
+            })
+            .collect(),
+        permanent_url: username
+            .as_ref()
+            .map(|username| format!("https://twitter.com/{}/status/{}", username, tweet.id)),
+        photos: Vec::new(),
+        place: None,
+        quoted_status: None,
+        quoted_status_id,
+        replies: Some(metrics.reply_count),
+        retweets: Some(metrics.retweet_count),
+        retweeted_status: None,
+        retweeted_status_id,
+        text: Some(tweet.text),
+        thread: Vec::new(),
+        time_parsed: tweet.created_at,
+        timestamp: tweet.created_at.map(|time| time.timestamp()),
+        urls: tweet
+            .entities
+            .urls
+            .into_iter()
+            .filter_map(|url| url.expanded_url)
+            .collect(),
+        user_id: tweet.author_id,
+        username,
+        videos: Vec::new(),
+        views: metrics.impression_count,
+        sensitive_content: None,
+        poll: None,
+        quote_count: Some(metrics.quote_count),
+        reply_count: Some(metrics.reply_count),
+        retweet_count: Some(metrics.retweet_count),
+        screen_name: None,
+        thread_id: None,
+    }
+}
+
+async fn request<T: DeserializeOwned>(
+    client: &TwitterClient,
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+) -> Result<T> {
+    let mut headers = HeaderMap::new();
+    client.auth.install_headers(&mut headers).await?;
+
+    let url = client.hosts.api_url(path);
+    let (response, _) = request_api(client, &url, headers, method, body).await?;
+    Ok(response)
+}
+
+fn tweet_query() -> String {
+    format!(
+        "tweet.fields={}&expansions=author_id&user.fields=name,username",
+        TWEET_FIELDS
+    )
+}
+
+pub async fn get_profile(client: &TwitterClient, username: &str) -> Result<Profile> {
+    let path = format!(
+        "2/users/by/username/{}?user.fields={}",
+        urlencoding::encode(username),
+        USER_FIELDS
+    );
+    let response: Response<User> = request(client, Method::GET, &path, None).await?;
+    Ok(response.data.into())
+}
+
+/// The authenticated user.
+pub async fn me(client: &TwitterClient) -> Result<Profile> {
+    let path = format!("2/users/me?user.fields={}", USER_FIELDS);
+    let response: Response<User> = request(client, Method::GET, &path, None).await?;
+    Ok(response.data.into())
+}
+
+pub async fn get_tweet(client: &TwitterClient, id: &str) -> Result<Tweet> {
+    let path = format!("2/tweets/{}?{}", urlencoding::encode(id), tweet_query());
+    let response: Response<V2Tweet> = request(client, Method::GET, &path, None).await?;
+    Ok(parse_tweet(response.data, &response.includes.users))
+}
+
+/// `count` is clamped to the 5 to 100 tweets a page of this endpoint can hold.
+pub async fn get_user_tweets(
+    client: &TwitterClient,
+    user_id: &str,
+    count: i32,
+    cursor: Option<&str>,
+) -> Result<QueryTweetsResponse> {
+    let mut path = format!(
+        "2/users/{}/tweets?max_results={}&{}",
+        urlencoding::encode(user_id),
+        count.clamp(5, 100),
+        tweet_query()
+    );
+    if let Some(cursor) = cursor {
+        path.push_str(&format!(
+            "&pagination_token={}",
+            urlencoding::encode(cursor)
+        ));
+    }
+
+    let page: TweetsPage = request(client, Method::GET, &path, None).await?;
+    let users = page.includes.users;
+    Ok(QueryTweetsResponse {
+        tweets: page
+            .data
+            .into_iter()
+            .map(|tweet| parse_tweet(tweet, &users))
+            .collect(),
+        next: page.meta.next_token,
+        previous: page.meta.previous_token,
+    })
+}
+
+/// Posts a tweet and returns X's response, `{"data": {"id", "text"}}`.
+pub async fn send_tweet(
+    client: &TwitterClient,
+    text: &str,
+    reply_to: Option<&str>,
+    media_data: Option<Vec<(Vec<u8>, String)>>,
+) -> Result<Value> {
+    let mut body = json!({ "text": text });
+    if let Some(reply_to) = reply_to {
+        body["reply"] = json!({ "in_reply_to_tweet_id": reply_to });
+    }
+    if let Some(media_files) = media_data {
+        let mut media_ids = Vec::new();
+        for (file_data, media_type) in media_files {
+            media_ids.push(crate::tweets::upload_media(client, file_data, &media_type).await?);
+        }
+        body["media"] = json!({ "media_ids": media_ids });
+    }
+
+    request(client, Method::POST, "2/tweets", Some(body)).await
+}
+
+pub async fn like_tweet(client: &TwitterClient, tweet_id: &str) -> Result<Value> {
+    let user_id = me(client).await?.id;
+    let path = format!("2/users/{}/likes", user_id);
+    request(
+        client,
+        Method::POST,
+        &path,
+        Some(json!({ "tweet_id": tweet_id })),
+    )
+    .await
+}
+
+pub async fn retweet(client: &TwitterClient, tweet_id: &str) -> Result<Value> {
+    let user_id = me(client).await?.id;
+    let path = format!("2/users/{}/retweets", user_id);
+    request(
+        client,
+        Method::POST,
+        &path,
+        Some(json!({ "tweet_id": tweet_id })),
+    )
+    .await
+}
+
+pub async fn follow_user(client: &TwitterClient, username: &str) -> Result<()> {
+    let user_id = me(client).await?.id;
+    let target = get_profile(client, username).await?.id;
+    let path = format!("2/users/{}/following", user_id);
+    let _: Value = request(
+        client,
+        Method::POST,
+        &path,
+        Some(json!({ "target_user_id": target })),
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn unfollow_user(client: &TwitterClient, username: &str) -> Result<()> {
+    let user_id = me(client).await?.id;
+    let target = get_profile(client, username).await?.id;
+    let path = format!("2/users/{}/following/{}", user_id, target);
+    let _: Value = request(client, Method::DELETE, &path, None).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::oauth1::OAuth1Auth;
+    use crate::auth::oauth2::{OAuth2Auth, OAuth2Token};
+    use crate::error::TwitterError;
+    use crate::mock::{MockResponse, MockServer, MOCK_ACCESS_TOKEN};
+    use crate::search::SearchMode;
+    use chrono::{Datelike, Utc};
+    use serde_json::json;
+
+    fn oauth1() -> OAuth1Auth {
+        OAuth1Auth::new("consumer", "consumer-secret", "1000-token", "token-secret")
+    }
+
+    #[tokio::test]
+    async fn test_oauth1_uses_signed_v2_requests() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper_with_auth(oauth1()).unwrap();
+        assert!(scraper.is_official_api());
+
+        let profile = scraper.get_profile("mockuser").await.unwrap();
+        assert_eq!(profile.id, "1000");
+        assert_eq!(profile.followers_count, 1500);
+        assert_eq!(profile.created_at.year(), 2006);
+        let request = &server.requests_to("/2/users/by/username/mockuser")[0];
+        let authorization = request.header("authorization").unwrap();
+        assert!(authorization.starts_with("OAuth oauth_consumer_key=\"consumer\""));
+        assert!(authorization.contains("oauth_signature="));
+        assert!(request.header("cookie").is_none());
+
+        let tweet = scraper.get_tweet("1800000000000000001").await.unwrap();
+        assert_eq!(tweet.username.as_deref(), Some("mockuser"));
+        assert_eq!(tweet.likes, Some(10));
+        assert_eq!(tweet.hashtags, vec!["mock"]);
+        assert_eq!(tweet.mentions[0].username.as_deref(), Some("rustlang"));
+
+        let page = scraper.get_user_tweets("1000", 20, None).await.unwrap();
+        assert_eq!(page.tweets.len(), 2);
+        assert_eq!(page.tweets[0].is_reply, Some(true));
+        assert!(page.next.is_some());
+
+        let response = scraper.send_tweet("hello", Some("1"), None).await.unwrap();
+        assert_eq!(response["data"]["text"], "hello");
+        let body = server.requests_to("/2/tweets")[0].body_json().unwrap();
+        assert_eq!(body["reply"]["in_reply_to_tweet_id"], "1");
+
+        scraper.follow_user("mockuser").await.unwrap();
+        let follow = &server.requests_to("/2/users/1000/following")[0];
+        assert_eq!(follow.body_json().unwrap()["target_user_id"], "1000");
+        scraper.like_tweet("1800000000000000001").await.unwrap();
+        assert_eq!(server.requests_to("/2/users/1000/likes").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_web_client_operations_are_unsupported() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper_with_auth(oauth1()).unwrap();
+
+        let result = scraper
+            .search_tweets("rust", 20, SearchMode::Latest, None)
+            .await;
+        assert!(matches!(result, Err(TwitterError::Unsupported(_))));
+        assert!(server.requests_to("SearchTimeline").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_oauth2_refreshes_expiring_and_rejected_tokens() {
+        let server = MockServer::start().await.unwrap();
+        let auth = OAuth2Auth::new(
+            "client",
+            OAuth2Token {
+                access_token: "expired".into(),
+                refresh_token: Some("refresh".into()),
+                expires_at: Some(Utc::now()),
+                scope: None,
+            },
+        );
+        let scraper = server.scraper_with_auth(auth.clone()).unwrap();
+
+        scraper.me().await.unwrap();
+        let token_requests = server.requests_to("/2/oauth2/token");
+        assert_eq!(token_requests.len(), 1);
+        assert!(token_requests[0]
+            .body_text()
+            .contains("grant_type=refresh_token"));
+        assert_eq!(
+            server.requests_to("/2/users/me")[0].header("authorization"),
+            Some(format!("Bearer {}", MOCK_ACCESS_TOKEN).as_str())
+        );
+        assert_eq!(
+            auth.token().refresh_token.as_deref(),
+            Some("mock_refresh_token")
+        );
+
+        server.mock_once(
+            "/2/users/me",
+            MockResponse::json(401, json!({"title": "Unauthorized", "status": 401})),
+        );
+        scraper.me().await.unwrap();
+        assert_eq!(server.requests_to("/2/oauth2/token").len(), 2);
+        assert_eq!(server.requests_to("/2/users/me").len(), 3);
+    }
+}
@@ -0,0 +1,499 @@
+//! Spreading read requests over many accounts.
+
+use crate::api::client::TwitterClient;
+use crate::error::{ApiErrorKind, Result, TwitterError};
+use crate::explore::ExploreTimeline;
+use crate::models::{Profile, Tweet};
+use crate::scraper::Scraper;
+use crate::search::SearchMode;
+use crate::timeline::v1::{QueryProfilesResponse, QueryTweetsResponse};
+use crate::timeline::v2::QueryTweetsResponse as V2QueryTweetsResponse;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long an account rests after a 429 or an automation warning (code 226), unless the
+/// response says when its budget resets.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatus {
+    Available,
+    /// Rate-limited or flagged as automated; skipped until `until`.
+    CoolingDown {
+        until: DateTime<Utc>,
+    },
+    /// The session expired or was rejected.
+    Expired,
+    Locked,
+    Suspended,
+}
+
+impl AccountStatus {
+    pub fn is_available(&self) -> bool {
+        match self {
+            AccountStatus::Available => true,
+            AccountStatus::CoolingDown { until } => *until <= Utc::now(),
+            _ => false,
+        }
+    }
+}
+
+struct PoolAccount {
+    name: String,
+    scraper: Scraper,
+    status: Mutex<AccountStatus>,
+}
+
+impl PoolAccount {
+    /// The account's status, also taking the session's cookie expiry into account.
+    async fn status(&self) -> AccountStatus {
+        let status = *self.status.lock().unwrap();
+        if !status.is_available() {
+            return status;
+        }
+        match self.scraper.session_status().await {
+            Ok(session) if !session.is_usable() => AccountStatus::Expired,
+            _ => AccountStatus::Available,
+        }
+    }
+
+    fn set_status(&self, status: AccountStatus) {
+        tracing::info!(account = %self.name, ?status, "Account taken out of rotation");
+        *self.status.lock().unwrap() = status;
+    }
+}
+
+/// Authenticated accounts that read requests are spread over.
+///
+/// Each request goes to the available account with the most budget left for its endpoint,
+/// rotating between accounts with equal budgets. An account that is rate-limited or flagged
+/// as automated cools down; one whose session expired or that is locked or suspended is
+/// skipped until [`AccountPool::restore`]. Either way the request is retried on another
+/// account.
+pub struct AccountPool {
+    accounts: Vec<PoolAccount>,
+    cooldown: Duration,
+    next: AtomicUsize,
+}
+
+impl Default for AccountPool {
+    fn default() -> Self {
+        Self {
+            accounts: Vec::new(),
+            cooldown: DEFAULT_COOLDOWN,
+            next: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl AccountPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Adds a logged-in scraper under `name`, which identifies the account in logs and
+    /// [`AccountPool::statuses`].
+    pub fn add_account(&mut self, name: impl Into<String>, scraper: Scraper) {
+        self.accounts.push(PoolAccount {
+            name: name.into(),
+            scraper,
+            status: Mutex::new(AccountStatus::Available),
+        });
+    }
+
+    pub fn add_client(&mut self, name: impl Into<String>, client: TwitterClient) {
+        self.add_account(name, Scraper::from_client(client));
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// The scraper of account `name`, e.g. to log it in again.
+    pub fn account(&self, name: &str) -> Option<&Scraper> {
+        self.find(name).map(|account| &account.scraper)
+    }
+
+    pub async fn statuses(&self) -> Vec<(String, AccountStatus)> {
+        let mut statuses = Vec::with_capacity(self.accounts.len());
+        for account in &self.accounts {
+            statuses.push((account.name.clone(), account.status().await));
+        }
+        statuses
+    }
+
+    /// Puts account `name` back into rotation, e.g. after logging it in again.
+    pub fn restore(&self, name: &str) {
+        if let Some(account) = self.find(name) {
+            *account.status.lock().unwrap() = AccountStatus::Available;
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<&PoolAccount> {
+        self.accounts.iter().find(|account| account.name == name)
+    }
+
+    /// Picks the available account, other than those in `tried`, with the most budget left
+    /// for `endpoint`. Accounts whose budget is exhausted are skipped until it resets.
+    async fn pick(&self, endpoint: &str, tried: &[usize]) -> Result<usize> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut best: Option<(usize, u32)> = None;
+        let mut next_reset: Option<DateTime<Utc>> = None;
+        let mut resting = |reset_at: DateTime<Utc>| {
+            next_reset = Some(next_reset.map_or(reset_at, |current| current.min(reset_at)));
+        };
+
+        for offset in 0..self.accounts.len() {
+            let index = (start + offset) % self.accounts.len();
+            if tried.contains(&index) {
+                continue;
+            }
+            let account = &self.accounts[index];
+            match account.status().await {
+                AccountStatus::Available => {}
+                AccountStatus::CoolingDown { until } => {
+                    resting(until);
+                    continue;
+                }
+                _ => continue,
+            }
+
+            let budget = account.scraper.rate_limit(endpoint);
+            if let Some(info) = budget.filter(|info| info.is_exhausted()) {
+                resting(info.reset_at);
+                continue;
+            }
+            let remaining = budget
+                .filter(|info| info.reset_at > Utc::now())
+                .map_or(u32::MAX, |info| info.remaining);
+            if best.is_none_or(|(_, most)| remaining > most) {
+                best = Some((index, remaining));
+            }
+        }
+
+        match (best, next_reset) {
+            (Some((index, _)), _) => Ok(index),
+            (None, Some(reset_at)) => Err(TwitterError::RateLimit {
+                reset_at: Some(reset_at),
+                endpoint: endpoint.to_string(),
+            }),
+            (None, None) => Err(TwitterError::Auth("No usable account in the pool".into())),
+        }
+    }
+
+    /// The status an account gets after `error`, or `None` if the error has nothing to do
+    /// with the account and is returned as is.
+    fn status_after(&self, error: &TwitterError) -> Option<AccountStatus> {
+        let cooldown =
+            || Utc::now() + chrono::Duration::from_std(self.cooldown).unwrap_or_default();
+        match error {
+            TwitterError::RateLimit { reset_at, .. } => Some(AccountStatus::CoolingDown {
+                until: reset_at
+                    .filter(|reset_at| *reset_at > Utc::now())
+                    .unwrap_or_else(cooldown),
+            }),
+            TwitterError::Http(error) => match error.kind() {
+                ApiErrorKind::AutomationBlocked | ApiErrorKind::RateLimited => {
+                    Some(AccountStatus::CoolingDown { until: cooldown() })
+                }
+                ApiErrorKind::AuthExpired => Some(AccountStatus::Expired),
+                ApiErrorKind::Locked => Some(AccountStatus::Locked),
+                ApiErrorKind::Suspended => Some(AccountStatus::Suspended),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Runs `request` on the best account for `endpoint`, moving on to the next one when
+    /// the account is rate-limited, flagged or no longer usable.
+    async fn run<'a, T, F, Fut>(&'a self, endpoint: &str, request: F) -> Result<T>
+    where
+        F: Fn(&'a Scraper) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut tried = Vec::new();
+        loop {
+            let index = self.pick(endpoint, &tried).await?;
+            tried.push(index);
+            let account = &self.accounts[index];
+            tracing::debug!(account = %account.name, %endpoint, "Sending request from pool");
+
+            let error = match request(&account.scraper).await {
+                Err(error) => error,
+                result => return result,
+            };
+            match self.status_after(&error) {
+                Some(status) => account.set_status(status),
+                None => return Err(error),
+            }
+            if tried.len() == self.accounts.len() {
+                return Err(error);
+            }
+        }
+    }
+
+    pub async fn get_profile(&self, username: &str) -> Result<Profile> {
+        self.run("UserByScreenName", |scraper| scraper.get_profile(username))
+            .await
+    }
+
+    pub async fn get_tweet(&self, id: &str) -> Result<Tweet> {
+        self.run("TweetDetail", |scraper| scraper.get_tweet(id))
+            .await
+    }
+
+    pub async fn get_tweet_by_rest_id(&self, id: &str) -> Result<Tweet> {
+        self.run("TweetResultByRestId", |scraper| {
+            scraper.get_tweet_by_rest_id(id)
+        })
+        .await
+    }
+
+    pub async fn get_user_tweets(
+        &self,
+        user_id: &str,
+        count: i32,
+        cursor: Option<String>,
+    ) -> Result<V2QueryTweetsResponse> {
+        self.run("UserTweets", |scraper| {
+            scraper.get_user_tweets(user_id, count, cursor.clone())
+        })
+        .await
+    }
+
+    pub async fn fetch_tweets_and_replies(
+        &self,
+        username: &str,
+        max_tweets: i32,
+        cursor: Option<&str>,
+    ) -> Result<V2QueryTweetsResponse> {
+        self.run("UserTweetsAndReplies", |scraper| {
+            scraper.fetch_tweets_and_replies(username, max_tweets, cursor)
+        })
+        .await
+    }
+
+    pub async fn fetch_tweets_and_replies_by_user_id(
+        &self,
+        user_id: &str,
+        max_tweets: i32,
+        cursor: Option<&str>,
+    ) -> Result<V2QueryTweetsResponse> {
+        self.run("UserTweetsAndReplies", |scraper| {
+            scraper.fetch_tweets_and_replies_by_user_id(user_id, max_tweets, cursor)
+        })
+        .await
+    }
+
+    pub async fn fetch_list_tweets(
+        &self,
+        list_id: &str,
+        max_tweets: i32,
+        cursor: Option<&str>,
+    ) -> Result<Value> {
+        self.run("ListLatestTweetsTimeline", |scraper| {
+            scraper.fetch_list_tweets(list_id, max_tweets, cursor)
+        })
+        .await
+    }
+
+    pub async fn search_tweets(
+        &self,
+        query: &str,
+        max_tweets: i32,
+        search_mode: SearchMode,
+        cursor: Option<String>,
+    ) -> Result<QueryTweetsResponse> {
+        self.run("SearchTimeline", |scraper| {
+            scraper.search_tweets(query, max_tweets, search_mode, cursor.clone())
+        })
+        .await
+    }
+
+    pub async fn search_profiles(
+        &self,
+        query: &str,
+        max_profiles: i32,
+        cursor: Option<String>,
+    ) -> Result<QueryProfilesResponse> {
+        self.run("SearchTimeline", |scraper| {
+            scraper.search_profiles(query, max_profiles, cursor.clone())
+        })
+        .await
+    }
+
+    pub async fn get_followers(
+        &self,
+        user_id: &str,
+        count: i32,
+        cursor: Option<String>,
+    ) -> Result<(Vec<Profile>, Option<String>)> {
+        self.run("Following", |scraper| {
+            scraper.get_followers(user_id, count, cursor.clone())
+        })
+        .await
+    }
+
+    pub async fn get_following(
+        &self,
+        user_id: &str,
+        count: i32,
+        cursor: Option<String>,
+    ) -> Result<(Vec<Profile>, Option<String>)> {
+        self.run("Following", |scraper| {
+            scraper.get_following(user_id, count, cursor.clone())
+        })
+        .await
+    }
+
+    pub async fn get_explore_timelines(&self) -> Result<Vec<ExploreTimeline>> {
+        self.run("ExplorePage", |scraper| scraper.get_explore_timelines())
+            .await
+    }
+
+    pub async fn get_trends(&self, timeline_id: &str, count: i16) -> Result<Vec<String>> {
+        self.run("GenericTimelineById", |scraper| {
+            scraper.get_trends(timeline_id, count)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockResponse, MockServer};
+    use serde_json::json;
+
+    async fn pool(server: &MockServer) -> AccountPool {
+        let mut pool = AccountPool::new();
+        for name in ["a", "b"] {
+            let mut scraper = server.guest_scraper().await.unwrap();
+            scraper
+                .set_from_cookie_string(&format!("auth_token={}; ct0={}", name, name))
+                .await
+                .unwrap();
+            pool.add_account(name, scraper);
+        }
+        pool
+    }
+
+    /// Accounts that sent the requests to `route`, in order.
+    fn senders(server: &MockServer, route: &str) -> Vec<String> {
+        server
+            .requests_to(route)
+            .iter()
+            .map(|request| {
+                let cookie = request.header("cookie").unwrap_or_default();
+                cookie
+                    .split("; ")
+                    .find_map(|cookie| cookie.strip_prefix("auth_token="))
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_to_account_with_most_budget() {
+        let server = MockServer::start().await.unwrap();
+        let pool = pool(&server).await;
+
+        for _ in 0..4 {
+            pool.get_profile("mockuser").await.unwrap();
+        }
+
+        assert_eq!(senders(&server, "UserByScreenName"), ["a", "b", "a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_account_cools_down() {
+        let server = MockServer::start().await.unwrap();
+        let pool = pool(&server).await;
+
+        server.mock_once(
+            "UserByScreenName",
+            MockResponse::json(429, json!({"errors": [{"code": 88}]})),
+        );
+        pool.get_profile("mockuser").await.unwrap();
+        pool.get_profile("mockuser").await.unwrap();
+
+        assert_eq!(senders(&server, "UserByScreenName"), ["a", "b", "b"]);
+        let statuses = pool.statuses().await;
+        assert!(matches!(statuses[0].1, AccountStatus::CoolingDown { .. }));
+        assert_eq!(statuses[1].1, AccountStatus::Available);
+    }
+
+    #[tokio::test]
+    async fn test_locked_account_is_skipped_until_restored() {
+        let server = MockServer::start().await.unwrap();
+        let pool = pool(&server).await;
+
+        server.mock_once(
+            "UserByScreenName",
+            MockResponse::json(403, json!({"errors": [{"code": 326}]})),
+        );
+        pool.get_profile("mockuser").await.unwrap();
+        pool.get_profile("mockuser").await.unwrap();
+        assert_eq!(senders(&server, "UserByScreenName"), ["a", "b", "b"]);
+        assert_eq!(pool.statuses().await[0].1, AccountStatus::Locked);
+
+        pool.restore("a");
+        assert_eq!(pool.statuses().await[0].1, AccountStatus::Available);
+    }
+
+    #[tokio::test]
+    async fn test_pool_reports_when_every_account_rests() {
+        let server = MockServer::start().await.unwrap();
+        let pool = pool(&server).await;
+        let automated = MockResponse::json(403, json!({"errors": [{"code": 226}]}));
+
+        server.mock("UserByScreenName", automated);
+        let error = pool.get_profile("mockuser").await.unwrap_err();
+        assert_eq!(
+            error.api_error().unwrap().kind(),
+            ApiErrorKind::AutomationBlocked
+        );
+
+        match pool.get_profile("mockuser").await {
+            Err(TwitterError::RateLimit { reset_at, .. }) => assert!(reset_at.is_some()),
+            other => panic!("expected rate limit error, got {:?}", other),
+        }
+        assert_eq!(server.requests_to("UserByScreenName").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried() {
+        let server = MockServer::start().await.unwrap();
+        let pool = pool(&server).await;
+
+        server.mock_once(
+            "UserByScreenName",
+            MockResponse::json(
+                200,
+                json!({"errors": [{"code": 50, "message": "User not found."}]}),
+            ),
+        );
+        assert!(pool.get_profile("nobody").await.is_err());
+        assert_eq!(server.requests_to("UserByScreenName").len(), 1);
+        assert!(pool
+            .statuses()
+            .await
+            .iter()
+            .all(|(_, status)| *status == AccountStatus::Available));
+    }
+}
@@ -1,11 +1,10 @@
 use crate::api::client::TwitterClient;
-use crate::api::requests::request_api;
+use crate::api::requests::{request_api, request_graphql};
 use crate::error::{Result, TwitterError};
 use crate::models::Profile;
 use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use reqwest::header::HeaderMap;
-use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
@@ -116,13 +115,16 @@ pub fn parse_profile(user: &LegacyUserRaw, is_blue_verified: Option<bool>) -> Pr
     };
 
     // Set website URL from entities using functional chaining
-    user.entities
+    if let Some(expanded_url) = user
+        .entities
         .as_ref()
         .and_then(|entities| entities.url.as_ref())
         .and_then(|url_entity| url_entity.urls.as_ref())
         .and_then(|urls| urls.first())
         .and_then(|first_url| first_url.expanded_url.as_ref())
-        .map(|expanded_url| profile.url = Some(expanded_url.clone()));
+    {
+        profile.url = Some(expanded_url.clone());
+    }
 
     profile
 }
@@ -134,6 +136,7 @@ pub struct UserResults {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "__typename")]
+#[allow(clippy::large_enum_variant)]
 pub enum UserResult {
     User(UserData),
     UserUnavailable(UserUnavailable),
@@ -197,35 +200,16 @@ pub async fn get_profile(client: &TwitterClient, screen_name: &str) -> Result<Pr
         "withSafetyModeUserFields": true
     });
 
-    let features = json!({
-        "hidden_profile_likes_enabled": false,
-        "hidden_profile_subscriptions_enabled": false,
-        "responsive_web_graphql_exclude_directive_enabled": true,
-        "verified_phone_label_enabled": false,
-        "subscriptions_verification_info_is_identity_verified_enabled": false,
-        "subscriptions_verification_info_verified_since_enabled": true,
-        "highlights_tweets_tab_ui_enabled": true,
-        "creator_subscriptions_tweet_preview_api_enabled": true,
-        "responsive_web_graphql_skip_user_profile_image_extensions_enabled": false,
-        "responsive_web_graphql_timeline_navigation_enabled": true
-    });
-
     let field_toggles = json!({
         "withAuxiliaryUserLabels": false
     });
 
-    let (response, _) = request_api::<UserRaw>(
-        &client.client,
-        "https://twitter.com/i/api/graphql/G3KGOASz96M-Qu0nwmGXNg/UserByScreenName",
-        headers,
-        Method::GET,
-        Some(json!({
-            "variables": variables,
-            "features": features,
-            "fieldToggles": field_toggles
-        })),
-    )
-    .await?;
+    let endpoint = client
+        .graphql_endpoint("UserByScreenName")
+        .with_variables(variables)
+        .with_field_toggles(field_toggles);
+
+    let (response, _) = request_graphql::<UserRaw>(client, &endpoint, headers).await?;
 
     if let Some(errors) = response.errors {
         if !errors.is_empty() {
@@ -255,30 +239,11 @@ pub async fn get_screen_name_by_user_id(client: &TwitterClient, user_id: &str) -
         "withSafetyModeUserFields": true
     });
 
-    let features = json!({
-        "hidden_profile_subscriptions_enabled": true,
-        "rweb_tipjar_consumption_enabled": true,
-        "responsive_web_graphql_exclude_directive_enabled": true,
-        "verified_phone_label_enabled": false,
-        "highlights_tweets_tab_ui_enabled": true,
-        "responsive_web_twitter_article_notes_tab_enabled": true,
-        "subscriptions_feature_can_gift_premium": false,
-        "creator_subscriptions_tweet_preview_api_enabled": true,
-        "responsive_web_graphql_skip_user_profile_image_extensions_enabled": false,
-        "responsive_web_graphql_timeline_navigation_enabled": true
-    });
+    let endpoint = client
+        .graphql_endpoint("UserByRestId")
+        .with_variables(variables);
 
-    let (response, _) = request_api::<UserRaw>(
-        &client.client,
-        "https://twitter.com/i/api/graphql/xf3jd90KKBCUxdlI_tNHZw/UserByRestId",
-        headers,
-        Method::GET,
-        Some(json!({
-            "variables": variables,
-            "features": features
-        })),
-    )
-    .await?;
+    let (response, _) = request_graphql::<UserRaw>(client, &endpoint, headers).await?;
 
     if let Some(errors) = response.errors {
         if !errors.is_empty() {
@@ -321,8 +286,8 @@ pub async fn me(client: &TwitterClient) -> Result<Profile> {
     client.auth.install_headers(&mut headers).await?;
 
     let (response, _) = request_api::<serde_json::Value>(
-        &client.client,
-        "https://api.x.com/1.1/account/verify_credentials.json",
+        client,
+        &client.hosts.api_url("1.1/account/verify_credentials.json"),
         headers,
         reqwest::Method::GET,
         None,
@@ -336,7 +301,8 @@ pub async fn me(client: &TwitterClient) -> Result<Profile> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{scraper::Scraper, tests::get_session};
+    use crate::mock::MockServer;
+    use crate::tests::get_session;
 
     #[tokio::test]
     async fn test_me() {
@@ -347,4 +313,24 @@ mod tests {
 
         assert!(!profile.username.is_empty(), "Expected profile");
     }
+
+    #[tokio::test]
+    async fn test_get_profile() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        let profile = scraper.get_profile("mockuser").await.unwrap();
+
+        assert_eq!(profile.id, "1000");
+        assert_eq!(profile.name, "Mock User");
+        assert_eq!(profile.url.as_deref(), Some("https://example.com/mockuser"));
+        assert_eq!(profile.pinned_tweet_id.as_deref(), Some("2000"));
+
+        let requests = server.requests_to("UserByScreenName");
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].header("x-csrf-token"),
+            Some(crate::mock::MOCK_CT0)
+        );
+    }
 }
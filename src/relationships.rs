@@ -1,11 +1,10 @@
 use crate::api::client::TwitterClient;
-use crate::api::requests::request_api;
+use crate::api::requests::request_graphql;
 use crate::api::requests::request_form_api;
 use crate::error::{Result, TwitterError};
 use crate::models::Profile;
 use crate::timeline::v1::QueryProfilesResponse;
 use chrono::{DateTime, Utc};
-use reqwest::Method;
 use serde::Deserialize;
 use serde_json::{json, Value};
 #[derive(Debug, Deserialize)]
@@ -43,6 +42,7 @@ pub struct TimelineData {
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
+#[allow(clippy::large_enum_variant)]
 pub enum TimelineInstruction {
     #[serde(rename = "TimelineAddEntries")]
     AddEntries { entries: Vec<TimelineEntry> },
@@ -53,7 +53,9 @@ pub enum TimelineInstruction {
 #[derive(Debug, Deserialize)]
 pub struct TimelineEntry {
     pub content: EntryContent,
+    #[serde(alias = "entryId")]
     pub entry_id: String,
+    #[serde(alias = "sortIndex")]
     pub sort_index: String,
 }
 
@@ -62,6 +64,23 @@ pub struct EntryContent {
     #[serde(rename = "itemContent")]
     pub item_content: Option<ItemContent>,
     pub cursor: Option<CursorContent>,
+    pub value: Option<String>,
+    #[serde(rename = "cursorType")]
+    pub cursor_type: Option<String>,
+}
+
+impl EntryContent {
+    /// Cursor type and value, whether nested under `cursor` or inlined in the content
+    /// as `TimelineTimelineCursor` entries are.
+    fn cursor_parts(&self) -> Option<(Option<&str>, &str)> {
+        match &self.cursor {
+            Some(cursor) => Some((cursor.cursor_type.as_deref(), cursor.value.as_str())),
+            None => self
+                .value
+                .as_deref()
+                .map(|value| (self.cursor_type.as_deref(), value)),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -121,7 +140,7 @@ pub struct UserLegacy {
     pub created_at: Option<String>,
     pub profile_image_url_https: Option<String>,
     pub profile_banner_url: Option<String>,
-    pub pinned_tweet_ids_str: Option<String>,
+    pub pinned_tweet_ids_str: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -171,6 +190,7 @@ pub struct InnerTimeline {
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
+#[allow(clippy::large_enum_variant)]
 pub enum Instruction {
     #[serde(rename = "TimelineAddEntries")]
     AddEntries {
@@ -183,7 +203,9 @@ pub enum Instruction {
 #[derive(Debug, Deserialize)]
 pub struct RelationshipTimelineEntry {
     pub content: EntryContent,
+    #[serde(alias = "entryId")]
     pub entry_id: String,
+    #[serde(alias = "sortIndex")]
     pub sort_index: String,
 }
 
@@ -246,25 +268,15 @@ async fn get_following_timeline(
         }
     }
 
-    let features = json!({
-        "responsive_web_twitter_article_tweet_consumption_enabled": false,
-        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled": true,
-        "longform_notetweets_inline_media_enabled": true,
-        "responsive_web_media_download_video_enabled": false,
-    });
-
-    let url = format!(
-        "https://twitter.com/i/api/graphql/iSicc7LrzWGBgDPL0tM_TQ/Following?variables={}&features={}",
-        urlencoding::encode(&variables.to_string()),
-        urlencoding::encode(&features.to_string())
-    );
+    let endpoint = client
+        .graphql_endpoint("Following")
+        .with_variables(variables);
 
     let mut headers = reqwest::header::HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
 
     let (_data, _) =
-        request_api::<RelationshipTimeline>(&client.client, &url, headers, Method::GET, None)
-            .await?;
+        request_graphql::<RelationshipTimeline>(client, &endpoint, headers).await?;
 
     Ok(_data)
 }
@@ -314,7 +326,10 @@ fn parse_relationship_timeline(timeline: &RelationshipTimeline) -> QueryProfiles
                                             .unwrap_or_default(),
                                         profile_image_url: legacy.profile_image_url_https.clone(),
                                         profile_banner_url: legacy.profile_banner_url.clone(),
-                                        pinned_tweet_id: legacy.pinned_tweet_ids_str.clone(),
+                                        pinned_tweet_id: legacy
+                                            .pinned_tweet_ids_str
+                                            .as_ref()
+                                            .and_then(|ids| ids.first().cloned()),
                                         is_blue_verified: Some(
                                             user_results.result.is_blue_verified.unwrap_or(false),
                                         ),
@@ -323,20 +338,20 @@ fn parse_relationship_timeline(timeline: &RelationshipTimeline) -> QueryProfiles
                                     profiles.push(profile);
                                 }
                             }
-                        } else if let Some(cursor_content) = &entry.content.cursor {
-                            match cursor_content.cursor_type.as_deref() {
-                                Some("Bottom") => next_cursor = Some(cursor_content.value.clone()),
-                                Some("Top") => previous_cursor = Some(cursor_content.value.clone()),
+                        } else if let Some((cursor_type, value)) = entry.content.cursor_parts() {
+                            match cursor_type {
+                                Some("Bottom") => next_cursor = Some(value.to_string()),
+                                Some("Top") => previous_cursor = Some(value.to_string()),
                                 _ => {}
                             }
                         }
                     }
                 }
                 TimelineInstruction::ReplaceEntry { entry } => {
-                    if let Some(cursor_content) = &entry.content.cursor {
-                        match cursor_content.cursor_type.as_deref() {
-                            Some("Bottom") => next_cursor = Some(cursor_content.value.clone()),
-                            Some("Top") => previous_cursor = Some(cursor_content.value.clone()),
+                    if let Some((cursor_type, value)) = entry.content.cursor_parts() {
+                        match cursor_type {
+                            Some("Bottom") => next_cursor = Some(value.to_string()),
+                            Some("Top") => previous_cursor = Some(value.to_string()),
                             _ => {}
                         }
                     }
@@ -355,7 +370,7 @@ fn parse_relationship_timeline(timeline: &RelationshipTimeline) -> QueryProfiles
 pub async fn follow_user(client: &TwitterClient, username: &str) -> Result<()> {
     let user_id = crate::profile::get_user_id_by_screen_name(client, username).await?;
 
-    let url = "https://api.x.com/1.1/friendships/create.json";
+    let url = client.hosts.api_url("1.1/friendships/create.json");
 
     let form = vec![
         (
@@ -381,7 +396,7 @@ pub async fn follow_user(client: &TwitterClient, username: &str) -> Result<()> {
     headers.insert("X-Twitter-Auth-Type", "OAuth2Session".parse().unwrap());
     headers.insert("X-Twitter-Client-Language", "en".parse().unwrap());
 
-    let (_, _) = request_form_api::<Value>(&client.client, url, headers, form).await?;
+    let (_, _) = request_form_api::<Value>(client, &url, headers, form).await?;
 
     Ok(())
 }
@@ -389,7 +404,7 @@ pub async fn follow_user(client: &TwitterClient, username: &str) -> Result<()> {
 pub async fn unfollow_user(client: &TwitterClient, username: &str) -> Result<()> {
     let user_id = crate::profile::get_user_id_by_screen_name(client, username).await?;
 
-    let url = "https://api.x.com/1.1/friendships/destroy.json";
+    let url = client.hosts.api_url("1.1/friendships/destroy.json");
 
     let form = vec![
         (
@@ -415,7 +430,36 @@ pub async fn unfollow_user(client: &TwitterClient, username: &str) -> Result<()>
     headers.insert("X-Twitter-Auth-Type", "OAuth2Session".parse().unwrap());
     headers.insert("X-Twitter-Client-Language", "en".parse().unwrap());
 
-    let (_, _) = request_form_api::<Value>(&client.client, url, headers, form).await?;
+    let (_, _) = request_form_api::<Value>(client, &url, headers, form).await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::MockServer;
+
+    #[tokio::test]
+    async fn test_get_following() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        let (profiles, next) = scraper.get_following("1000", 20, None).await.unwrap();
+
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].username, "mockfriend");
+        assert_eq!(next.as_deref(), Some("1000|FOLLOWINGBOTTOM"));
+    }
+
+    #[tokio::test]
+    async fn test_follow_user() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        scraper.follow_user("mockuser").await.unwrap();
+
+        let requests = server.requests_to("/1.1/friendships/create.json");
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].body_text().contains("user_id=1000"));
+    }
+}
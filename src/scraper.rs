@@ -1,15 +1,33 @@
-use crate::api::client::TwitterClient;
-use crate::auth::user_auth::TwitterUserAuth;
+use crate::api::cassette::Cassette;
+use crate::api::client::{TwitterClient, TwitterClientBuilder};
+use crate::api::discovery;
+use crate::api::endpoints::OperationRegistry;
+use crate::api::hosts::Hosts;
+use crate::api::interceptor::Interceptor;
+use crate::api::proxy::{ProxyConfig, ProxyRotation};
+use crate::api::rate_limit::RateLimitInfo;
+use crate::api::retry::RetryPolicy;
+use crate::auth::challenge::LoginChallengeHandler;
+use crate::auth::config::AuthConfig;
+use crate::auth::health::{SessionEvent, SessionMonitor, SessionStatus};
+use crate::auth::relogin::AutoRelogin;
+use crate::auth::session::CookieFormat;
+use crate::auth::store::SessionStore;
+use crate::auth::user_auth::{TwitterAuth, TwitterUserAuth};
 use crate::constants::BEARER_TOKEN;
 use crate::error::Result;
 use crate::error::TwitterError;
 use crate::explore::ExploreTimeline;
 use crate::messages::DirectMessagesResponse;
-use crate::models::{Profile, Tweet};
+use crate::models::{AccountSession, Profile, Tweet};
+use crate::official_api;
 use crate::search::{fetch_search_tweets, SearchMode};
 use crate::timeline::v1::{QueryProfilesResponse, QueryTweetsResponse};
 use crate::timeline::v2::QueryTweetsResponse as V2QueryTweetsResponse;
 use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
 
 pub struct Scraper {
     pub twitter_client: TwitterClient,
@@ -17,9 +35,200 @@ pub struct Scraper {
 
 impl Scraper {
     pub async fn new() -> Result<Self> {
+        Self::with_hosts(Hosts::default()).await
+    }
+
+    pub async fn with_hosts(hosts: Hosts) -> Result<Self> {
+        Self::with_client_builder(TwitterClientBuilder::new().with_hosts(hosts)).await
+    }
+
+    /// A guest scraper whose HTTP client uses `builder`'s timeouts, pool and header
+    /// profile; the settings are kept when logging in.
+    pub async fn with_client_builder(builder: TwitterClientBuilder) -> Result<Self> {
         let auth = Box::new(TwitterUserAuth::new(BEARER_TOKEN.to_string()).await?);
-        let twitter_client = TwitterClient::new(auth.clone())?;
-        Ok(Self { twitter_client })
+        Ok(Self {
+            twitter_client: builder.build(auth)?,
+        })
+    }
+
+    /// A scraper authenticating with `auth`, such as
+    /// [`OAuth1Auth`](crate::auth::oauth1::OAuth1Auth) or
+    /// [`OAuth2Auth`](crate::auth::oauth2::OAuth2Auth) for the official API.
+    pub fn with_auth(auth: impl TwitterAuth) -> Result<Self> {
+        Ok(Self::from_client(TwitterClient::new(Box::new(auth))?))
+    }
+
+    pub fn from_client(twitter_client: TwitterClient) -> Self {
+        Self { twitter_client }
+    }
+
+    /// Whether requests go to the official v2 API rather than the web client's GraphQL API.
+    /// With official API auth, the profile, tweet, like, retweet and follow methods use v2
+    /// endpoints, and methods that only exist in the web client fail with
+    /// [`TwitterError::Unsupported`].
+    pub fn is_official_api(&self) -> bool {
+        self.twitter_client.auth.is_official_api()
+    }
+
+    /// Records or replays this scraper's requests through `cassette`.
+    pub fn set_cassette(&mut self, cassette: Cassette) {
+        self.twitter_client.cassette = Some(Arc::new(cassette));
+    }
+
+    /// Waits up to `max_wait` for rate limits to reset instead of failing; `None` disables
+    /// waiting.
+    pub fn set_rate_limit_wait(&mut self, max_wait: Option<Duration>) {
+        self.twitter_client.rate_limit_wait = max_wait;
+    }
+
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.twitter_client.retry_policy = retry_policy;
+    }
+
+    /// Latest budget reported for `endpoint`, a GraphQL operation name or REST path.
+    pub fn rate_limit(&self, endpoint: &str) -> Option<RateLimitInfo> {
+        self.twitter_client.rate_limit(endpoint)
+    }
+
+    /// GraphQL operations used by this scraper; query IDs can be patched through it.
+    pub fn operations(&self) -> &OperationRegistry {
+        &self.twitter_client.operations
+    }
+
+    /// Applies query ID and feature overrides from a JSON or TOML file.
+    pub fn load_operation_overrides(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.twitter_client.operations.load_file(path)
+    }
+
+    /// Refreshes query IDs from the x.com web client bundle. Returns the names of the
+    /// operations whose query ID changed.
+    pub async fn refresh_operations(&self) -> Result<Vec<String>> {
+        discovery::refresh_operations(&self.twitter_client).await
+    }
+
+    /// Like [`Scraper::refresh_operations`], reading a saved copy of the bundle.
+    pub fn refresh_operations_from_file(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Vec<String>> {
+        discovery::refresh_from_file(&self.twitter_client.operations, path)
+    }
+
+    /// Refreshes query IDs automatically when a GraphQL request fails with 404.
+    pub fn set_operation_discovery(&mut self, enabled: bool) {
+        self.twitter_client.operation_discovery = enabled;
+    }
+
+    /// Runs `interceptor` for every request this scraper sends, after the ones already
+    /// added.
+    pub fn add_interceptor(&mut self, interceptor: Arc<dyn Interceptor>) {
+        self.twitter_client.interceptors.push(interceptor);
+    }
+
+    /// Sends every request, including the login, through `proxy`.
+    pub fn set_proxy(&mut self, proxy: ProxyConfig) -> Result<()> {
+        self.set_proxies(vec![proxy], "")
+    }
+
+    /// Sends every request through one of `proxies`, starting with the one assigned to
+    /// `account` and moving to the next when it cannot be reached.
+    pub fn set_proxies(&mut self, proxies: Vec<ProxyConfig>, account: &str) -> Result<()> {
+        self.twitter_client.proxies = Some(Arc::new(ProxyRotation::new(
+            proxies,
+            account,
+            self.twitter_client.builder.clone(),
+        )?));
+        Ok(())
+    }
+
+    /// The proxy requests are currently sent through.
+    pub fn current_proxy(&self) -> Option<ProxyConfig> {
+        self.twitter_client.current_proxy()
+    }
+
+    /// Rebuilds the HTTP client around `auth`, keeping the configured hosts and cassette.
+    fn reset_client(&mut self, auth: TwitterUserAuth) -> Result<()> {
+        self.twitter_client = self.client_for(auth)?;
+        Ok(())
+    }
+
+    /// A new client for `auth` sharing this scraper's configuration.
+    fn client_for(&self, auth: TwitterUserAuth) -> Result<TwitterClient> {
+        let mut twitter_client = self
+            .twitter_client
+            .builder
+            .clone()
+            .build(Box::new(auth))?
+            .with_hosts(self.twitter_client.hosts.clone());
+        twitter_client.cassette = self.twitter_client.cassette.clone();
+        twitter_client.rate_limits = self.twitter_client.rate_limits.clone();
+        twitter_client.rate_limit_wait = self.twitter_client.rate_limit_wait;
+        twitter_client.retry_policy = self.twitter_client.retry_policy.clone();
+        twitter_client.operations = self.twitter_client.operations.clone();
+        twitter_client.operation_discovery = self.twitter_client.operation_discovery;
+        twitter_client.auto_relogin = self.twitter_client.auto_relogin.clone();
+        twitter_client.proxies = self.twitter_client.proxies.clone();
+        twitter_client.interceptors = self.twitter_client.interceptors.clone();
+        Ok(twitter_client)
+    }
+
+    /// Logs in again with `config`'s credentials whenever X rejects the session, replaying
+    /// the failed request once.
+    pub fn enable_auto_relogin(&mut self, config: AuthConfig) {
+        self.twitter_client.auto_relogin = Some(Arc::new(AutoRelogin::new(config)));
+    }
+
+    pub fn disable_auto_relogin(&mut self) {
+        self.twitter_client.auto_relogin = None;
+    }
+
+    /// Uses a guest token for at most `max_age` before activating a new one. Only applies
+    /// while not logged in.
+    pub fn set_guest_token_max_age(&mut self, max_age: Duration) -> Result<()> {
+        let auth = self.user_auth()?.clone().with_guest_token_max_age(max_age);
+        self.reset_client(auth)
+    }
+
+    /// Lets `handler` answer login steps such as email codes, 2FA without a stored secret
+    /// and captchas instead of failing the login.
+    pub fn set_login_challenge_handler(
+        &mut self,
+        handler: Arc<dyn LoginChallengeHandler>,
+    ) -> Result<()> {
+        let auth = self.user_auth()?.clone().with_challenge_handler(handler);
+        self.reset_client(auth)
+    }
+
+    fn user_auth(&self) -> Result<&TwitterUserAuth> {
+        self.twitter_client
+            .auth
+            .as_any()
+            .downcast_ref::<TwitterUserAuth>()
+            .ok_or_else(|| TwitterError::Auth("Invalid auth type".into()))
+    }
+
+    /// Status of the session from its cookie expiry and the last validation result.
+    pub async fn session_status(&self) -> Result<SessionStatus> {
+        Ok(self.user_auth()?.session_status().await)
+    }
+
+    /// Checks the session with X and returns its updated status.
+    pub async fn validate_session(&self) -> Result<SessionStatus> {
+        self.user_auth()?
+            .validate_session(&self.twitter_client)
+            .await
+    }
+
+    /// Receives an event when the session nears expiry or stops working.
+    pub fn subscribe_session_events(&self) -> Result<broadcast::Receiver<SessionEvent>> {
+        Ok(self.user_auth()?.subscribe_session_events())
+    }
+
+    /// Validates the session every `interval` in the background until the returned monitor
+    /// is dropped.
+    pub fn start_session_monitor(&self, interval: Duration) -> Result<SessionMonitor> {
+        let auth = self.user_auth()?;
+        Ok(auth.spawn_session_monitor(self.client_for(auth.clone())?, interval))
     }
 
     pub async fn login(
@@ -37,7 +246,7 @@ impl Scraper {
         {
             let mut auth = user_auth.clone();
             auth.login(
-                &self.twitter_client.client,
+                &self.twitter_client,
                 &username,
                 &password,
                 email.as_deref(),
@@ -53,10 +262,41 @@ impl Scraper {
         }
     }
 
+    /// Ends the session on X, clears the cookies and deletes the session from the
+    /// session store. Later requests are made as a guest.
+    pub async fn logout(&self) -> Result<()> {
+        self.user_auth()?.logout(&self.twitter_client).await
+    }
+
+    /// Devices and apps logged in to the account.
+    pub async fn list_sessions(&self) -> Result<Vec<AccountSession>> {
+        self.user_auth()?.list_sessions(&self.twitter_client).await
+    }
+
+    pub async fn revoke_session(&self, hashed_token: &str) -> Result<()> {
+        self.user_auth()?
+            .revoke_session(&self.twitter_client, hashed_token)
+            .await
+    }
+
+    /// Logs out every other device and app, keeping this session.
+    pub async fn revoke_other_sessions(&self) -> Result<()> {
+        self.user_auth()?
+            .revoke_other_sessions(&self.twitter_client)
+            .await
+    }
+
+    /// Works without logging in.
     pub async fn get_profile(&self, username: &str) -> Result<crate::models::Profile> {
+        if self.is_official_api() {
+            return official_api::get_profile(&self.twitter_client, username).await;
+        }
         crate::profile::get_profile(&self.twitter_client, username).await
     }
     pub async fn me(&self) -> Result<crate::models::Profile> {
+        if self.is_official_api() {
+            return official_api::me(&self.twitter_client).await;
+        }
         crate::profile::me(&self.twitter_client).await
     }
     pub async fn send_tweet(
@@ -65,6 +305,10 @@ impl Scraper {
         reply_to: Option<&str>,
         media_data: Option<Vec<(Vec<u8>, String)>>,
     ) -> Result<Value> {
+        if self.is_official_api() {
+            return official_api::send_tweet(&self.twitter_client, text, reply_to, media_data)
+                .await;
+        }
         crate::tweets::create_tweet_request(&self.twitter_client, text, reply_to, media_data).await
     }
 
@@ -90,6 +334,107 @@ impl Scraper {
         }
     }
 
+    /// Loads a session file, a Netscape `cookies.txt` or a browser extension cookie export.
+    pub async fn load_cookies(&mut self, cookie_file: &str) -> Result<()> {
+        if let Some(user_auth) = self
+            .twitter_client
+            .auth
+            .as_any()
+            .downcast_ref::<TwitterUserAuth>()
+        {
+            let mut auth = user_auth.clone();
+            auth.load_cookies_from_file(cookie_file).await?;
+
+            self.reset_client(auth)
+        } else {
+            Err(TwitterError::Auth("Invalid auth type".into()))
+        }
+    }
+
+    pub async fn export_cookies(&self, format: CookieFormat) -> Result<String> {
+        if let Some(user_auth) = self
+            .twitter_client
+            .auth
+            .as_any()
+            .downcast_ref::<TwitterUserAuth>()
+        {
+            user_auth.export_cookies(format).await
+        } else {
+            Err(TwitterError::Auth("Invalid auth type".into()))
+        }
+    }
+
+    pub async fn import_cookies(
+        &mut self,
+        content: &str,
+        format: Option<CookieFormat>,
+    ) -> Result<()> {
+        if let Some(user_auth) = self
+            .twitter_client
+            .auth
+            .as_any()
+            .downcast_ref::<TwitterUserAuth>()
+        {
+            let mut auth = user_auth.clone();
+            auth.import_cookies(content, format).await?;
+
+            self.reset_client(auth)
+        } else {
+            Err(TwitterError::Auth("Invalid auth type".into()))
+        }
+    }
+
+    /// Persists the session to `store` under `account`, saving it again whenever a response
+    /// updates the `auth_token` or `ct0` cookie. Call [`Scraper::load_session`] to restore a
+    /// saved session.
+    pub fn set_session_store(&mut self, store: Arc<dyn SessionStore>, account: &str) -> Result<()> {
+        if let Some(user_auth) = self
+            .twitter_client
+            .auth
+            .as_any()
+            .downcast_ref::<TwitterUserAuth>()
+        {
+            let auth = user_auth.clone().with_session_store(store, account);
+
+            self.reset_client(auth)
+        } else {
+            Err(TwitterError::Auth("Invalid auth type".into()))
+        }
+    }
+
+    /// Restores the session saved in the session store. Returns `false` if none was saved.
+    pub async fn load_session(&mut self) -> Result<bool> {
+        if let Some(user_auth) = self
+            .twitter_client
+            .auth
+            .as_any()
+            .downcast_ref::<TwitterUserAuth>()
+        {
+            let auth = user_auth.clone();
+            if !auth.load_session().await? {
+                return Ok(false);
+            }
+
+            self.reset_client(auth)?;
+            Ok(true)
+        } else {
+            Err(TwitterError::Auth("Invalid auth type".into()))
+        }
+    }
+
+    pub async fn save_session(&self) -> Result<()> {
+        if let Some(user_auth) = self
+            .twitter_client
+            .auth
+            .as_any()
+            .downcast_ref::<TwitterUserAuth>()
+        {
+            user_auth.save_session().await
+        } else {
+            Err(TwitterError::Auth("Invalid auth type".into()))
+        }
+    }
+
     pub async fn get_cookie_string(&self) -> Result<String> {
         if let Some(user_auth) = self
             .twitter_client
@@ -113,9 +458,7 @@ impl Scraper {
             let mut auth = user_auth.clone();
             auth.set_cookies(json_str).await?;
 
-            self.twitter_client.auth = Box::new(auth.clone());
-            self.twitter_client = TwitterClient::new(Box::new(auth))?;
-            Ok(())
+            self.reset_client(auth)
         } else {
             Err(TwitterError::Auth("Invalid auth type".into()))
         }
@@ -131,9 +474,7 @@ impl Scraper {
             let mut auth = user_auth.clone();
             auth.set_from_cookie_string(cookie_string).await?;
 
-            self.twitter_client.auth = Box::new(auth.clone());
-            self.twitter_client = TwitterClient::new(Box::new(auth))?;
-            Ok(())
+            self.reset_client(auth)
         } else {
             Err(TwitterError::Auth("Invalid auth type".into()))
         }
@@ -158,10 +499,16 @@ impl Scraper {
     }
 
     pub async fn follow_user(&self, username: &str) -> Result<()> {
+        if self.is_official_api() {
+            return official_api::follow_user(&self.twitter_client, username).await;
+        }
         crate::relationships::follow_user(&self.twitter_client, username).await
     }
 
     pub async fn unfollow_user(&self, username: &str) -> Result<()> {
+        if self.is_official_api() {
+            return official_api::unfollow_user(&self.twitter_client, username).await;
+        }
         crate::relationships::unfollow_user(&self.twitter_client, username).await
     }
 
@@ -208,10 +555,16 @@ impl Scraper {
     }
 
     pub async fn like_tweet(&self, tweet_id: &str) -> Result<Value> {
+        if self.is_official_api() {
+            return official_api::like_tweet(&self.twitter_client, tweet_id).await;
+        }
         crate::tweets::like_tweet(&self.twitter_client, tweet_id).await
     }
 
     pub async fn retweet(&self, tweet_id: &str) -> Result<Value> {
+        if self.is_official_api() {
+            return official_api::retweet(&self.twitter_client, tweet_id).await;
+        }
         crate::tweets::retweet(&self.twitter_client, tweet_id).await
     }
 
@@ -224,10 +577,23 @@ impl Scraper {
         crate::tweets::create_long_tweet(&self.twitter_client, text, reply_to, media_ids).await
     }
 
+    /// Fetches a tweet with its conversation context. Requires a logged-in session; use
+    /// [`Scraper::get_tweet_by_rest_id`] without one.
     pub async fn get_tweet(&self, id: &str) -> Result<Tweet> {
+        if self.is_official_api() {
+            return official_api::get_tweet(&self.twitter_client, id).await;
+        }
         crate::tweets::get_tweet(&self.twitter_client, id).await
     }
 
+    /// Fetches a single tweet. Works without logging in.
+    pub async fn get_tweet_by_rest_id(&self, id: &str) -> Result<Tweet> {
+        if self.is_official_api() {
+            return official_api::get_tweet(&self.twitter_client, id).await;
+        }
+        crate::tweets::get_tweet_by_rest_id(&self.twitter_client, id).await
+    }
+
     pub async fn search_tweets(
         &self,
         query: &str,
@@ -247,12 +613,22 @@ impl Scraper {
         crate::search::search_profiles(&self.twitter_client, query, max_profiles, cursor).await
     }
 
+    /// Works without logging in.
     pub async fn get_user_tweets(
         &self,
         user_id: &str,
         count: i32,
         cursor: Option<String>,
     ) -> Result<V2QueryTweetsResponse> {
+        if self.is_official_api() {
+            return official_api::get_user_tweets(
+                &self.twitter_client,
+                user_id,
+                count,
+                cursor.as_deref(),
+            )
+            .await;
+        }
         crate::tweets::fetch_user_tweets(&self.twitter_client, user_id, count, cursor.as_deref())
             .await
     }
@@ -1,11 +1,10 @@
 use crate::api::client::TwitterClient;
-use crate::api::requests::request_api;
+use crate::api::requests::request_graphql;
 use crate::error::Result;
 use crate::timeline::search::{
     parse_search_timeline_tweets, parse_search_timeline_users, SearchTimeline,
 };
 use crate::timeline::v1::{QueryProfilesResponse, QueryTweetsResponse};
-use reqwest::Method;
 use serde_json::json;
 #[derive(Debug, Clone, Copy)]
 pub enum SearchMode {
@@ -73,70 +72,42 @@ async fn get_search_timeline(
         _ => {}
     }
 
-    let features = json!({
-        "longform_notetweets_inline_media_enabled": true,
-        "responsive_web_enhance_cards_enabled": false,
-        "responsive_web_media_download_video_enabled": false,
-        "responsive_web_twitter_article_tweet_consumption_enabled": false,
-        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled": true,
-        "interactive_text_enabled": false,
-        "responsive_web_text_conversations_enabled": false,
-        "vibe_api_enabled": false,
-        "rweb_lists_timeline_redesign_enabled": true,
-        "responsive_web_graphql_exclude_directive_enabled": true,
-        "verified_phone_label_enabled": false,
-        "creator_subscriptions_tweet_preview_api_enabled": true,
-        "responsive_web_graphql_timeline_navigation_enabled": true,
-        "responsive_web_graphql_skip_user_profile_image_extensions_enabled": false,
-        "tweetypie_unmention_optimization_enabled": true,
-        "responsive_web_edit_tweet_api_enabled": true,
-        "graphql_is_translatable_rweb_tweet_is_translatable_enabled": true,
-        "view_counts_everywhere_api_enabled": true,
-        "longform_notetweets_consumption_enabled": true,
-        "tweet_awards_web_tipping_enabled": false,
-        "freedom_of_speech_not_reach_fetch_enabled": true,
-        "standardized_nudges_misinfo": true,
-        "longform_notetweets_rich_text_read_enabled": true,
-        "responsive_web_enhance_cards_enabled": false,
-        "subscriptions_verification_info_enabled": true,
-        "subscriptions_verification_info_reason_enabled": true,
-        "subscriptions_verification_info_verified_since_enabled": true,
-        "super_follow_badge_privacy_enabled": false,
-        "super_follow_exclusive_tweet_notifications_enabled": false,
-        "super_follow_tweet_api_enabled": false,
-        "super_follow_user_api_enabled": false,
-        "android_graphql_skip_api_media_color_palette": false,
-        "creator_subscriptions_subscription_count_enabled": false,
-        "blue_business_profile_image_shape_enabled": false,
-        "unified_cards_ad_metadata_container_dynamic_card_content_query_enabled": false
-    });
-
     let field_toggles = json!({
         "withArticleRichContentState": false
     });
 
-    let params = [
-        ("variables", serde_json::to_string(&variables)?),
-        ("features", serde_json::to_string(&features)?),
-        ("fieldToggles", serde_json::to_string(&field_toggles)?),
-    ];
-
-    let query_string = params
-        .iter()
-        .map(|(k, v)| format!("{}={}", k, urlencoding::encode(v)))
-        .collect::<Vec<_>>()
-        .join("&");
+    let endpoint = client
+        .graphql_endpoint("SearchTimeline")
+        .with_variables(variables)
+        .with_field_toggles(field_toggles);
 
     let mut headers = reqwest::header::HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
 
-    let url = format!(
-        "https://api.x.com/graphql/gkjsKepM6gl_HmFWoWKfgg/SearchTimeline?{}",
-        query_string
-    );
-
-    let (response, _) =
-        request_api::<SearchTimeline>(&client.client, &url, headers, Method::GET, None).await?;
+    let (response, _) = request_graphql::<SearchTimeline>(client, &endpoint, headers).await?;
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::MockServer;
+    use crate::search::SearchMode;
+
+    #[tokio::test]
+    async fn test_search_tweets_and_profiles() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        let tweets = scraper
+            .search_tweets("rust", 20, SearchMode::Latest, None)
+            .await
+            .unwrap();
+        assert_eq!(tweets.tweets.len(), 1);
+        assert_eq!(tweets.tweets[0].id.as_deref(), Some("2000"));
+
+        let profiles = scraper.search_profiles("mock", 20, None).await.unwrap();
+        assert_eq!(profiles.profiles.len(), 1);
+        assert_eq!(profiles.profiles[0].username, "mockfriend");
+    }
+}
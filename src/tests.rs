@@ -1,10 +1,68 @@
-use crate::{error::TwitterError, scraper::Scraper};
+use crate::{error::TwitterError, mock::MockServer, scraper::Scraper};
 
 pub async fn get_session() -> Result<Scraper, TwitterError> {
-    dotenv::dotenv().ok();
-    let mut scraper = Scraper::new().await?;
-    let cookie_string = std::env::var("TWITTER_COOKIE_STRING")
-        .expect("TWITTER_COOKIE_STRING environment variable not set");
-    scraper.set_from_cookie_string(&cookie_string).await?;
-    Ok(scraper)
+    let server = MockServer::start().await?;
+    server.scraper().await
+}
+
+/// Everything logged through `tracing` on the current thread while the returned guard is
+/// alive, at every level, one line per span or event.
+pub fn capture_logs() -> (LogCapture, tracing::subscriber::DefaultGuard) {
+    let capture = LogCapture::default();
+    let guard = tracing::subscriber::set_default(capture.clone());
+    (capture, guard)
+}
+
+#[derive(Clone, Default)]
+pub struct LogCapture {
+    lines: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    next_id: std::sync::Arc<std::sync::atomic::AtomicU64>,
+}
+
+impl LogCapture {
+    pub fn text(&self) -> String {
+        self.lines.lock().unwrap().join("\n")
+    }
+
+    fn push(&self, name: &str, fields: &dyn Fn(&mut dyn tracing::field::Visit)) {
+        let mut line = name.to_string();
+        fields(&mut LineVisitor(&mut line));
+        self.lines.lock().unwrap().push(line);
+    }
+}
+
+struct LineVisitor<'a>(&'a mut String);
+
+impl tracing::field::Visit for LineVisitor<'_> {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.0.push_str(&format!(" {}={:?}", field.name(), value));
+    }
+}
+
+impl tracing::Subscriber for LogCapture {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        self.push(span.metadata().name(), &|visitor| span.record(visitor));
+        let id = self
+            .next_id
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        tracing::span::Id::from_u64(id + 1)
+    }
+
+    fn record(&self, _: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+        self.push("record", &|visitor| values.record(visitor));
+    }
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        self.push(event.metadata().name(), &|visitor| event.record(visitor));
+    }
+
+    fn enter(&self, _: &tracing::span::Id) {}
+
+    fn exit(&self, _: &tracing::span::Id) {}
 }
@@ -1,11 +1,9 @@
-use crate::api::requests::request_api;
+use crate::api::requests::request_graphql;
 use crate::error::Result;
 use crate::api::client::TwitterClient;
 use serde::Deserialize;
 use serde_json::Value;
-use urlencoding;
 use reqwest::header::HeaderMap;
-use reqwest::Method;
 
 #[derive(Debug, Deserialize)]
 pub struct HomeTimelineResponse {
@@ -71,48 +69,14 @@ pub async fn fetch_home_timeline(
         "seenTweetIds": seen_tweet_ids,
     });
 
-    let features = serde_json::json!({
-        "rweb_tipjar_consumption_enabled": true,
-        "responsive_web_graphql_exclude_directive_enabled": true,
-        "verified_phone_label_enabled": false,
-        "creator_subscriptions_tweet_preview_api_enabled": true,
-        "responsive_web_graphql_timeline_navigation_enabled": true,
-        "responsive_web_graphql_skip_user_profile_image_extensions_enabled": false,
-        "communities_web_enable_tweet_community_results_fetch": true,
-        "c9s_tweet_anatomy_moderator_badge_enabled": true,
-        "articles_preview_enabled": true,
-        "responsive_web_edit_tweet_api_enabled": true,
-        "graphql_is_translatable_rweb_tweet_is_translatable_enabled": true,
-        "view_counts_everywhere_api_enabled": true,
-        "longform_notetweets_consumption_enabled": true,
-        "responsive_web_twitter_article_tweet_consumption_enabled": true,
-        "tweet_awards_web_tipping_enabled": false,
-        "creator_subscriptions_quote_tweet_preview_enabled": false,
-        "freedom_of_speech_not_reach_fetch_enabled": true,
-        "standardized_nudges_misinfo": true,
-        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled": true,
-        "rweb_video_timestamps_enabled": true,
-        "longform_notetweets_rich_text_read_enabled": true,
-        "longform_notetweets_inline_media_enabled": true,
-        "responsive_web_enhance_cards_enabled": false,
-    });
-
-    let url = format!(
-        "https://x.com/i/api/graphql/HJFjzBgCs16TqxewQOeLNg/HomeTimeline?variables={}&features={}",
-        urlencoding::encode(&variables.to_string()),
-        urlencoding::encode(&features.to_string())
-    );
+    let endpoint = client
+        .graphql_endpoint("HomeTimeline")
+        .with_variables(variables);
 
     let mut headers = HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
 
-    let (response, _) = request_api::<HomeTimelineResponse>(
-        &client.client,
-        &url,
-        headers,
-        Method::GET,
-        None,
-    ).await?;
+    let (response, _) = request_graphql::<HomeTimelineResponse>(client, &endpoint, headers).await?;
 
     let home = response
         .data.map(|data| data.home.home_timeline.instructions);
@@ -310,6 +310,7 @@ pub struct TimelineV1 {
 }
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum ParseTweetResult {
     Success { tweet: Tweet },
     Error { err: String },
@@ -227,12 +227,10 @@ pub fn parse_legacy_tweet(
         .map(|mentions| {
             mentions
                 .iter()
-                .filter_map(|m| {
-                    Some(Mention {
-                        id: m.id_str.clone().unwrap_or_default(),
-                        name: m.name.clone(),
-                        username: m.screen_name.clone(),
-                    })
+                .map(|m| Mention {
+                    id: m.id_str.clone().unwrap_or_default(),
+                    name: m.name.clone(),
+                    username: m.screen_name.clone(),
                 })
                 .collect()
         })
@@ -439,13 +437,7 @@ pub fn parse_timeline_tweets_v2(timeline: &TimelineV2) -> QueryTweetsResponse {
     for instruction in instructions {
         let entries = instruction
             .entries.as_deref()
-            .unwrap_or_else(|| {
-                instruction
-                    .entry
-                    .as_ref()
-                    .map(std::slice::from_ref)
-                    .unwrap_or_default()
-            });
+            .unwrap_or(instruction.entry.as_slice());
 
         for entry in entries {
             let content = match &entry.content {
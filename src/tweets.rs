@@ -1,12 +1,15 @@
 use crate::api::endpoints::Endpoints;
-use crate::api::requests::{request_api, request_multipart_api};
+use crate::api::features::{FeatureSet, QUOTE_TWEET};
+use crate::api::requests::{request_api, request_graphql, request_multipart_api, MultipartForm};
 use crate::error::{Result, TwitterError};
 use crate::models::tweets::Tweet;
 use crate::profile::get_user_id_by_screen_name;
+use crate::timeline::v2::parse_result;
 use crate::timeline::v2::parse_threaded_conversation;
 use crate::timeline::v2::parse_timeline_tweets_v2;
 use crate::timeline::v2::QueryTweetsResponse;
 use crate::timeline::v2::ThreadedConversation;
+use crate::timeline::v2::TweetResult;
 use reqwest::header::HeaderMap;
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
@@ -58,17 +61,11 @@ pub async fn fetch_tweets(
         variables["cursor"] = json!(cursor_val);
     }
 
-    let (value, _headers) = request_api(
-        &client.client,
-        "https://twitter.com/i/api/graphql/YNXM2DGuE2Sff6a2JD3Ztw/UserTweets",
-        headers,
-        Method::GET,
-        Some(json!({
-            "variables": variables,
-            "features": get_default_features()
-        })),
-    )
-    .await?;
+    let endpoint = client
+        .graphql_endpoint("UserTweets")
+        .with_variables(variables);
+
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
     Ok(value)
 }
@@ -84,10 +81,10 @@ pub async fn fetch_tweets_and_replies(
 
     let user_id = get_user_id_by_screen_name(client, username).await?;
 
-    let endpoint = Endpoints::user_tweets_and_replies(&user_id, max_tweets.min(40), cursor);
+    let endpoint =
+        Endpoints::user_tweets_and_replies(client, &user_id, max_tweets.min(40), cursor);
 
-    let (value, _headers) =
-        request_api(&client.client, &endpoint.to_request_url(), headers, Method::GET, None).await?;
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
     let parsed_response = parse_timeline_tweets_v2(&value);
     Ok(parsed_response)
@@ -102,10 +99,10 @@ pub async fn fetch_tweets_and_replies_by_user_id(
     let mut headers = HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
 
-    let endpoint = Endpoints::user_tweets_and_replies(user_id, max_tweets.min(40), cursor);
+    let endpoint =
+        Endpoints::user_tweets_and_replies(client, user_id, max_tweets.min(40), cursor);
 
-    let (value, _headers) =
-        request_api(&client.client, &endpoint.to_request_url(), headers, Method::GET, None).await?;
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
     let parsed_response = parse_timeline_tweets_v2(&value);
     Ok(parsed_response)
@@ -129,17 +126,11 @@ pub async fn fetch_list_tweets(
         variables["cursor"] = json!(cursor_val);
     }
 
-    let (value, _headers) = request_api(
-        &client.client,
-        "https://twitter.com/i/api/graphql/LFKj1wqHNTsEJ4Oq7TzaNA/ListLatestTweetsTimeline",
-        headers,
-        Method::GET,
-        Some(json!({
-            "variables": variables,
-            "features": get_default_features()
-        })),
-    )
-    .await?;
+    let endpoint = client
+        .graphql_endpoint("ListLatestTweetsTimeline")
+        .with_variables(variables);
+
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
     Ok(value)
 }
@@ -178,17 +169,12 @@ pub async fn create_quote_tweet(
         variables["media"]["media_entities"] = json!(media_entities);
     }
 
-    let (value, _headers) = request_api(
-        &client.client,
-        "https://twitter.com/i/api/graphql/a1p9RWpkYKBjWv_I3WzS-A/CreateTweet",
-        headers,
-        Method::POST,
-        Some(json!({
-            "variables": variables,
-            "features": create_quote_tweet_features()
-        })),
-    )
-    .await?;
+    let endpoint = client
+        .graphql_endpoint("CreateTweet")
+        .with_variables(variables)
+        .with_merged_features(&FeatureSet::from_pairs(QUOTE_TWEET));
+
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
     Ok(value)
 }
@@ -197,18 +183,13 @@ pub async fn like_tweet(client: &TwitterClient, tweet_id: &str) -> Result<Value>
     let mut headers = HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
 
-    let (value, _headers) = request_api(
-        &client.client,
-        "https://twitter.com/i/api/graphql/lI07N6Otwv1PhnEgXILM7A/FavoriteTweet",
-        headers,
-        Method::POST,
-        Some(json!({
-            "variables": {
-                "tweet_id": tweet_id
-            }
-        })),
-    )
-    .await?;
+    let endpoint = client
+        .graphql_endpoint("FavoriteTweet")
+        .with_variables(json!({
+            "tweet_id": tweet_id
+        }));
+
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
     Ok(value)
 }
@@ -217,19 +198,14 @@ pub async fn retweet(client: &TwitterClient, tweet_id: &str) -> Result<Value> {
     let mut headers = HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
 
-    let (value, _headers) = request_api(
-        &client.client,
-        "https://twitter.com/i/api/graphql/ojPdsZsimiJrUGLR1sjUtA/CreateRetweet",
-        headers,
-        Method::POST,
-        Some(json!({
-            "variables": {
-                "tweet_id": tweet_id,
-                "dark_request": false
-            }
-        })),
-    )
-    .await?;
+    let endpoint = client
+        .graphql_endpoint("CreateRetweet")
+        .with_variables(json!({
+            "tweet_id": tweet_id,
+            "dark_request": false
+        }));
+
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
     Ok(value)
 }
@@ -269,17 +245,11 @@ pub async fn create_long_tweet(
             .collect::<Vec<_>>());
     }
 
-    let (value, _headers) = request_api(
-        &client.client,
-        "https://twitter.com/i/api/graphql/YNXM2DGuE2Sff6a2JD3Ztw/CreateNoteTweet",
-        headers,
-        Method::POST,
-        Some(json!({
-            "variables": variables,
-            "features": get_long_tweet_features()
-        })),
-    )
-    .await?;
+    let endpoint = client
+        .graphql_endpoint("CreateNoteTweet")
+        .with_variables(variables);
+
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
     Ok(value)
 }
@@ -303,17 +273,11 @@ pub async fn fetch_liked_tweets(
         variables["cursor"] = json!(cursor_val);
     }
 
-    let (value, _headers) = request_api(
-        &client.client,
-        "https://twitter.com/i/api/graphql/YlkSUg4Czo2Zx7yRqpwDow/Likes",
-        headers,
-        Method::GET,
-        Some(json!({
-            "variables": variables,
-            "features": get_default_features()
-        })),
-    )
-    .await?;
+    let endpoint = client
+        .graphql_endpoint("Likes")
+        .with_variables(variables);
+
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
     Ok(value)
 }
@@ -326,7 +290,7 @@ pub async fn upload_media(
     let mut headers = HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
 
-    let upload_url = "https://upload.twitter.com/1.1/media/upload.json";
+    let upload_url = client.hosts.upload_url("1.1/media/upload.json");
 
     // Check if media is video
     let is_video = media_type.starts_with("video/");
@@ -336,10 +300,9 @@ pub async fn upload_media(
         upload_video_in_chunks(client, file_data, media_type, headers).await
     } else {
         // Handle image upload directly
-        let form = reqwest::multipart::Form::new()
-            .part("media", reqwest::multipart::Part::bytes(file_data));
+        let form = MultipartForm::new().bytes("media", file_data);
 
-        let (response, _) = request_multipart_api::<Value>(&client.client, upload_url, headers, form).await?;
+        let (response, _) = request_multipart_api::<Value>(client, &upload_url, headers, form).await?;
 
         response["media_id_string"]
             .as_str()
@@ -354,12 +317,12 @@ async fn upload_video_in_chunks(
     media_type: &str,
     headers: HeaderMap,
 ) -> Result<String> {
-    let upload_url = "https://upload.twitter.com/1.1/media/upload.json";
+    let upload_url = client.hosts.upload_url("1.1/media/upload.json");
 
     // INIT command
     let (init_response, _) = request_api::<Value>(
-        &client.client,
-        upload_url,
+        client,
+        &upload_url,
         headers.clone(),
         Method::POST,
         Some(json!({
@@ -377,23 +340,19 @@ async fn upload_video_in_chunks(
 
     // APPEND command - upload in chunks
     let chunk_size = 5 * 1024 * 1024; // 5MB chunks
-    let mut segment_index = 0;
-
-    for chunk in file_data.chunks(chunk_size) {
-        let form = reqwest::multipart::Form::new()
+    for (segment_index, chunk) in file_data.chunks(chunk_size).enumerate() {
+        let form = MultipartForm::new()
             .text("command", "APPEND")
             .text("media_id", media_id.clone())
             .text("segment_index", segment_index.to_string())
-            .part("media", reqwest::multipart::Part::bytes(chunk.to_vec()));
+            .bytes("media", chunk.to_vec());
 
-        let (_, _) = request_multipart_api::<Value>(&client.client, upload_url, headers.clone(), form).await?;
-
-        segment_index += 1;
+        let (_, _) = request_multipart_api::<Value>(client, &upload_url, headers.clone(), form).await?;
     }
 
     // FINALIZE command
     let (finalize_response, _) = request_api::<Value>(
-        &client.client,
+        client,
         &format!("{}?command=FINALIZE&media_id={}", upload_url, media_id),
         headers.clone(),
         Method::POST,
@@ -410,14 +369,14 @@ async fn upload_video_in_chunks(
 }
 
 async fn check_upload_status(client: &TwitterClient, media_id: &str, headers: &HeaderMap) -> Result<()> {
-    let upload_url = "https://upload.twitter.com/1.1/media/upload.json";
+    let upload_url = client.hosts.upload_url("1.1/media/upload.json");
 
     for _ in 0..20 {
         // Maximum 20 attempts
         tokio::time::sleep(tokio::time::Duration::from_secs(5)).await; // Wait 5 seconds
 
         let (status_response, _) = request_api::<Value>(
-            &client.client,
+            client,
             &format!("{}?command=STATUS&media_id={}", upload_url, media_id),
             headers.clone(),
             Method::GET,
@@ -440,123 +399,36 @@ async fn check_upload_status(client: &TwitterClient, media_id: &str, headers: &H
 pub async fn get_tweet(client: &TwitterClient, id: &str) -> Result<Tweet> {
     let mut headers = HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
-    let tweet_detail_request = Endpoints::tweet_detail(id);
-    let url = tweet_detail_request.to_request_url();
+    let tweet_detail_request = Endpoints::tweet_detail(client, id);
 
-    let (response, _) = request_api::<Value>(&client.client, &url, headers, Method::GET, None).await?;
+    let (response, _) = request_graphql::<Value>(client, &tweet_detail_request, headers).await?;
     let data = response.clone();
     let conversation: ThreadedConversation = serde_json::from_value(data)?;
     let tweets = parse_threaded_conversation(&conversation);
     tweets.into_iter().next().ok_or_else(|| TwitterError::Api("No tweets found".into()))
 }
 
-fn create_tweet_features() -> Value {
-    json!({
-        "interactive_text_enabled": true,
-        "longform_notetweets_inline_media_enabled": false,
-        "responsive_web_text_conversations_enabled": false,
-        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled": false,
-        "vibe_api_enabled": false,
-        "rweb_lists_timeline_redesign_enabled": true,
-        "responsive_web_graphql_exclude_directive_enabled": true,
-        "verified_phone_label_enabled": false,
-        "creator_subscriptions_tweet_preview_api_enabled": true,
-        "responsive_web_graphql_timeline_navigation_enabled": true,
-        "responsive_web_graphql_skip_user_profile_image_extensions_enabled": false,
-        "tweetypie_unmention_optimization_enabled": true,
-        "responsive_web_edit_tweet_api_enabled": true,
-        "graphql_is_translatable_rweb_tweet_is_translatable_enabled": true,
-        "view_counts_everywhere_api_enabled": true,
-        "longform_notetweets_consumption_enabled": true,
-        "tweet_awards_web_tipping_enabled": false,
-        "freedom_of_speech_not_reach_fetch_enabled": true,
-        "standardized_nudges_misinfo": true,
-        "longform_notetweets_rich_text_read_enabled": true,
-        "responsive_web_enhance_cards_enabled": false,
-        "subscriptions_verification_info_enabled": true,
-        "subscriptions_verification_info_reason_enabled": true,
-        "subscriptions_verification_info_verified_since_enabled": true,
-        "super_follow_badge_privacy_enabled": false,
-        "super_follow_exclusive_tweet_notifications_enabled": false,
-        "super_follow_tweet_api_enabled": false,
-        "super_follow_user_api_enabled": false,
-        "android_graphql_skip_api_media_color_palette": false,
-        "creator_subscriptions_subscription_count_enabled": false,
-        "blue_business_profile_image_shape_enabled": false,
-        "unified_cards_ad_metadata_container_dynamic_card_content_query_enabled": false,
-        "rweb_video_timestamps_enabled": false,
-        "c9s_tweet_anatomy_moderator_badge_enabled": false,
-        "responsive_web_twitter_article_tweet_consumption_enabled": false
-    })
-}
-
-fn get_default_features() -> Value {
-    json!({
-        "interactive_text_enabled": true,
-        "longform_notetweets_inline_media_enabled": false,
-        "responsive_web_text_conversations_enabled": false,
-        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled": false,
-        "vibe_api_enabled": false,
-        "rweb_lists_timeline_redesign_enabled": true,
-        "responsive_web_graphql_exclude_directive_enabled": true,
-        "verified_phone_label_enabled": false,
-        "creator_subscriptions_tweet_preview_api_enabled": true,
-        "responsive_web_graphql_timeline_navigation_enabled": true,
-        "responsive_web_graphql_skip_user_profile_image_extensions_enabled": false,
-        "tweetypie_unmention_optimization_enabled": true,
-        "responsive_web_edit_tweet_api_enabled": true,
-        "graphql_is_translatable_rweb_tweet_is_translatable_enabled": true,
-        "view_counts_everywhere_api_enabled": true,
-        "longform_notetweets_consumption_enabled": true,
-        "tweet_awards_web_tipping_enabled": false,
-        "freedom_of_speech_not_reach_fetch_enabled": true,
-        "standardized_nudges_misinfo": true,
-        "longform_notetweets_rich_text_read_enabled": true,
-        "responsive_web_enhance_cards_enabled": false,
-        "subscriptions_verification_info_enabled": true,
-        "subscriptions_verification_info_reason_enabled": true,
-        "subscriptions_verification_info_verified_since_enabled": true,
-        "super_follow_badge_privacy_enabled": false,
-        "super_follow_exclusive_tweet_notifications_enabled": false,
-        "super_follow_tweet_api_enabled": false,
-        "super_follow_user_api_enabled": false,
-        "android_graphql_skip_api_media_color_palette": false,
-        "creator_subscriptions_subscription_count_enabled": false,
-        "blue_business_profile_image_shape_enabled": false,
-        "unified_cards_ad_metadata_container_dynamic_card_content_query_enabled": false,
-        "rweb_video_timestamps_enabled": true,
-        "c9s_tweet_anatomy_moderator_badge_enabled": true,
-        "responsive_web_twitter_article_tweet_consumption_enabled": false,
-        "creator_subscriptions_quote_tweet_preview_enabled": false,
-        "profile_label_improvements_pcf_label_in_post_enabled": false,
-        "rweb_tipjar_consumption_enabled": true,
-        "articles_preview_enabled": true
-    })
-}
-
-// Helper function for long tweet features
-fn get_long_tweet_features() -> Value {
-    json!({
-        "premium_content_api_read_enabled": false,
-        "communities_web_enable_tweet_community_results_fetch": true,
-        "c9s_tweet_anatomy_moderator_badge_enabled": true,
-        "responsive_web_grok_analyze_button_fetch_trends_enabled": true,
-        "responsive_web_edit_tweet_api_enabled": true,
-        "graphql_is_translatable_rweb_tweet_is_translatable_enabled": true,
-        "view_counts_everywhere_api_enabled": true,
-        "longform_notetweets_consumption_enabled": true,
-        "responsive_web_twitter_article_tweet_consumption_enabled": true,
-        "tweet_awards_web_tipping_enabled": false,
-        "longform_notetweets_rich_text_read_enabled": true,
-        "longform_notetweets_inline_media_enabled": true,
-        "responsive_web_graphql_exclude_directive_enabled": true,
-        "verified_phone_label_enabled": false,
-        "freedom_of_speech_not_reach_fetch_enabled": true,
-        "standardized_nudges_misinfo": true,
-        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled": true,
-        "responsive_web_graphql_timeline_navigation_enabled": true,
-        "responsive_web_enhance_cards_enabled": false
-    })
+/// Fetches a single tweet with `TweetResultByRestId`, which also works with a guest token.
+pub async fn get_tweet_by_rest_id(client: &TwitterClient, id: &str) -> Result<Tweet> {
+    let mut headers = HeaderMap::new();
+    client.auth.install_headers(&mut headers).await?;
+    let endpoint = Endpoints::tweet_by_rest_id(client, id);
+
+    let (response, _) = request_graphql::<Value>(client, &endpoint, headers).await?;
+    let result: TweetResult = serde_json::from_value(response["data"]["tweetResult"].clone())?;
+    let result = result
+        .result
+        .ok_or_else(|| TwitterError::Api("No tweets found".into()))?;
+    // Tweets with visibility restrictions wrap the tweet in `tweet`.
+    let result = result.tweet.as_deref().unwrap_or(&result);
+
+    let parsed = parse_result(result);
+    match parsed.tweet {
+        Some(tweet) if parsed.success => Ok(tweet),
+        _ => Err(parsed
+            .err
+            .unwrap_or_else(|| TwitterError::Api("No tweets found".into()))),
+    }
 }
 
 pub async fn create_tweet_request(
@@ -601,62 +473,15 @@ pub async fn create_tweet_request(
 
         variables["media"]["media_entities"] = json!(media_entities);
     }
-    let features = create_tweet_features();
     // Make the create tweet request
-    let (value, _headers) = request_api(
-        &client.client,
-        "https://twitter.com/i/api/graphql/a1p9RWpkYKBjWv_I3WzS-A/CreateTweet",
-        headers,
-        Method::POST,
-        Some(json!({
-            "variables": variables,
-            "features": features,
-            "fieldToggles": {}
-        })),
-    )
-    .await?;
+    let endpoint = client
+        .graphql_endpoint("CreateTweet")
+        .with_variables(variables)
+        .with_field_toggles(json!({}));
 
-    Ok(value)
-}
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
-fn create_quote_tweet_features() -> Value {
-    json!({
-        "interactive_text_enabled": true,
-        "longform_notetweets_inline_media_enabled": false,
-        "responsive_web_text_conversations_enabled": false,
-        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled": false,
-        "vibe_api_enabled": false,
-        "rweb_lists_timeline_redesign_enabled": true,
-        "responsive_web_graphql_exclude_directive_enabled": true,
-        "verified_phone_label_enabled": false,
-        "creator_subscriptions_tweet_preview_api_enabled": true,
-        "responsive_web_graphql_timeline_navigation_enabled": true,
-        "responsive_web_graphql_skip_user_profile_image_extensions_enabled": false,
-        "tweetypie_unmention_optimization_enabled": true,
-        "responsive_web_edit_tweet_api_enabled": true,
-        "graphql_is_translatable_rweb_tweet_is_translatable_enabled": true,
-        "view_counts_everywhere_api_enabled": true,
-        "longform_notetweets_consumption_enabled": true,
-        "tweet_awards_web_tipping_enabled": false,
-        "freedom_of_speech_not_reach_fetch_enabled": true,
-        "standardized_nudges_misinfo": true,
-        "longform_notetweets_rich_text_read_enabled": true,
-        "responsive_web_enhance_cards_enabled": false,
-        "subscriptions_verification_info_enabled": true,
-        "subscriptions_verification_info_reason_enabled": true,
-        "subscriptions_verification_info_verified_since_enabled": true,
-        "super_follow_badge_privacy_enabled": false,
-        "super_follow_exclusive_tweet_notifications_enabled": false,
-        "super_follow_tweet_api_enabled": false,
-        "super_follow_user_api_enabled": false,
-        "android_graphql_skip_api_media_color_palette": false,
-        "creator_subscriptions_subscription_count_enabled": false,
-        "blue_business_profile_image_shape_enabled": false,
-        "unified_cards_ad_metadata_container_dynamic_card_content_query_enabled": false,
-        "rweb_video_timestamps_enabled": true,
-        "c9s_tweet_anatomy_moderator_badge_enabled": true,
-        "responsive_web_twitter_article_tweet_consumption_enabled": false
-    })
+    Ok(value)
 }
 
 pub async fn fetch_user_tweets(
@@ -669,11 +494,101 @@ pub async fn fetch_user_tweets(
     let mut headers = HeaderMap::new();
     client.auth.install_headers(&mut headers).await?;
 
-    let endpoint = Endpoints::user_tweets(user_id, max_tweets.min(200), cursor);
+    let endpoint = Endpoints::user_tweets(client, user_id, max_tweets.min(200), cursor);
 
-    let (value, _headers) =
-        request_api(&client.client, &endpoint.to_request_url(), headers, Method::GET, None).await?;
+    let (value, _headers) = request_graphql(client, &endpoint, headers).await?;
 
     let parsed_response = parse_timeline_tweets_v2(&value);
     Ok(parsed_response)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::{MockServer, MOCK_GUEST_TOKEN, MOCK_MEDIA_ID};
+
+    #[tokio::test]
+    async fn test_fetch_user_tweets() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        let response = scraper.get_user_tweets("1000", 20, None).await.unwrap();
+
+        assert_eq!(response.tweets.len(), 2);
+        assert_eq!(response.tweets[0].id.as_deref(), Some("2000"));
+        assert_eq!(response.next.as_deref(), Some("DAABCgABGBOTTOM"));
+
+        let requests = server.requests_to("UserTweets");
+        let variables = requests[0].query_json("variables").unwrap();
+        assert_eq!(variables["userId"], "1000");
+    }
+
+    #[tokio::test]
+    async fn test_get_tweet() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        let tweet = scraper.get_tweet("2000").await.unwrap();
+
+        assert_eq!(tweet.id.as_deref(), Some("2000"));
+        assert_eq!(tweet.username.as_deref(), Some("mockuser"));
+    }
+
+    #[tokio::test]
+    async fn test_get_tweet_by_rest_id_as_guest() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.guest_scraper().await.unwrap();
+
+        let tweet = scraper.get_tweet_by_rest_id("2000").await.unwrap();
+
+        assert_eq!(tweet.id.as_deref(), Some("2000"));
+        assert_eq!(tweet.username.as_deref(), Some("mockuser"));
+        assert_eq!(tweet.views, Some(1024));
+
+        let requests = server.requests_to("TweetResultByRestId");
+        assert_eq!(requests[0].query_json("variables").unwrap()["tweetId"], "2000");
+        assert_eq!(requests[0].header("x-guest-token"), Some(MOCK_GUEST_TOKEN));
+    }
+
+    #[tokio::test]
+    async fn test_quote_tweet_keeps_registered_features() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+        scraper
+            .operations()
+            .load_json(r#"{"CreateTweet": {"features": {"new_switch_enabled": true}}}"#)
+            .unwrap();
+
+        scraper
+            .send_quote_tweet("quoting", "2000", None)
+            .await
+            .unwrap();
+
+        let requests = server.requests_to("CreateTweet");
+        let features = &requests[0].body_json().unwrap()["features"];
+        assert_eq!(features["new_switch_enabled"], true);
+        assert_eq!(features["rweb_video_timestamps_enabled"], true);
+        assert_eq!(features["c9s_tweet_anatomy_moderator_badge_enabled"], true);
+        assert_eq!(features["interactive_text_enabled"], true);
+    }
+
+    #[tokio::test]
+    async fn test_send_tweet_with_media() {
+        let server = MockServer::start().await.unwrap();
+        let scraper = server.scraper().await.unwrap();
+
+        let media = vec![(vec![0u8; 16], "image/png".to_string())];
+        scraper
+            .send_tweet("hello from the mock", None, Some(media))
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests_to("/1.1/media/upload.json").len(), 1);
+        let requests = server.requests_to("CreateTweet");
+        let body = requests[0].body_json().unwrap();
+        assert_eq!(body["variables"]["tweet_text"], "hello from the mock");
+        assert_eq!(
+            body["variables"]["media"]["media_entities"][0]["media_id"],
+            MOCK_MEDIA_ID
+        );
+    }
+}